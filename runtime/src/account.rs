@@ -0,0 +1,142 @@
+//! Checks on `AccountInfo`s before their data is decoded
+
+use crate::solana_program::{account_info::AccountInfo, pubkey::Pubkey};
+
+use crate::{ProgramAccount, ValidationError};
+
+/// Check that `account_info` is owned by `program_id` and its data starts with
+/// `T::DISCRIMINATOR`
+pub fn validate_account_info<T: ProgramAccount>(
+    account_info: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<(), ValidationError> {
+    if account_info.owner != program_id {
+        return Err(ValidationError::InvalidOwner {
+            expected: *program_id,
+            actual: *account_info.owner,
+        });
+    }
+
+    let data = account_info.data.borrow();
+    let disc_len = T::DISCRIMINATOR.len();
+    if data.len() < disc_len {
+        return Err(ValidationError::DataTooShort {
+            expected: disc_len,
+            actual: data.len(),
+        });
+    }
+    if data[..disc_len] != *T::DISCRIMINATOR {
+        return Err(ValidationError::InvalidDiscriminator {
+            expected: T::DISCRIMINATOR.to_vec(),
+            actual: data[..disc_len].to_vec(),
+        });
+    }
+    Ok(())
+}
+
+/// Validate `account_info` like [`validate_account_info`] and decode its data
+pub fn try_from_account_info<T: ProgramAccount>(
+    account_info: &AccountInfo,
+    program_id: &Pubkey,
+) -> Result<T, ValidationError> {
+    validate_account_info::<T>(account_info, program_id)?;
+    let data = account_info.data.borrow();
+    T::decode(&data).map_err(|e| ValidationError::DeserializationError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    #[derive(Debug, PartialEq, BorshSerialize, BorshDeserialize)]
+    struct Counter {
+        count: u64,
+    }
+
+    impl ProgramAccount for Counter {
+        const DISCRIMINATOR: &'static [u8] = &[1, 2, 3, 4, 5, 6, 7, 8];
+        const NAME: &'static str = "Counter";
+        const PROGRAM_ID: Option<Pubkey> = Some(Pubkey::new_from_array([5; 32]));
+
+        fn decode(data: &[u8]) -> std::io::Result<Self> {
+            crate::try_from_slice_with_discriminator(Self::DISCRIMINATOR, data)
+        }
+
+        fn encode(&self) -> std::io::Result<Vec<u8>> {
+            let mut data = Vec::new();
+            crate::serialize_with_discriminator(Self::DISCRIMINATOR, self, &mut data)?;
+            Ok(data)
+        }
+    }
+
+    #[test]
+    fn test_try_from_account_info() {
+        let key = Pubkey::new_unique();
+        let owner = Counter::PROGRAM_ID.unwrap();
+        let mut lamports = 0;
+        let mut data = Counter { count: 3 }.encode().unwrap();
+        let info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+
+        assert_eq!(
+            try_from_account_info::<Counter>(&info, &owner).unwrap(),
+            Counter { count: 3 }
+        );
+        assert!(matches!(
+            validate_account_info::<Counter>(&info, &Pubkey::new_unique()),
+            Err(ValidationError::InvalidOwner { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_account_info_data() {
+        let key = Pubkey::new_unique();
+        let owner = Counter::PROGRAM_ID.unwrap();
+        let mut lamports = 0;
+
+        let mut short = vec![1, 2, 3];
+        let info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut short,
+            &owner,
+            false,
+            0,
+        );
+        assert!(matches!(
+            validate_account_info::<Counter>(&info, &owner),
+            Err(ValidationError::DataTooShort {
+                expected: 8,
+                actual: 3
+            })
+        ));
+
+        let mut lamports = 0;
+        let mut other = vec![9; 16];
+        let info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut other,
+            &owner,
+            false,
+            0,
+        );
+        assert!(matches!(
+            validate_account_info::<Counter>(&info, &owner),
+            Err(ValidationError::InvalidDiscriminator { .. })
+        ));
+    }
+}
@@ -0,0 +1,76 @@
+//! Helpers behind the `anchor_lang` trait impls of crates generated with `--anchor`
+//!
+//! They follow what `#[account]` generates: the discriminator is checked with Anchor's
+//! error codes, and bytes after the encoded value (unused account space) are ignored.
+
+use anchor_lang::error::ErrorCode;
+
+/// Check that account data starts with `discriminator`
+pub fn check_account_discriminator(discriminator: &[u8], data: &[u8]) -> anchor_lang::Result<()> {
+    if data.len() < discriminator.len() {
+        return Err(ErrorCode::AccountDiscriminatorNotFound.into());
+    }
+    if data[..discriminator.len()] != *discriminator {
+        return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+    }
+    Ok(())
+}
+
+/// Borsh-decode account data, skipping the first `discriminator_len` bytes unchecked
+pub fn try_deserialize_unchecked<T: borsh::BorshDeserialize>(
+    discriminator_len: usize,
+    data: &[u8],
+) -> anchor_lang::Result<T> {
+    let mut body = data
+        .get(discriminator_len..)
+        .ok_or(ErrorCode::AccountDiscriminatorNotFound)?;
+    T::deserialize(&mut body).map_err(|_| ErrorCode::AccountDidNotDeserialize.into())
+}
+
+/// Copy a `Pod` value out of account data, skipping the first `discriminator_len`
+/// bytes unchecked
+pub fn pod_try_deserialize_unchecked<T: bytemuck::Pod>(
+    discriminator_len: usize,
+    data: &[u8],
+) -> anchor_lang::Result<T> {
+    let body = data
+        .get(discriminator_len..discriminator_len + std::mem::size_of::<T>())
+        .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+    bytemuck::try_pod_read_unaligned(body).map_err(|_| ErrorCode::AccountDidNotDeserialize.into())
+}
+
+/// Map an encoding failure to Anchor's `AccountDidNotSerialize`
+pub fn serialize_error(_: std::io::Error) -> anchor_lang::error::Error {
+    ErrorCode::AccountDidNotSerialize.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISC: [u8; 2] = [7, 9];
+
+    #[test]
+    fn test_check_account_discriminator() {
+        assert!(check_account_discriminator(&DISC, &[7, 9, 1]).is_ok());
+        assert_eq!(
+            check_account_discriminator(&DISC, &[7]).unwrap_err(),
+            ErrorCode::AccountDiscriminatorNotFound.into()
+        );
+        assert_eq!(
+            check_account_discriminator(&DISC, &[7, 8, 1]).unwrap_err(),
+            ErrorCode::AccountDiscriminatorMismatch.into()
+        );
+    }
+
+    #[test]
+    fn test_deserialize_ignores_unused_space() {
+        let data = [7, 9, 42, 0, 0, 0, 0, 0];
+        assert_eq!(try_deserialize_unchecked::<u32>(2, &data).unwrap(), 42);
+        assert_eq!(pod_try_deserialize_unchecked::<u32>(2, &data).unwrap(), 42);
+        assert_eq!(
+            pod_try_deserialize_unchecked::<u64>(2, &data).unwrap_err(),
+            ErrorCode::AccountDidNotDeserialize.into()
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Encoding and decoding of discriminator-prefixed data
+
+use std::io;
+
+/// The bytes after `discriminator`, or an `InvalidData` error if `data` doesn't start with it
+pub fn strip_discriminator<'a>(discriminator: &[u8], data: &'a [u8]) -> io::Result<&'a [u8]> {
+    if data.len() < discriminator.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Data too short for discriminator",
+        ));
+    }
+    let (actual, rest) = data.split_at(discriminator.len());
+    if actual != discriminator {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Invalid discriminator. Expected: {:?}, Actual: {:?}",
+                discriminator, actual
+            ),
+        ));
+    }
+    Ok(rest)
+}
+
+/// Borsh-decode `data` after checking its discriminator; trailing bytes are an error
+pub fn try_from_slice_with_discriminator<T: borsh::BorshDeserialize>(
+    discriminator: &[u8],
+    data: &[u8],
+) -> io::Result<T> {
+    T::try_from_slice(strip_discriminator(discriminator, data)?)
+}
+
+/// Borsh-decode a value from the front of `buf` after checking its discriminator,
+/// advancing `buf` past both
+pub fn deserialize_with_discriminator<T: borsh::BorshDeserialize>(
+    discriminator: &[u8],
+    buf: &mut &[u8],
+) -> io::Result<T> {
+    *buf = strip_discriminator(discriminator, buf)?;
+    T::deserialize(buf)
+}
+
+/// Write `discriminator` followed by the Borsh encoding of `value`
+pub fn serialize_with_discriminator<T: borsh::BorshSerialize + ?Sized, W: io::Write>(
+    discriminator: &[u8],
+    value: &T,
+    writer: &mut W,
+) -> io::Result<()> {
+    writer.write_all(discriminator)?;
+    value.serialize(writer)
+}
+
+/// Copy a `Pod` value out of `data` after checking its discriminator
+///
+/// The bytes after the discriminator must be exactly `size_of::<T>()` long and
+/// suitably aligned.
+pub fn pod_from_slice_with_discriminator<T: bytemuck::Pod>(
+    discriminator: &[u8],
+    data: &[u8],
+) -> io::Result<T> {
+    bytemuck::try_from_bytes::<T>(strip_discriminator(discriminator, data)?)
+        .copied()
+        .map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Bytemuck conversion error: {:?}", e),
+            )
+        })
+}
+
+/// Write `discriminator` followed by the raw bytes of a `Pod` value
+pub fn pod_serialize_with_discriminator<T: bytemuck::Pod, W: io::Write>(
+    discriminator: &[u8],
+    value: &T,
+    writer: &mut W,
+) -> io::Result<()> {
+    writer.write_all(discriminator)?;
+    writer.write_all(bytemuck::bytes_of(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISC: [u8; 2] = [7, 9];
+
+    #[test]
+    fn test_strip_discriminator() {
+        assert_eq!(strip_discriminator(&DISC, &[7, 9, 1]).unwrap(), [1]);
+
+        let err = strip_discriminator(&DISC, &[7]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("too short"));
+
+        let err = strip_discriminator(&DISC, &[7, 8, 1]).unwrap_err();
+        assert!(err.to_string().contains("Expected: [7, 9], Actual: [7, 8]"));
+    }
+
+    #[test]
+    fn test_borsh_roundtrip() {
+        let mut data = Vec::new();
+        serialize_with_discriminator(&DISC, &42u32, &mut data).unwrap();
+        assert_eq!(data, [7, 9, 42, 0, 0, 0]);
+        assert_eq!(
+            try_from_slice_with_discriminator::<u32>(&DISC, &data).unwrap(),
+            42
+        );
+
+        // Streaming decode leaves what follows for the caller
+        data.push(5);
+        assert!(try_from_slice_with_discriminator::<u32>(&DISC, &data).is_err());
+        let mut buf = &data[..];
+        assert_eq!(
+            deserialize_with_discriminator::<u32>(&DISC, &mut buf).unwrap(),
+            42
+        );
+        assert_eq!(buf, [5]);
+    }
+
+    #[test]
+    fn test_pod_roundtrip() {
+        let mut data = Vec::new();
+        pod_serialize_with_discriminator(&[1u8], &[3u8, 4u8], &mut data).unwrap();
+        assert_eq!(data, [1, 3, 4]);
+        assert_eq!(
+            pod_from_slice_with_discriminator::<[u8; 2]>(&[1], &data).unwrap(),
+            [3, 4]
+        );
+        assert!(pod_from_slice_with_discriminator::<[u8; 3]>(&[1], &data).is_err());
+    }
+}
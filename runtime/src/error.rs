@@ -0,0 +1,27 @@
+//! Error types shared by all generated crates
+
+use crate::solana_program::pubkey::Pubkey;
+
+/// Error type for account validation
+#[derive(Debug, thiserror::Error)]
+pub enum ValidationError {
+    #[error("Invalid account owner. Expected: {expected}, Actual: {actual}")]
+    InvalidOwner { expected: Pubkey, actual: Pubkey },
+    #[error("Account data too short. Expected at least {expected} bytes, got {actual}")]
+    DataTooShort { expected: usize, actual: usize },
+    #[error("Invalid discriminator. Expected: {expected:?}, Actual: {actual:?}")]
+    InvalidDiscriminator { expected: Vec<u8>, actual: Vec<u8> },
+    #[error("Deserialization error: {0}")]
+    DeserializationError(String),
+}
+
+/// Error type for event parsing
+#[derive(Debug, thiserror::Error)]
+pub enum EventParseError {
+    #[error("Data too short for discriminator")]
+    DataTooShort,
+    #[error("Unknown event discriminator: {0:?}")]
+    UnknownDiscriminator([u8; 8]),
+    #[error("Deserialization error: {0}")]
+    DeserializationError(String),
+}
@@ -0,0 +1,39 @@
+//! Support code shared by the crates `solana-idl-codegen` generates
+//!
+//! Generated accounts, instructions and events keep their inherent methods
+//! (`try_from_slice_with_discriminator`, `deserialize`, `validate_account_info`, ...),
+//! but their bodies call the helpers here. That keeps the generated crates small and
+//! gives every program crate the same [`ValidationError`] and [`EventParseError`].
+//!
+//! The `anchor` feature adds [`anchor`], used by crates generated with `--anchor`.
+
+#[cfg(not(any(feature = "solana-program", feature = "anchor")))]
+compile_error!("enable the `solana-program` feature (the default) or `anchor`");
+
+mod account;
+#[cfg(feature = "anchor")]
+pub mod anchor;
+mod discriminator;
+mod error;
+mod return_data;
+mod traits;
+
+pub use account::{try_from_account_info, validate_account_info};
+pub use discriminator::{
+    deserialize_with_discriminator, pod_from_slice_with_discriminator,
+    pod_serialize_with_discriminator, serialize_with_discriminator, strip_discriminator,
+    try_from_slice_with_discriminator,
+};
+pub use error::{EventParseError, ValidationError};
+pub use return_data::decode_return_data;
+pub use traits::{ProgramAccount, ProgramEvent, ProgramInstruction};
+
+#[cfg(feature = "anchor")]
+pub use anchor_lang::solana_program;
+/// The Solana crate behind every `Pubkey` and `AccountInfo` here
+///
+/// Generated crates have no `solana-program` dependency of their own and use this
+/// instead, so their types are always the runtime's. With `anchor` it is anchor-lang's,
+/// so the same types also work with Anchor's traits.
+#[cfg(not(feature = "anchor"))]
+pub use solana_program;
@@ -0,0 +1,30 @@
+//! Decoding of instruction return data
+
+use std::io::{self, Read};
+
+/// Borsh-decode the return data of an instruction
+///
+/// The runtime trims trailing zero bytes from return data (`5u64` arrives as `[5]`),
+/// so the missing tail is read back as zeros.
+pub fn decode_return_data<T: borsh::BorshDeserialize>(data: &[u8]) -> io::Result<T> {
+    T::deserialize_reader(&mut data.chain(io::repeat(0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_trimmed_return_data() {
+        assert_eq!(decode_return_data::<u64>(&[5]).unwrap(), 5);
+        assert_eq!(decode_return_data::<u64>(&[]).unwrap(), 0);
+        assert_eq!(
+            decode_return_data::<(u8, Option<u32>)>(&[1, 1, 2]).unwrap(),
+            (1, Some(2))
+        );
+        assert_eq!(
+            decode_return_data::<u64>(&300u64.to_le_bytes()).unwrap(),
+            300
+        );
+    }
+}
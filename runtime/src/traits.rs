@@ -0,0 +1,55 @@
+//! Traits every generated instruction, account and event implements
+//!
+//! They let indexers and tests handle any item of any generated program generically,
+//! e.g. `fn decode_all<T: ProgramAccount>(accounts: &[Vec<u8>])`. `PROGRAM_ID` is
+//! `None` when the IDL doesn't declare the program's address.
+
+use crate::solana_program::pubkey::Pubkey;
+
+/// Instruction data of a program: the discriminator followed by the Borsh args
+pub trait ProgramInstruction: Sized {
+    /// Bytes the instruction data starts with
+    const DISCRIMINATOR: &'static [u8];
+    /// Instruction name in the IDL
+    const NAME: &'static str;
+    /// Program that executes the instruction, if the IDL declares it
+    const PROGRAM_ID: Option<Pubkey>;
+
+    /// Decode instruction data, checking the discriminator
+    fn decode(data: &[u8]) -> std::io::Result<Self>;
+
+    /// Encode as instruction data, discriminator first
+    fn encode(&self) -> std::io::Result<Vec<u8>>;
+}
+
+/// Account owned by a program: the discriminator followed by the account data
+pub trait ProgramAccount: Sized {
+    /// Bytes the account data starts with
+    const DISCRIMINATOR: &'static [u8];
+    /// Account name in the IDL
+    const NAME: &'static str;
+    /// Program that owns the account, if the IDL declares it
+    const PROGRAM_ID: Option<Pubkey>;
+
+    /// Decode account data, checking the discriminator
+    fn decode(data: &[u8]) -> std::io::Result<Self>;
+
+    /// Encode as account data, discriminator first
+    fn encode(&self) -> std::io::Result<Vec<u8>>;
+}
+
+/// Event emitted by a program: the discriminator followed by the Borsh fields
+pub trait ProgramEvent: Sized {
+    /// Bytes the event data starts with
+    const DISCRIMINATOR: &'static [u8];
+    /// Event name in the IDL
+    const NAME: &'static str;
+    /// Program that emits the event, if the IDL declares it
+    const PROGRAM_ID: Option<Pubkey>;
+
+    /// Decode event data, checking the discriminator
+    fn decode(data: &[u8]) -> std::io::Result<Self>;
+
+    /// Encode as event data, discriminator first
+    fn encode(&self) -> std::io::Result<Vec<u8>>;
+}
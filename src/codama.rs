@@ -0,0 +1,1048 @@
+//! Codama (formerly Kinobi) IDL front-end
+//!
+//! Codama describes a program as a tree of `*Node` objects under a `rootNode`.
+//! This module reads that tree and lowers it into the Anchor-shaped [`Idl`] model, so
+//! the generator doesn't need to know which format the input was written in.
+//!
+//! Type nodes are lowered only when they have a Borsh-compatible layout (`u32`
+//! length prefixes, `u8` option and enum tags, little-endian numbers). Anything else
+//! is rejected with the JSON path of the offending node rather than silently
+//! generating bindings with the wrong layout.
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+use heck::ToPascalCase;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::idl::{
+    Account, AccountArg, Arg, ArrayType, DefinedTypeOrString, EnumFields, EnumVariant, Error,
+    Field, Idl, IdlType, Instruction, Metadata, Pda, Program, Seed, StructFields, TypeDef,
+    TypeDefType,
+};
+
+/// Whether a parsed JSON document is a Codama `rootNode`
+pub fn is_root_node(value: &Value) -> bool {
+    value.get("kind").and_then(Value::as_str) == Some("rootNode")
+}
+
+/// Parse a Codama `rootNode` document and lower it into an [`Idl`]
+pub fn from_str(json: &str) -> Result<Idl> {
+    let root: RootNode = serde_json::from_str(json).context("Failed to parse Codama rootNode")?;
+    lower(&root.program)
+}
+
+#[derive(Debug, Deserialize)]
+struct RootNode {
+    program: ProgramNode,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProgramNode {
+    name: String,
+    public_key: String,
+    #[serde(default)]
+    version: Option<String>,
+    #[serde(default)]
+    origin: Option<String>,
+    #[serde(default)]
+    docs: Vec<String>,
+    #[serde(default)]
+    accounts: Vec<AccountNode>,
+    #[serde(default)]
+    instructions: Vec<InstructionNode>,
+    #[serde(default)]
+    defined_types: Vec<DefinedTypeNode>,
+    #[serde(default)]
+    pdas: Vec<PdaNode>,
+    #[serde(default)]
+    errors: Vec<ErrorNode>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccountNode {
+    name: String,
+    #[serde(default)]
+    docs: Vec<String>,
+    data: Value,
+    #[serde(default)]
+    discriminators: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InstructionNode {
+    name: String,
+    #[serde(default)]
+    docs: Vec<String>,
+    #[serde(default)]
+    accounts: Vec<InstructionAccountNode>,
+    #[serde(default)]
+    arguments: Vec<InstructionArgumentNode>,
+    #[serde(default)]
+    discriminators: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InstructionAccountNode {
+    name: String,
+    #[serde(default)]
+    is_writable: bool,
+    /// `true`, `false` or `"either"`
+    #[serde(default)]
+    is_signer: Value,
+    #[serde(default)]
+    is_optional: bool,
+    #[serde(default)]
+    docs: Vec<String>,
+    #[serde(default)]
+    default_value: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InstructionArgumentNode {
+    name: String,
+    #[serde(rename = "type")]
+    ty: Value,
+    #[serde(default)]
+    docs: Vec<String>,
+    #[serde(default)]
+    default_value: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DefinedTypeNode {
+    name: String,
+    #[serde(rename = "type")]
+    ty: Value,
+    #[serde(default)]
+    docs: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PdaNode {
+    name: String,
+    #[serde(default)]
+    seeds: Vec<Value>,
+    #[serde(default)]
+    program_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorNode {
+    name: String,
+    code: u32,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    docs: Vec<String>,
+}
+
+/// A struct field or instruction argument, before its discriminator is split off
+struct NamedValue {
+    name: String,
+    ty: Value,
+    docs: Vec<String>,
+    default_value: Option<Value>,
+}
+
+fn lower(program: &ProgramNode) -> Result<Idl> {
+    let types = program
+        .defined_types
+        .iter()
+        .map(|ty| {
+            let path = format!("definedTypes.{}", ty.name);
+            Ok(TypeDef {
+                name: type_name(&ty.name),
+                docs: docs(&ty.docs),
+                ty: lower_type_def(&ty.ty, &path)?,
+                serialization: None,
+                repr: None,
+                generics: None,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let accounts = program
+        .accounts
+        .iter()
+        .map(lower_account)
+        .collect::<Result<Vec<_>>>()?;
+
+    let instructions = program
+        .instructions
+        .iter()
+        .map(|ix| lower_instruction(ix, program))
+        .collect::<Result<Vec<_>>>()?;
+
+    let errors = program
+        .errors
+        .iter()
+        .map(|e| Error {
+            code: e.code,
+            name: e.name.clone(),
+            msg: e.message.clone(),
+            docs: docs(&e.docs),
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Idl {
+        address: Some(program.public_key.clone()),
+        version: None,
+        name: None,
+        metadata: Some(Metadata {
+            name: Some(program.name.clone()),
+            version: program.version.clone(),
+            spec: None,
+            description: (!program.docs.is_empty()).then(|| program.docs.join("\n")),
+            address: None,
+            origin: Some(
+                program
+                    .origin
+                    .clone()
+                    .unwrap_or_else(|| "codama".to_string()),
+            ),
+            deployments: None,
+        }),
+        instructions,
+        accounts: Some(accounts),
+        types: Some(types),
+        errors: (!errors.is_empty()).then_some(errors),
+        events: None,
+        constants: None,
+    })
+}
+
+fn lower_account(account: &AccountNode) -> Result<Account> {
+    let path = format!("accounts.{}", account.name);
+    let struct_node = expect_kind(&account.data, "structTypeNode", &format!("{}.data", path))?;
+    let mut fields = named_values(struct_node, &format!("{}.data", path))?;
+    let discriminator = lower_discriminators(&account.discriminators, &mut fields, &path)?;
+    let fields = fields
+        .into_iter()
+        .map(|f| lower_field(f, &path))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Account {
+        name: type_name(&account.name),
+        discriminator,
+        docs: docs(&account.docs),
+        ty: Some(TypeDefType::Struct {
+            fields: StructFields::Named(fields),
+        }),
+    })
+}
+
+fn lower_instruction(ix: &InstructionNode, program: &ProgramNode) -> Result<Instruction> {
+    let path = format!("instructions.{}", ix.name);
+    let mut arguments: Vec<NamedValue> = ix
+        .arguments
+        .iter()
+        .map(|a| NamedValue {
+            name: a.name.clone(),
+            ty: a.ty.clone(),
+            docs: a.docs.clone(),
+            default_value: a.default_value.clone(),
+        })
+        .collect();
+    let discriminator = lower_discriminators(&ix.discriminators, &mut arguments, &path)?;
+    if discriminator.is_none() && !ix.discriminators.is_empty() {
+        bail!(
+            "{}: only constant and field discriminators at offset 0 are supported for instructions",
+            path
+        );
+    }
+
+    let args = arguments
+        .into_iter()
+        .map(|a| {
+            let arg_path = format!("{}.arguments.{}", path, a.name);
+            Ok(Arg {
+                ty: lower_type(&a.ty, &arg_path)?,
+                docs: docs(&a.docs),
+                name: a.name,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let accounts = ix
+        .accounts
+        .iter()
+        .map(|account| {
+            let (address, pda) = match &account.default_value {
+                Some(value) => lower_account_default(value, ix, program),
+                None => (None, None),
+            };
+            AccountArg {
+                name: account.name.clone(),
+                docs: docs(&account.docs),
+                // "either" signers are left to the caller, so they aren't required here
+                signer: account.is_signer.as_bool().unwrap_or(false),
+                writable: account.is_writable,
+                pda,
+                address,
+                optional: account.is_optional.then_some(true),
+                accounts: None,
+                relations: None,
+            }
+        })
+        .collect();
+
+    Ok(Instruction {
+        name: ix.name.clone(),
+        docs: docs(&ix.docs),
+        discriminator,
+        accounts,
+        args,
+        discriminant: None,
+        returns: None,
+    })
+}
+
+/// Lower a type used as a defined type: structs and enums keep their shape, anything
+/// else becomes an alias
+fn lower_type_def(node: &Value, path: &str) -> Result<TypeDefType> {
+    match kind(node, path)? {
+        "structTypeNode" => Ok(TypeDefType::Struct {
+            fields: StructFields::Named(
+                named_values(node, path)?
+                    .into_iter()
+                    .map(|f| lower_field(f, path))
+                    .collect::<Result<_>>()?,
+            ),
+        }),
+        "tupleTypeNode" => Ok(TypeDefType::Struct {
+            fields: StructFields::Tuple(lower_tuple_items(node, path)?),
+        }),
+        "enumTypeNode" => lower_enum(node, path),
+        _ => Ok(TypeDefType::Alias {
+            alias: lower_type(node, path)?,
+        }),
+    }
+}
+
+fn lower_enum(node: &Value, path: &str) -> Result<TypeDefType> {
+    if let Some(size) = node.get("size") {
+        let format = number_format(size, &format!("{}.size", path))?;
+        if format != "u8" {
+            bail!("{}: enum tags must be u8 for Borsh, found {}", path, format);
+        }
+    }
+    let variants = node
+        .get("variants")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("{}: enumTypeNode without variants", path))?
+        .iter()
+        .map(|variant| {
+            let name = str_field(variant, "name", path)?;
+            let variant_path = format!("{}.{}", path, name);
+            let fields = match kind(variant, &variant_path)? {
+                "enumEmptyVariantTypeNode" => None,
+                "enumStructVariantTypeNode" => {
+                    let struct_node = variant
+                        .get("struct")
+                        .ok_or_else(|| anyhow!("{}: missing struct", variant_path))?;
+                    Some(EnumFields::Named(
+                        named_values(struct_node, &variant_path)?
+                            .into_iter()
+                            .map(|f| lower_field(f, &variant_path))
+                            .collect::<Result<_>>()?,
+                    ))
+                }
+                "enumTupleVariantTypeNode" => {
+                    let tuple = variant
+                        .get("tuple")
+                        .ok_or_else(|| anyhow!("{}: missing tuple", variant_path))?;
+                    Some(EnumFields::Tuple(lower_tuple_items(tuple, &variant_path)?))
+                }
+                other => bail!(
+                    "{}: unsupported enum variant node `{}`",
+                    variant_path,
+                    other
+                ),
+            };
+            Ok(EnumVariant {
+                name: name.to_string(),
+                fields,
+                value: variant.get("discriminator").and_then(Value::as_u64),
+                docs: None,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(TypeDefType::Enum { variants })
+}
+
+fn lower_tuple_items(node: &Value, path: &str) -> Result<Vec<IdlType>> {
+    expect_kind(node, "tupleTypeNode", path)?
+        .get("items")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("{}: tupleTypeNode without items", path))?
+        .iter()
+        .enumerate()
+        .map(|(i, item)| lower_type(item, &format!("{}.{}", path, i)))
+        .collect()
+}
+
+fn lower_field(field: NamedValue, path: &str) -> Result<Field> {
+    let field_path = format!("{}.{}", path, field.name);
+    Ok(Field {
+        ty: lower_type(&field.ty, &field_path)?,
+        name: field.name,
+        docs: docs(&field.docs),
+    })
+}
+
+/// Lower a Codama type node into an [`IdlType`] with the same Borsh layout
+fn lower_type(node: &Value, path: &str) -> Result<IdlType> {
+    let simple = |name: &str| Ok(IdlType::Simple(name.to_string()));
+    let child = |key: &str| {
+        node.get(key)
+            .ok_or_else(|| anyhow!("{}: missing `{}`", path, key))
+    };
+
+    match kind(node, path)? {
+        "numberTypeNode" => {
+            let format = number_format(node, path)?;
+            simple(format)
+        }
+        "amountTypeNode" | "dateTimeTypeNode" | "solAmountTypeNode" => {
+            lower_type(child("number")?, path)
+        }
+        "booleanTypeNode" => {
+            if let Some(size) = node.get("size") {
+                let format = number_format(size, path)?;
+                if format != "u8" {
+                    bail!("{}: booleans must be stored as u8, found {}", path, format);
+                }
+            }
+            simple("bool")
+        }
+        "publicKeyTypeNode" => simple("pubkey"),
+        "sizePrefixTypeNode" => {
+            require_u32_prefix(child("prefix")?, path)?;
+            match kind(child("type")?, path)? {
+                "stringTypeNode" => simple("string"),
+                "bytesTypeNode" => simple("bytes"),
+                other => bail!("{}: unsupported size-prefixed `{}`", path, other),
+            }
+        }
+        "fixedSizeTypeNode" => {
+            let size = child("size")?
+                .as_u64()
+                .ok_or_else(|| anyhow!("{}: fixedSizeTypeNode size is not a number", path))?;
+            match kind(child("type")?, path)? {
+                "stringTypeNode" | "bytesTypeNode" => Ok(IdlType::Array {
+                    array: ArrayType::Tuple((
+                        Box::new(IdlType::Simple("u8".to_string())),
+                        size as usize,
+                    )),
+                }),
+                other => bail!("{}: unsupported fixed-size `{}`", path, other),
+            }
+        }
+        "arrayTypeNode" => {
+            let item = Box::new(lower_type(child("item")?, path)?);
+            let count = child("count")?;
+            match kind(count, path)? {
+                "prefixedCountNode" => {
+                    require_u32_prefix(child_of(count, "prefix", path)?, path)?;
+                    Ok(IdlType::Vec { vec: item })
+                }
+                "fixedCountNode" => {
+                    let size = child_of(count, "value", path)?
+                        .as_u64()
+                        .ok_or_else(|| anyhow!("{}: fixedCountNode value is not a number", path))?;
+                    Ok(IdlType::Array {
+                        array: ArrayType::Tuple((item, size as usize)),
+                    })
+                }
+                other => bail!("{}: unsupported array count `{}`", path, other),
+            }
+        }
+        "setTypeNode" => {
+            require_prefixed_count(child("count")?, path)?;
+            Ok(IdlType::HashSet {
+                hash_set: Box::new(lower_type(child("item")?, path)?),
+            })
+        }
+        "mapTypeNode" => {
+            require_prefixed_count(child("count")?, path)?;
+            Ok(IdlType::HashMap {
+                hash_map: (
+                    Box::new(lower_type(child("key")?, path)?),
+                    Box::new(lower_type(child("value")?, path)?),
+                ),
+            })
+        }
+        "optionTypeNode" => {
+            if node.get("fixed").and_then(Value::as_bool) == Some(true) {
+                bail!("{}: fixed-size options have no Borsh equivalent", path);
+            }
+            let inner = Box::new(lower_type(child("item")?, path)?);
+            let prefix = match node.get("prefix") {
+                Some(prefix) => number_format(prefix, path)?,
+                None => "u8",
+            };
+            match prefix {
+                "u8" => Ok(IdlType::Option { option: inner }),
+                "u32" => Ok(IdlType::COption { coption: inner }),
+                other => bail!("{}: unsupported option prefix {}", path, other),
+            }
+        }
+        "definedTypeLinkNode" => Ok(IdlType::Defined {
+            defined: DefinedTypeOrString::String(type_name(str_field(node, "name", path)?)),
+        }),
+        inline @ ("structTypeNode" | "tupleTypeNode" | "enumTypeNode") => bail!(
+            "{}: inline `{}` is not supported, declare it as a defined type",
+            path,
+            inline
+        ),
+        other => bail!("{}: unsupported type node `{}`", path, other),
+    }
+}
+
+/// Resolve the discriminator of an account or instruction to its leading bytes
+///
+/// Field discriminators remove the field they point at, since the generated code
+/// writes and checks the discriminator itself. Size discriminators can't be
+/// expressed as a prefix and are skipped.
+fn lower_discriminators(
+    discriminators: &[Value],
+    fields: &mut Vec<NamedValue>,
+    path: &str,
+) -> Result<Option<Vec<u8>>> {
+    for discriminator in discriminators {
+        let disc_path = format!("{}.discriminators", path);
+        if discriminator
+            .get("offset")
+            .and_then(Value::as_u64)
+            .unwrap_or(0)
+            != 0
+        {
+            continue;
+        }
+        match kind(discriminator, &disc_path)? {
+            "constantDiscriminatorNode" => {
+                let constant = child_of(discriminator, "constant", &disc_path)?;
+                let bytes = encode_constant(
+                    child_of(constant, "type", &disc_path)?,
+                    child_of(constant, "value", &disc_path)?,
+                    &disc_path,
+                )?;
+                return Ok(Some(bytes));
+            }
+            "fieldDiscriminatorNode" => {
+                let name = str_field(discriminator, "name", &disc_path)?;
+                let index = fields
+                    .iter()
+                    .position(|f| f.name == name)
+                    .ok_or_else(|| anyhow!("{}: no field named `{}`", disc_path, name))?;
+                if index != 0 {
+                    bail!(
+                        "{}: discriminator field `{}` is not the first field",
+                        disc_path,
+                        name
+                    );
+                }
+                let field = &fields[index];
+                let field_path = format!("{}.{}", path, name);
+                let value = field.default_value.as_ref().ok_or_else(|| {
+                    anyhow!("{}: discriminator field has no default value", field_path)
+                })?;
+                let bytes = encode_constant(&field.ty, value, &field_path)?;
+                fields.remove(index);
+                return Ok(Some(bytes));
+            }
+            _ => {}
+        }
+    }
+    Ok(None)
+}
+
+/// Borsh bytes of a constant value node of the given type
+fn encode_constant(ty: &Value, value: &Value, path: &str) -> Result<Vec<u8>> {
+    match kind(value, path)? {
+        "numberValueNode" => {
+            let format = number_format(ty, path)?;
+            let number = value
+                .get("number")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| anyhow!("{}: number value is not an unsigned integer", path))?;
+            let len = match format {
+                "u8" => 1,
+                "u16" => 2,
+                "u32" => 4,
+                "u64" => 8,
+                other => bail!("{}: unsupported discriminator type {}", path, other),
+            };
+            if len < 8 && number >> (len * 8) != 0 {
+                bail!("{}: value {} does not fit in {}", path, number, format);
+            }
+            Ok(number.to_le_bytes()[..len].to_vec())
+        }
+        "bytesValueNode" => {
+            let data = str_field(value, "data", path)?;
+            decode_bytes(data, str_field(value, "encoding", path)?, path)
+        }
+        "stringValueNode" => Ok(str_field(value, "string", path)?.as_bytes().to_vec()),
+        "publicKeyValueNode" => decode_bytes(str_field(value, "publicKey", path)?, "base58", path),
+        other => bail!("{}: unsupported constant value `{}`", path, other),
+    }
+}
+
+fn decode_bytes(data: &str, encoding: &str, path: &str) -> Result<Vec<u8>> {
+    match encoding {
+        "base16" => (0..data.len())
+            .step_by(2)
+            .map(|i| {
+                data.get(i..i + 2)
+                    .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                    .ok_or_else(|| anyhow!("{}: invalid base16 data `{}`", path, data))
+            })
+            .collect(),
+        "base58" => bs58::decode(data)
+            .into_vec()
+            .map_err(|e| anyhow!("{}: invalid base58 data: {}", path, e)),
+        "base64" => base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .map_err(|e| anyhow!("{}: invalid base64 data: {}", path, e)),
+        "utf8" => Ok(data.as_bytes().to_vec()),
+        other => bail!("{}: unknown bytes encoding `{}`", path, other),
+    }
+}
+
+/// Fixed address or PDA derivation from an instruction account's default value
+fn lower_account_default(
+    value: &Value,
+    ix: &InstructionNode,
+    program: &ProgramNode,
+) -> (Option<String>, Option<Pda>) {
+    match value.get("kind").and_then(Value::as_str) {
+        Some("publicKeyValueNode") => (
+            value
+                .get("publicKey")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            None,
+        ),
+        Some("programIdValueNode") => (Some(program.public_key.clone()), None),
+        Some("pdaValueNode") => (None, lower_pda_value(value, ix, program)),
+        _ => (None, None),
+    }
+}
+
+/// Lower a `pdaValueNode`; returns `None` if a seed can't be expressed as an Anchor seed
+fn lower_pda_value(value: &Value, ix: &InstructionNode, program: &ProgramNode) -> Option<Pda> {
+    let pda_ref = value.get("pda")?;
+    let linked;
+    let pda = match pda_ref.get("kind")?.as_str()? {
+        "pdaLinkNode" => {
+            let name = pda_ref.get("name")?.as_str()?;
+            program.pdas.iter().find(|p| p.name == name)?
+        }
+        "pdaNode" => {
+            linked = PdaNode::deserialize(pda_ref).ok()?;
+            &linked
+        }
+        _ => return None,
+    };
+
+    let seed_values = value
+        .get("seeds")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    let seeds = pda
+        .seeds
+        .iter()
+        .map(|seed| match seed.get("kind")?.as_str()? {
+            "constantPdaSeedNode" => {
+                let bytes = match seed.get("value")? {
+                    v if v.get("kind")?.as_str()? == "programIdValueNode" => {
+                        decode_bytes(&program.public_key, "base58", "").ok()?
+                    }
+                    v => encode_constant(seed.get("type")?, v, "").ok()?,
+                };
+                Some(Seed::Const { value: bytes })
+            }
+            "variablePdaSeedNode" => {
+                let name = seed.get("name")?.as_str()?;
+                let provided = seed_values
+                    .iter()
+                    .find(|v| v.get("name").and_then(Value::as_str) == Some(name))
+                    .and_then(|v| v.get("value"));
+                match provided {
+                    Some(v) => {
+                        let path = v.get("name")?.as_str()?.to_string();
+                        match v.get("kind")?.as_str()? {
+                            "accountValueNode" => Some(Seed::Account { path }),
+                            "argumentValueNode" => Some(Seed::Arg { path }),
+                            _ => None,
+                        }
+                    }
+                    // Unbound seeds default to the account or argument of the same name
+                    None if ix.accounts.iter().any(|a| a.name == name) => Some(Seed::Account {
+                        path: name.to_string(),
+                    }),
+                    None if ix.arguments.iter().any(|a| a.name == name) => Some(Seed::Arg {
+                        path: name.to_string(),
+                    }),
+                    None => None,
+                }
+            }
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let program_id = match &pda.program_id {
+        Some(id) => Some(Program::Const {
+            value: decode_bytes(id, "base58", "").ok()?,
+        }),
+        None => None,
+    };
+
+    Some(Pda {
+        seeds,
+        program: program_id,
+    })
+}
+
+fn named_values(struct_node: &Value, path: &str) -> Result<Vec<NamedValue>> {
+    expect_kind(struct_node, "structTypeNode", path)?
+        .get("fields")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("{}: structTypeNode without fields", path))?
+        .iter()
+        .map(|field| {
+            Ok(NamedValue {
+                name: str_field(field, "name", path)?.to_string(),
+                ty: child_of(field, "type", path)?.clone(),
+                docs: field
+                    .get("docs")
+                    .and_then(|d| serde_json::from_value(d.clone()).ok())
+                    .unwrap_or_default(),
+                default_value: field.get("defaultValue").cloned(),
+            })
+        })
+        .collect()
+}
+
+fn require_u32_prefix(prefix: &Value, path: &str) -> Result<()> {
+    let format = number_format(prefix, path)?;
+    if format != "u32" {
+        bail!("{}: Borsh length prefixes are u32, found {}", path, format);
+    }
+    Ok(())
+}
+
+fn require_prefixed_count(count: &Value, path: &str) -> Result<()> {
+    match kind(count, path)? {
+        "prefixedCountNode" => require_u32_prefix(child_of(count, "prefix", path)?, path),
+        other => bail!("{}: unsupported collection count `{}`", path, other),
+    }
+}
+
+/// Format of a little-endian `numberTypeNode`
+fn number_format<'a>(node: &'a Value, path: &str) -> Result<&'a str> {
+    expect_kind(node, "numberTypeNode", path)?;
+    if node.get("endian").and_then(Value::as_str) == Some("be") {
+        bail!("{}: big-endian numbers are not supported", path);
+    }
+    let format = str_field(node, "format", path)?;
+    match format {
+        "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" | "i128" | "f32"
+        | "f64" => Ok(format),
+        other => bail!("{}: unsupported number format `{}`", path, other),
+    }
+}
+
+fn kind<'a>(node: &'a Value, path: &str) -> Result<&'a str> {
+    node.get("kind")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("{}: expected a Codama node with a `kind`", path))
+}
+
+fn expect_kind<'a>(node: &'a Value, expected: &str, path: &str) -> Result<&'a Value> {
+    let found = kind(node, path)?;
+    if found != expected {
+        bail!("{}: expected `{}`, found `{}`", path, expected, found);
+    }
+    Ok(node)
+}
+
+fn child_of<'a>(node: &'a Value, key: &str, path: &str) -> Result<&'a Value> {
+    node.get(key)
+        .ok_or_else(|| anyhow!("{}: missing `{}`", path, key))
+}
+
+fn str_field<'a>(node: &'a Value, key: &str, path: &str) -> Result<&'a str> {
+    child_of(node, key, path)?
+        .as_str()
+        .ok_or_else(|| anyhow!("{}: `{}` is not a string", path, key))
+}
+
+/// Codama names are camelCase; type names become PascalCase like Anchor's
+fn type_name(name: &str) -> String {
+    name.to_pascal_case()
+}
+
+fn docs(docs: &[String]) -> Option<Vec<String>> {
+    (!docs.is_empty()).then(|| docs.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32_prefix() -> Value {
+        serde_json::json!({"kind": "numberTypeNode", "format": "u32", "endian": "le"})
+    }
+
+    fn number(format: &str) -> Value {
+        serde_json::json!({"kind": "numberTypeNode", "format": format, "endian": "le"})
+    }
+
+    const ROOT: &str = r#"{
+        "kind": "rootNode",
+        "standard": "codama",
+        "version": "1.0.0",
+        "program": {
+            "kind": "programNode",
+            "name": "counter",
+            "publicKey": "Count3AcZucFDPSFBAeHkQ6AvttieKUkyJ8HiQGhQwe",
+            "version": "0.1.0",
+            "docs": ["A counter program"],
+            "accounts": [{
+                "kind": "accountNode",
+                "name": "counter",
+                "docs": [],
+                "data": {"kind": "structTypeNode", "fields": [
+                    {"kind": "structFieldTypeNode", "name": "discriminator",
+                     "type": {"kind": "fixedSizeTypeNode", "size": 8, "type": {"kind": "bytesTypeNode"}},
+                     "defaultValue": {"kind": "bytesValueNode", "data": "ffd8b2a9c3e1d0a1", "encoding": "base16"},
+                     "defaultValueStrategy": "omitted"},
+                    {"kind": "structFieldTypeNode", "name": "authority", "type": {"kind": "publicKeyTypeNode"}},
+                    {"kind": "structFieldTypeNode", "name": "count", "type": {"kind": "numberTypeNode", "format": "u64", "endian": "le"}},
+                    {"kind": "structFieldTypeNode", "name": "mode", "type": {"kind": "definedTypeLinkNode", "name": "counterMode"}}
+                ]},
+                "discriminators": [{"kind": "fieldDiscriminatorNode", "name": "discriminator", "offset": 0}]
+            }],
+            "instructions": [{
+                "kind": "instructionNode",
+                "name": "increment",
+                "docs": ["Add to the counter"],
+                "accounts": [
+                    {"kind": "instructionAccountNode", "name": "counter", "isWritable": true, "isSigner": false,
+                     "defaultValue": {"kind": "pdaValueNode", "pda": {"kind": "pdaLinkNode", "name": "counter"},
+                        "seeds": [{"kind": "pdaSeedValueNode", "name": "authority", "value": {"kind": "accountValueNode", "name": "authority"}}]}},
+                    {"kind": "instructionAccountNode", "name": "authority", "isWritable": false, "isSigner": true},
+                    {"kind": "instructionAccountNode", "name": "payer", "isWritable": true, "isSigner": "either"},
+                    {"kind": "instructionAccountNode", "name": "systemProgram", "isWritable": false, "isSigner": false,
+                     "defaultValue": {"kind": "publicKeyValueNode", "publicKey": "11111111111111111111111111111111"}}
+                ],
+                "arguments": [
+                    {"kind": "instructionArgumentNode", "name": "discriminator",
+                     "type": {"kind": "numberTypeNode", "format": "u8", "endian": "le"},
+                     "defaultValue": {"kind": "numberValueNode", "number": 3},
+                     "defaultValueStrategy": "omitted"},
+                    {"kind": "instructionArgumentNode", "name": "amount", "type": {"kind": "numberTypeNode", "format": "u32", "endian": "le"}},
+                    {"kind": "instructionArgumentNode", "name": "memo",
+                     "type": {"kind": "optionTypeNode", "fixed": false,
+                              "item": {"kind": "sizePrefixTypeNode", "type": {"kind": "stringTypeNode", "encoding": "utf8"},
+                                       "prefix": {"kind": "numberTypeNode", "format": "u32", "endian": "le"}},
+                              "prefix": {"kind": "numberTypeNode", "format": "u8", "endian": "le"}}}
+                ],
+                "discriminators": [{"kind": "fieldDiscriminatorNode", "name": "discriminator", "offset": 0}]
+            }],
+            "definedTypes": [{
+                "kind": "definedTypeNode",
+                "name": "counterMode",
+                "docs": [],
+                "type": {"kind": "enumTypeNode", "variants": [
+                    {"kind": "enumEmptyVariantTypeNode", "name": "up"},
+                    {"kind": "enumTupleVariantTypeNode", "name": "step",
+                     "tuple": {"kind": "tupleTypeNode", "items": [{"kind": "numberTypeNode", "format": "u16", "endian": "le"}]}}
+                ], "size": {"kind": "numberTypeNode", "format": "u8", "endian": "le"}}
+            }],
+            "pdas": [{
+                "kind": "pdaNode",
+                "name": "counter",
+                "seeds": [
+                    {"kind": "constantPdaSeedNode", "type": {"kind": "stringTypeNode", "encoding": "utf8"},
+                     "value": {"kind": "stringValueNode", "string": "counter"}},
+                    {"kind": "variablePdaSeedNode", "name": "authority", "type": {"kind": "publicKeyTypeNode"}}
+                ]
+            }],
+            "errors": [{"kind": "errorNode", "name": "overflow", "code": 6000, "message": "Counter overflowed", "docs": []}]
+        },
+        "additionalPrograms": []
+    }"#;
+
+    #[test]
+    fn test_is_root_node() {
+        assert!(is_root_node(&serde_json::from_str(ROOT).unwrap()));
+        assert!(!is_root_node(&serde_json::json!({"instructions": []})));
+    }
+
+    #[test]
+    fn test_lower_root_node() {
+        let idl = from_str(ROOT).unwrap();
+        assert_eq!(idl.get_name(), "counter");
+        assert_eq!(
+            idl.get_address(),
+            Some("Count3AcZucFDPSFBAeHkQ6AvttieKUkyJ8HiQGhQwe")
+        );
+
+        // The field discriminator becomes the account discriminator and leaves the struct
+        let account = &idl.accounts.as_ref().unwrap()[0];
+        assert_eq!(account.name, "Counter");
+        assert_eq!(
+            account.discriminator,
+            Some(vec![0xff, 0xd8, 0xb2, 0xa9, 0xc3, 0xe1, 0xd0, 0xa1])
+        );
+        match &account.ty {
+            Some(TypeDefType::Struct {
+                fields: StructFields::Named(fields),
+            }) => {
+                let names: Vec<_> = fields.iter().map(|f| f.name.as_str()).collect();
+                assert_eq!(names, ["authority", "count", "mode"]);
+                assert!(matches!(
+                    &fields[2].ty,
+                    IdlType::Defined { defined } if defined.name() == "CounterMode"
+                ));
+            }
+            other => panic!("Expected struct, got {:?}", other),
+        }
+
+        let ix = &idl.instructions[0];
+        assert_eq!(ix.discriminator, Some(vec![3]));
+        assert_eq!(ix.args.len(), 2);
+        assert!(matches!(
+            &ix.args[1].ty,
+            IdlType::Option { option } if matches!(**option, IdlType::Simple(ref s) if s == "string")
+        ));
+        assert!(!ix.accounts[2].signer);
+        assert_eq!(
+            ix.accounts[3].address.as_deref(),
+            Some("11111111111111111111111111111111")
+        );
+        let pda = ix.accounts[0].pda.as_ref().unwrap();
+        assert!(matches!(&pda.seeds[0], Seed::Const { value } if value == b"counter"));
+        assert!(matches!(&pda.seeds[1], Seed::Account { path } if path == "authority"));
+
+        let errors = idl.errors.as_ref().unwrap();
+        assert_eq!(errors[0].code, 6000);
+    }
+
+    #[test]
+    fn test_lowered_idl_generates() {
+        let idl = from_str(ROOT).unwrap();
+        let code = crate::codegen::generate(&idl, "counter").unwrap();
+        assert!(code.accounts.contains("pub struct Counter"));
+        assert!(code
+            .instructions
+            .contains("INCREMENT_IX_DISCM: [u8; 1] = [3u8]"));
+        assert!(code.types.contains("pub enum CounterMode"));
+    }
+
+    #[test]
+    fn test_lower_type_layouts() {
+        let vec = serde_json::json!({
+            "kind": "arrayTypeNode",
+            "item": {"kind": "publicKeyTypeNode"},
+            "count": {"kind": "prefixedCountNode", "prefix": u32_prefix()}
+        });
+        assert!(matches!(
+            lower_type(&vec, "x").unwrap(),
+            IdlType::Vec { .. }
+        ));
+
+        let fixed = serde_json::json!({
+            "kind": "arrayTypeNode",
+            "item": number("u8"),
+            "count": {"kind": "fixedCountNode", "value": 4}
+        });
+        assert!(matches!(
+            lower_type(&fixed, "x").unwrap(),
+            IdlType::Array {
+                array: ArrayType::Tuple((_, 4))
+            }
+        ));
+
+        let coption = serde_json::json!({
+            "kind": "optionTypeNode",
+            "item": {"kind": "publicKeyTypeNode"},
+            "prefix": u32_prefix()
+        });
+        assert!(matches!(
+            lower_type(&coption, "x").unwrap(),
+            IdlType::COption { .. }
+        ));
+
+        let map = serde_json::json!({
+            "kind": "mapTypeNode",
+            "key": {"kind": "publicKeyTypeNode"},
+            "value": number("u64"),
+            "count": {"kind": "prefixedCountNode", "prefix": u32_prefix()}
+        });
+        assert!(matches!(
+            lower_type(&map, "x").unwrap(),
+            IdlType::HashMap { .. }
+        ));
+    }
+
+    #[test]
+    fn test_lower_type_rejects_non_borsh_layouts() {
+        let u64_prefixed = serde_json::json!({
+            "kind": "sizePrefixTypeNode",
+            "type": {"kind": "stringTypeNode", "encoding": "utf8"},
+            "prefix": number("u64")
+        });
+        let err = lower_type(&u64_prefixed, "types.name")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("types.name"), "{}", err);
+        assert!(err.contains("u32"), "{}", err);
+
+        let remainder = serde_json::json!({
+            "kind": "arrayTypeNode",
+            "item": number("u8"),
+            "count": {"kind": "remainderCountNode"}
+        });
+        assert!(lower_type(&remainder, "x").is_err());
+
+        let big_endian =
+            serde_json::json!({"kind": "numberTypeNode", "format": "u16", "endian": "be"});
+        assert!(lower_type(&big_endian, "x").is_err());
+
+        let unknown = serde_json::json!({"kind": "hiddenPrefixTypeNode"});
+        let err = lower_type(&unknown, "x").unwrap_err().to_string();
+        assert!(err.contains("hiddenPrefixTypeNode"), "{}", err);
+    }
+
+    #[test]
+    fn test_constant_discriminator() {
+        let discriminators = vec![serde_json::json!({
+            "kind": "constantDiscriminatorNode",
+            "offset": 0,
+            "constant": {
+                "kind": "constantValueNode",
+                "type": {"kind": "bytesTypeNode"},
+                "value": {"kind": "bytesValueNode", "data": "AQID", "encoding": "base64"}
+            }
+        })];
+        let mut fields = vec![];
+        assert_eq!(
+            lower_discriminators(&discriminators, &mut fields, "x").unwrap(),
+            Some(vec![1, 2, 3])
+        );
+
+        // Size discriminators can't be expressed as a prefix
+        let size = vec![serde_json::json!({"kind": "sizeDiscriminatorNode", "size": 165})];
+        assert_eq!(lower_discriminators(&size, &mut fields, "x").unwrap(), None);
+    }
+}
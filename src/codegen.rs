@@ -1,8 +1,9 @@
 use crate::idl::{ArrayType, *};
 use anyhow::Result;
-use heck::{ToPascalCase, ToSnakeCase};
-use proc_macro2::TokenStream;
+use heck::{ToPascalCase, ToShoutySnakeCase, ToSnakeCase};
+use proc_macro2::{Ident, TokenStream};
 use quote::{format_ident, quote};
+use std::collections::HashSet;
 use syn::parse_str;
 
 /// Represents the generated code split into modules
@@ -13,9 +14,40 @@ pub struct GeneratedCode {
     pub errors: String,
     pub events: String,
     pub types: String,
+    /// IDL types renamed to avoid clashing with generated or imported items,
+    /// as `(original, renamed)` pairs
+    pub renamed_types: Vec<(String, String)>,
+}
+
+/// Optional output features, chosen on the command line
+#[derive(Debug, Clone, Default)]
+pub struct GenerateOptions {
+    /// Implement `anchor_lang` traits for accounts, `*IxData` and `*Keys`, and add
+    /// `AccountInfo`-based `*Accounts` structs for CPI
+    pub anchor: bool,
 }
 
 pub fn generate(idl: &Idl, module_name: &str) -> Result<GeneratedCode> {
+    generate_with_options(idl, module_name, &GenerateOptions::default())
+}
+
+pub fn generate_with_options(
+    idl: &Idl,
+    module_name: &str,
+    options: &GenerateOptions,
+) -> Result<GeneratedCode> {
+    let (idl, renamed_types) = resolve_type_name_conflicts(idl, options);
+    let idl = expand_type_aliases(idl.as_ref());
+    let idl = with_legacy_account_discriminators(idl.as_ref());
+    // Relations name accounts relative to their group, so resolve them before flattening
+    let relations: Vec<_> = idl
+        .instructions
+        .iter()
+        .map(|ix| ix.relations(idl.is_legacy_anchor()))
+        .collect();
+    let idl = flatten_account_groups(idl.as_ref());
+    let idl = idl.as_ref();
+
     let mut types_tokens = TokenStream::new();
     let mut accounts_tokens = TokenStream::new();
     let mut instructions_tokens = TokenStream::new();
@@ -25,6 +57,16 @@ pub fn generate(idl: &Idl, module_name: &str) -> Result<GeneratedCode> {
     // Generate module header
     let _module_ident = format_ident!("{}", module_name);
 
+    // Types that contain each other by value refer to one another through `Box`
+    let mut boxed_refs: std::collections::HashMap<&str, HashSet<&str>> =
+        std::collections::HashMap::new();
+    for group in recursive_type_groups(idl) {
+        for name in &group {
+            boxed_refs.insert(name, group.iter().copied().collect());
+        }
+    }
+    let no_boxing = HashSet::new();
+
     // Generate account discriminators
     // Note: In new format IDLs, accounts reference types that are generated separately
     // We'll add discriminator impl blocks for accounts that match type names
@@ -34,7 +76,10 @@ pub fn generate(idl: &Idl, module_name: &str) -> Result<GeneratedCode> {
             // Only generate if account has type definition (old format)
             if account.ty.is_some() {
                 // Inline type definitions handle their own discriminators
-                accounts_tokens.extend(generate_account(account)?);
+                accounts_tokens.extend(generate_account(
+                    account,
+                    boxed_refs.get(account.name.as_str()).unwrap_or(&no_boxing),
+                )?);
             } else if let Some(disc) = &account.discriminator {
                 // For accounts that reference types (new format), store discriminator
                 // to be applied to the matching type later
@@ -46,14 +91,29 @@ pub fn generate(idl: &Idl, module_name: &str) -> Result<GeneratedCode> {
     // Generate types (including those referenced by accounts)
     if let Some(types) = &idl.types {
         for ty in types {
-            let mut type_tokens = generate_type_def(ty)?;
-
             // Check if this type has a discriminator (is an account)
             let has_discriminator = account_discriminators.contains_key(&ty.name);
+            let boxed = boxed_refs.get(ty.name.as_str()).unwrap_or(&no_boxing);
+
+            let mut type_tokens = match &ty.ty {
+                // A type alias can't carry the discriminator impl, so alias accounts
+                // become a newtype with the same Borsh layout
+                TypeDefType::Alias { alias } if has_discriminator => generate_type_def(
+                    &TypeDef {
+                        ty: TypeDefType::Struct {
+                            fields: StructFields::Tuple(vec![alias.clone()]),
+                        },
+                        ..ty.clone()
+                    },
+                    boxed,
+                )?,
+                _ => generate_type_def(ty, boxed)?,
+            };
 
             // Add discriminator methods if there's a matching account discriminator
             if let Some(disc) = account_discriminators.get(&ty.name) {
-                let name = format_ident!("{}", ty.name);
+                let name = rust_ident(&ty.name);
+                let disc_len = proc_macro2::Literal::usize_unsuffixed(disc.len());
                 let disc_bytes = disc.iter().map(|b| quote! { #b });
 
                 // Check if this type uses bytemuck serialization
@@ -67,32 +127,14 @@ pub fn generate(idl: &Idl, module_name: &str) -> Result<GeneratedCode> {
                     // For bytemuck types, use bytemuck for deserialization
                     type_tokens.extend(quote! {
                         impl #name {
-                            pub const DISCRIMINATOR: [u8; 8] = [#(#disc_bytes),*];
+                            pub const DISCRIMINATOR: [u8; #disc_len] = [#(#disc_bytes),*];
 
                             pub fn try_from_slice_with_discriminator(data: &[u8]) -> std::io::Result<Self> {
-                                if data.len() < 8 {
-                                    return Err(std::io::Error::new(
-                                        std::io::ErrorKind::InvalidData,
-                                        "Data too short for discriminator",
-                                    ));
-                                }
-                                if data[..8] != Self::DISCRIMINATOR {
-                                    return Err(std::io::Error::new(
-                                        std::io::ErrorKind::InvalidData,
-                                        "Invalid discriminator",
-                                    ));
-                                }
-                                bytemuck::try_from_bytes::<Self>(&data[8..])
-                                    .copied()
-                                    .map_err(|e| std::io::Error::new(
-                                        std::io::ErrorKind::InvalidData,
-                                        format!("Bytemuck conversion error: {:?}", e),
-                                    ))
+                                solana_idl_codegen_runtime::pod_from_slice_with_discriminator(&Self::DISCRIMINATOR, data)
                             }
 
                             pub fn serialize_with_discriminator<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-                                writer.write_all(&Self::DISCRIMINATOR)?;
-                                writer.write_all(bytemuck::bytes_of(self))
+                                solana_idl_codegen_runtime::pod_serialize_with_discriminator(&Self::DISCRIMINATOR, self, writer)
                             }
                         }
                     });
@@ -100,27 +142,14 @@ pub fn generate(idl: &Idl, module_name: &str) -> Result<GeneratedCode> {
                     // For borsh types, use borsh for deserialization
                     type_tokens.extend(quote! {
                         impl #name {
-                            pub const DISCRIMINATOR: [u8; 8] = [#(#disc_bytes),*];
+                            pub const DISCRIMINATOR: [u8; #disc_len] = [#(#disc_bytes),*];
 
                             pub fn try_from_slice_with_discriminator(data: &[u8]) -> std::io::Result<Self> {
-                                if data.len() < 8 {
-                                    return Err(std::io::Error::new(
-                                        std::io::ErrorKind::InvalidData,
-                                        "Data too short for discriminator",
-                                    ));
-                                }
-                                if data[..8] != Self::DISCRIMINATOR {
-                                    return Err(std::io::Error::new(
-                                        std::io::ErrorKind::InvalidData,
-                                        "Invalid discriminator",
-                                    ));
-                                }
-                                borsh::BorshDeserialize::try_from_slice(&data[8..])
+                                solana_idl_codegen_runtime::try_from_slice_with_discriminator(&Self::DISCRIMINATOR, data)
                             }
 
                             pub fn serialize_with_discriminator<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-                                writer.write_all(&Self::DISCRIMINATOR)?;
-                                borsh::BorshSerialize::serialize(self, writer)
+                                solana_idl_codegen_runtime::serialize_with_discriminator(&Self::DISCRIMINATOR, self, writer)
                             }
                         }
                     });
@@ -143,7 +172,20 @@ pub fn generate(idl: &Idl, module_name: &str) -> Result<GeneratedCode> {
 
     // Generate instruction structs and enums
     let has_program_id = idl.get_address().is_some();
-    instructions_tokens.extend(generate_instructions(&idl.instructions, has_program_id)?);
+    instructions_tokens.extend(generate_instructions(
+        &idl.instructions,
+        has_program_id,
+        idl.is_shank(),
+    )?);
+    instructions_tokens.extend(generate_relation_helpers(idl, &relations));
+    instructions_tokens.extend(generate_keys_resolvers(idl, &relations, has_program_id));
+    instructions_tokens.extend(generate_instruction_builders(idl, has_program_id));
+    instructions_tokens.extend(generate_program_handle(idl, has_program_id));
+
+    // Generate program constants
+    if let Some(constants) = &idl.constants {
+        types_tokens.extend(generate_constants(constants)?);
+    }
 
     // Generate errors
     if let Some(errors) = &idl.errors {
@@ -159,6 +201,19 @@ pub fn generate(idl: &Idl, module_name: &str) -> Result<GeneratedCode> {
         events_tokens.extend(generate_event_parsing_helpers(events)?);
     }
 
+    // Shared traits over the generated items
+    let (accounts, instructions, events) =
+        generate_program_trait_impls(idl, &renamed_types, has_program_id);
+    accounts_tokens.extend(accounts);
+    instructions_tokens.extend(instructions);
+    events_tokens.extend(events);
+
+    if options.anchor {
+        let (accounts, instructions) = generate_anchor_impls(idl, has_program_id);
+        accounts_tokens.extend(accounts);
+        instructions_tokens.extend(instructions);
+    }
+
     // Format each module with appropriate imports
     let types_code = format_module(types_tokens, &[], "types")?;
     let accounts_code = format_module(accounts_tokens, &["types"], "accounts")?;
@@ -177,9 +232,451 @@ pub fn generate(idl: &Idl, module_name: &str) -> Result<GeneratedCode> {
         errors: errors_code,
         events: events_code,
         types: types_code,
+        renamed_types,
     })
 }
 
+/// Names the generated crate defines or imports unqualified, so IDL types can't reuse them
+///
+/// Covers the generated enums and errors, the imports every module starts with, and
+/// prelude items that a glob-imported type of the same name would shadow.
+const RESERVED_TYPE_NAMES: &[&str] = &[
+    "Instruction",
+    "ErrorCode",
+    "ParsedEvent",
+    "EventParseError",
+    "ValidationError",
+    "RelationError",
+    "AccountFetcher",
+    "UnresolvedAccounts",
+    "BuildError",
+    "Program",
+    "ProgramInstruction",
+    "ProgramAccount",
+    "ProgramEvent",
+    "ID",
+    "Pubkey",
+    "AccountMeta",
+    "ProgramError",
+    "Pod",
+    "Zeroable",
+    "BorshSerialize",
+    "BorshDeserialize",
+    "Result",
+    "Option",
+    "Some",
+    "None",
+    "Ok",
+    "Err",
+    "Vec",
+    "String",
+    "Box",
+    "U256",
+    "I256",
+    "COption",
+];
+
+/// Rename IDL types, accounts and events whose names clash with generated items
+///
+/// Besides [`RESERVED_TYPE_NAMES`], each instruction reserves its `IxArgs`, `Keys`,
+/// `KeysResolver`, `Builder` and `IxData` structs (and `ReturnData` alias if it has one,
+/// `Accounts` struct in Anchor mode) and each event its `Event` wrapper. A clashing name `X` becomes
+/// `XType` (or `XType2`, `XType3`, ... if that is taken too), and every reference to
+/// it is rewritten, so the result is deterministic for a given IDL. The IDL is only
+/// cloned when something needs renaming.
+fn resolve_type_name_conflicts<'a>(
+    idl: &'a Idl,
+    options: &GenerateOptions,
+) -> (std::borrow::Cow<'a, Idl>, Vec<(String, String)>) {
+    use std::collections::{HashMap, HashSet};
+
+    let mut reserved: HashSet<String> = RESERVED_TYPE_NAMES.iter().map(|s| s.to_string()).collect();
+    for ix in &idl.instructions {
+        let pascal = ix.name.to_pascal_case();
+        reserved.insert(format!("{}IxArgs", pascal));
+        reserved.insert(format!("{}Keys", pascal));
+        reserved.insert(format!("{}IxData", pascal));
+        reserved.insert(format!("{}KeysResolver", pascal));
+        reserved.insert(format!("{}Builder", pascal));
+        if ix.returns.is_some() {
+            reserved.insert(format!("{}ReturnData", pascal));
+        }
+        if options.anchor {
+            reserved.insert(format!("{}Accounts", pascal));
+        }
+    }
+    for event in idl.events.iter().flatten() {
+        reserved.insert(format!("{}Event", event.name));
+    }
+
+    // Types come first so their renames win; accounts and events usually share their names
+    let entity_names: Vec<&str> = idl
+        .types
+        .iter()
+        .flatten()
+        .map(|t| t.name.as_str())
+        .chain(idl.accounts.iter().flatten().map(|a| a.name.as_str()))
+        .chain(idl.events.iter().flatten().map(|e| e.name.as_str()))
+        .collect();
+
+    let mut taken: HashSet<String> = reserved.clone();
+    taken.extend(entity_names.iter().map(|n| n.to_string()));
+
+    let mut renames: HashMap<String, String> = HashMap::new();
+    let mut renamed_types = Vec::new();
+    for name in entity_names {
+        if !reserved.contains(name) || renames.contains_key(name) {
+            continue;
+        }
+        let mut candidate = format!("{}Type", name);
+        let mut n = 2;
+        while taken.contains(&candidate) {
+            candidate = format!("{}Type{}", name, n);
+            n += 1;
+        }
+        taken.insert(candidate.clone());
+        renames.insert(name.to_string(), candidate.clone());
+        renamed_types.push((name.to_string(), candidate));
+    }
+
+    if renames.is_empty() {
+        return (std::borrow::Cow::Borrowed(idl), renamed_types);
+    }
+
+    let mut idl = idl.clone();
+    let rename_doc = |original: &str| format!("IDL name: `{}`", original);
+
+    for ty in idl.types.iter_mut().flatten() {
+        if let Some(new_name) = renames.get(&ty.name) {
+            ty.docs
+                .get_or_insert_with(Vec::new)
+                .push(rename_doc(&ty.name));
+            ty.name = new_name.clone();
+        }
+    }
+    for account in idl.accounts.iter_mut().flatten() {
+        if let Some(new_name) = renames.get(&account.name) {
+            if account.ty.is_some() {
+                account
+                    .docs
+                    .get_or_insert_with(Vec::new)
+                    .push(rename_doc(&account.name));
+            }
+            account.name = new_name.clone();
+        }
+    }
+    for event in idl.events.iter_mut().flatten() {
+        if let Some(new_name) = renames.get(&event.name) {
+            event.name = new_name.clone();
+        }
+    }
+    for_each_idl_type_mut(&mut idl, &mut |ty| rename_type_refs(ty, &renames));
+
+    (std::borrow::Cow::Owned(idl), renamed_types)
+}
+
+/// Call `f` on every top-level type in the IDL: type definition and account
+/// fields, alias targets, instruction args and return types, event fields and constants
+///
+/// Nested types (e.g. the `T` in `Vec<T>`) are left to `f`.
+pub(crate) fn for_each_idl_type_mut(idl: &mut Idl, f: &mut dyn FnMut(&mut IdlType)) {
+    fn visit_type_def(ty: &mut TypeDefType, f: &mut dyn FnMut(&mut IdlType)) {
+        let mut visit_fields = |fields: &mut StructFields| match fields {
+            StructFields::Named(fields) => fields.iter_mut().for_each(|field| f(&mut field.ty)),
+            StructFields::Tuple(types) => types.iter_mut().for_each(&mut *f),
+        };
+        match ty {
+            TypeDefType::Struct { fields } => visit_fields(fields),
+            TypeDefType::Enum { variants } => {
+                for variant in variants {
+                    match &mut variant.fields {
+                        Some(EnumFields::Named(fields)) => {
+                            fields.iter_mut().for_each(|field| f(&mut field.ty))
+                        }
+                        Some(EnumFields::Tuple(types)) => types.iter_mut().for_each(&mut *f),
+                        None => {}
+                    }
+                }
+            }
+            TypeDefType::Alias { alias } => f(alias),
+        }
+    }
+
+    for ty in idl.types.iter_mut().flatten() {
+        visit_type_def(&mut ty.ty, f);
+    }
+    for account in idl.accounts.iter_mut().flatten() {
+        if let Some(ty) = &mut account.ty {
+            visit_type_def(ty, f);
+        }
+    }
+    for event in idl.events.iter_mut().flatten() {
+        for field in event.fields.iter_mut().flatten() {
+            f(&mut field.ty);
+        }
+    }
+    for ix in &mut idl.instructions {
+        for arg in &mut ix.args {
+            f(&mut arg.ty);
+        }
+        if let Some(returns) = &mut ix.returns {
+            f(returns);
+        }
+    }
+    for constant in idl.constants.iter_mut().flatten() {
+        f(&mut constant.ty);
+    }
+}
+
+/// Rewrite references to renamed types inside an IDL type
+fn rename_type_refs(ty: &mut IdlType, renames: &std::collections::HashMap<String, String>) {
+    match ty {
+        IdlType::Simple(_) if is_pubkey_type(ty) => {}
+        // Old-format IDLs sometimes reference defined types as bare strings
+        IdlType::Simple(name) => {
+            if let Some(new_name) = renames.get(name.as_str()) {
+                *name = new_name.clone();
+            }
+        }
+        IdlType::Vec { vec: inner }
+        | IdlType::Option { option: inner }
+        | IdlType::COption { coption: inner }
+        | IdlType::HashSet { hash_set: inner }
+        | IdlType::BTreeSet { btree_set: inner } => rename_type_refs(inner, renames),
+        IdlType::HashMap {
+            hash_map: (key, value),
+        }
+        | IdlType::BTreeMap {
+            btree_map: (key, value),
+        } => {
+            rename_type_refs(key, renames);
+            rename_type_refs(value, renames);
+        }
+        IdlType::Array {
+            array: ArrayType::Tuple((inner, _)) | ArrayType::Generic((inner, _)),
+        } => rename_type_refs(inner, renames),
+        IdlType::Defined { defined } => {
+            let name = match defined {
+                DefinedTypeOrString::String(name) => name,
+                DefinedTypeOrString::Nested(defined) => {
+                    for arg in defined.generics.iter_mut().flatten() {
+                        if let GenericArg::Type { ty } = arg {
+                            rename_type_refs(ty, renames);
+                        }
+                    }
+                    &mut defined.name
+                }
+            };
+            if let Some(new_name) = renames.get(name.as_str()) {
+                *name = new_name.clone();
+            }
+        }
+        IdlType::Generic { .. } => {}
+    }
+}
+
+/// Expand composite instruction account groups into flat, prefixed accounts
+///
+/// `Keys` structs, `AccountMeta` arrays and decoders then follow the on-chain
+/// order without knowing about groups. See [`Instruction::flat_accounts`].
+fn flatten_account_groups(idl: &Idl) -> std::borrow::Cow<'_, Idl> {
+    let has_groups = idl
+        .instructions
+        .iter()
+        .any(|ix| ix.accounts.iter().any(|a| a.accounts.is_some()));
+    if !has_groups {
+        return std::borrow::Cow::Borrowed(idl);
+    }
+
+    let mut idl = idl.clone();
+    for ix in &mut idl.instructions {
+        ix.accounts = ix.flat_accounts();
+    }
+    std::borrow::Cow::Owned(idl)
+}
+
+/// Give legacy Anchor accounts the `sha256("account:<Name>")` discriminator Anchor
+/// derives for them, since old IDLs leave it out
+pub(crate) fn with_legacy_account_discriminators(idl: &Idl) -> std::borrow::Cow<'_, Idl> {
+    let missing = |idl: &Idl| {
+        idl.accounts
+            .iter()
+            .flatten()
+            .any(|account| account.discriminator.is_none())
+    };
+    if !idl.is_legacy_anchor() || !idl.is_anchor() || !missing(idl) {
+        return std::borrow::Cow::Borrowed(idl);
+    }
+
+    let mut idl = idl.clone();
+    for account in idl.accounts.iter_mut().flatten() {
+        if account.discriminator.is_none() {
+            account.discriminator = Some(crate::convert::anchor_discriminator(
+                "account",
+                &account.name,
+            ));
+        }
+    }
+    std::borrow::Cow::Owned(idl)
+}
+
+/// Type aliases by name, with their generic parameters and aliased type
+type AliasMap = std::collections::HashMap<String, (Vec<GenericParam>, IdlType)>;
+
+/// Replace references to type aliases with the aliased type
+///
+/// Aliases are still emitted as `pub type` items, but the serde helpers and the
+/// account/event generation need to see the underlying type of each field. The IDL
+/// is only cloned when it declares aliases.
+fn expand_type_aliases(idl: &Idl) -> std::borrow::Cow<'_, Idl> {
+    let aliases: AliasMap = idl
+        .types
+        .iter()
+        .flatten()
+        .filter_map(|t| match &t.ty {
+            TypeDefType::Alias { alias } => Some((
+                t.name.clone(),
+                (t.generics.clone().unwrap_or_default(), alias.clone()),
+            )),
+            _ => None,
+        })
+        .collect();
+    if aliases.is_empty() {
+        return std::borrow::Cow::Borrowed(idl);
+    }
+
+    let mut idl = idl.clone();
+    for_each_idl_type_mut(&mut idl, &mut |ty| expand_alias_refs(ty, &aliases, 0));
+    std::borrow::Cow::Owned(idl)
+}
+
+/// Expand alias references inside an IDL type, substituting generic arguments
+///
+/// `depth` stops self-referential aliases from expanding forever; such a reference
+/// is left pointing at the alias.
+fn expand_alias_refs(ty: &mut IdlType, aliases: &AliasMap, depth: usize) {
+    const MAX_ALIAS_DEPTH: usize = 32;
+
+    let (name, args) = match ty {
+        IdlType::Vec { vec: inner }
+        | IdlType::Option { option: inner }
+        | IdlType::COption { coption: inner }
+        | IdlType::HashSet { hash_set: inner }
+        | IdlType::BTreeSet { btree_set: inner } => {
+            return expand_alias_refs(inner, aliases, depth);
+        }
+        IdlType::HashMap {
+            hash_map: (key, value),
+        }
+        | IdlType::BTreeMap {
+            btree_map: (key, value),
+        } => {
+            expand_alias_refs(key, aliases, depth);
+            return expand_alias_refs(value, aliases, depth);
+        }
+        IdlType::Array { array } => {
+            let (ArrayType::Tuple((inner, _)) | ArrayType::Generic((inner, _))) = array;
+            return expand_alias_refs(inner, aliases, depth);
+        }
+        IdlType::Generic { .. } => return,
+        IdlType::Simple(_) if is_pubkey_type(ty) => return,
+        IdlType::Simple(name) => (name.clone(), vec![]),
+        IdlType::Defined { defined } => {
+            if let DefinedTypeOrString::Nested(DefinedType {
+                generics: Some(args),
+                ..
+            }) = defined
+            {
+                for arg in args.iter_mut() {
+                    if let GenericArg::Type { ty } = arg {
+                        expand_alias_refs(ty, aliases, depth);
+                    }
+                }
+            }
+            let args = match defined {
+                DefinedTypeOrString::Nested(defined) => {
+                    defined.generics.clone().unwrap_or_default()
+                }
+                DefinedTypeOrString::String(_) => vec![],
+            };
+            (defined.name().to_string(), args)
+        }
+    };
+
+    if depth >= MAX_ALIAS_DEPTH {
+        return;
+    }
+    if let Some((params, target)) = aliases.get(&name) {
+        let mut expanded = target.clone();
+        substitute_generics(&mut expanded, params, &args);
+        expand_alias_refs(&mut expanded, aliases, depth + 1);
+        *ty = expanded;
+    }
+}
+
+/// Replace generic parameters in `ty` with the matching arguments
+fn substitute_generics(ty: &mut IdlType, params: &[GenericParam], args: &[GenericArg]) {
+    let arg_for = |name: &str| {
+        params
+            .iter()
+            .position(|p| p.name() == name)
+            .and_then(|i| args.get(i))
+    };
+
+    match ty {
+        IdlType::Generic { generic } => {
+            if let Some(GenericArg::Type { ty: arg }) = arg_for(generic) {
+                *ty = arg.clone();
+            }
+        }
+        IdlType::Vec { vec: inner }
+        | IdlType::Option { option: inner }
+        | IdlType::COption { coption: inner }
+        | IdlType::HashSet { hash_set: inner }
+        | IdlType::BTreeSet { btree_set: inner } => substitute_generics(inner, params, args),
+        IdlType::HashMap {
+            hash_map: (key, value),
+        }
+        | IdlType::BTreeMap {
+            btree_map: (key, value),
+        } => {
+            substitute_generics(key, params, args);
+            substitute_generics(value, params, args);
+        }
+        IdlType::Array { array } => {
+            let (ArrayType::Tuple((inner, _)) | ArrayType::Generic((inner, _))) = array;
+            substitute_generics(inner, params, args);
+            if let ArrayType::Generic((inner, len)) = array {
+                if let Some(GenericArg::Const { value }) = arg_for(len) {
+                    *array = match value.parse::<usize>() {
+                        Ok(size) => ArrayType::Tuple((inner.clone(), size)),
+                        Err(_) => ArrayType::Generic((inner.clone(), value.clone())),
+                    };
+                }
+            }
+        }
+        IdlType::Defined {
+            defined:
+                DefinedTypeOrString::Nested(DefinedType {
+                    generics: Some(nested_args),
+                    ..
+                }),
+        } => {
+            for nested in nested_args.iter_mut() {
+                match nested {
+                    GenericArg::Type { ty } => substitute_generics(ty, params, args),
+                    GenericArg::Const { value } => {
+                        if let Some(GenericArg::Const { value: arg }) = arg_for(value) {
+                            *value = arg.clone();
+                        }
+                    }
+                }
+            }
+        }
+        IdlType::Simple(_) | IdlType::Defined { .. } => {}
+    }
+}
+
 fn format_module(tokens: TokenStream, imports: &[&str], module_type: &str) -> Result<String> {
     if tokens.is_empty() {
         return Ok(String::new());
@@ -221,8 +718,8 @@ fn format_module(tokens: TokenStream, imports: &[&str], module_type: &str) -> Re
                 #[allow(unused_imports)]
                 use bytemuck::{Pod, Zeroable};
                 #[allow(unused_imports)]
-                use solana_program::instruction::AccountMeta;
-                use solana_program::pubkey::Pubkey;
+                use crate::solana_program::instruction::AccountMeta;
+                use crate::solana_program::pubkey::Pubkey;
             }
         }
     };
@@ -259,19 +756,33 @@ fn format_module(tokens: TokenStream, imports: &[&str], module_type: &str) -> Re
 
 fn generate_lib_module(idl: &Idl) -> String {
     let program_id_declaration = if let Some(address) = idl.get_address() {
-        format!("solana_program::declare_id!(\"{}\");\n\n", address)
+        format!("solana_program::pubkey::declare_id!(\"{}\");\n\n", address)
     } else {
         // If no address is provided, use a placeholder comment
-        "// Program ID not specified in IDL\n// solana_program::declare_id!(\"YourProgramIdHere\");\n\n".to_string()
+        "// Program ID not specified in IDL\n// solana_program::pubkey::declare_id!(\"YourProgramIdHere\");\n\n".to_string()
     };
 
     // Note: We don't re-export events::* to avoid ambiguous glob re-exports
     // since events are often also defined in types. Users can access events
     // via the events module directly (e.g., crate::events::EventName)
 
+    // The program description becomes the crate-level docs
+    let description: String = idl
+        .metadata
+        .as_ref()
+        .and_then(|m| m.description.as_deref())
+        .map(|description| {
+            let lines: String = description
+                .lines()
+                .map(|line| format!("//! {}", line).trim_end().to_string() + "\n")
+                .collect();
+            format!("//!\n{}", lines)
+        })
+        .unwrap_or_default();
+
     format!(
         r#"//! Generated Solana program bindings
-
+{}
 {}pub mod accounts;
 pub mod errors;
 pub mod events;
@@ -284,811 +795,3317 @@ pub use errors::*;
 pub use instructions::*;
 pub use types::*;
 
-// Helper function for serde serialization of Pubkey as string
-#[cfg(feature = "serde")]
-pub fn serialize_pubkey_as_string<S>(
-    pubkey: &solana_program::pubkey::Pubkey,
-    serializer: S,
-) -> Result<S::Ok, S::Error>
-where
-    S: serde::Serializer,
-{{
-    serializer.serialize_str(&pubkey.to_string())
-}}
-"#,
-        program_id_declaration
+// Shared with every generated program crate
+pub use solana_idl_codegen_runtime::{{ProgramAccount, ProgramEvent, ProgramInstruction}};
+// The runtime's Solana crate, so `Pubkey` and `AccountInfo` are the ones it (and, with
+// `--anchor`, anchor-lang) uses
+pub use solana_idl_codegen_runtime::solana_program;
+{}{}{}{}"#,
+        description,
+        program_id_declaration,
+        BUILTIN_TYPES_MODULE,
+        SERDE_PUBKEY_MODULE,
+        SERDE_ARRAY_MODULES,
+        SERDE_INT_MODULE
     )
 }
 
-/// Check if a type is an array with more than 32 elements
-/// (serde only supports arrays up to size 32 by default)
-fn is_large_array(ty: &IdlType) -> bool {
-    match ty {
-        IdlType::Array { array } => match array {
-            ArrayType::Tuple((_, size)) => *size > 32,
-        },
-        _ => false,
+/// Types backing IDL types that have no std equivalent, emitted into the generated `lib.rs`.
+///
+/// `u256`/`i256` become `U256`/`I256`, 32 little-endian bytes on the wire, and
+/// `coption` becomes `COption`, whose Borsh tag is a `u32` rather than a `u8`.
+const BUILTIN_TYPES_MODULE: &str = r#"
+macro_rules! wide_int {
+    ($(#[$doc:meta])* $name:ident, $small:ty, $fill:expr) => {
+        $(#[$doc])*
+        #[derive(
+            Clone,
+            Copy,
+            Debug,
+            Default,
+            PartialEq,
+            Eq,
+            Hash,
+            borsh::BorshSerialize,
+            borsh::BorshDeserialize,
+            bytemuck::Pod,
+            bytemuck::Zeroable,
+        )]
+        #[repr(transparent)]
+        pub struct $name(pub [u8; 32]);
+
+        impl $name {
+            pub const fn from_le_bytes(bytes: [u8; 32]) -> Self {
+                Self(bytes)
+            }
+
+            pub const fn to_le_bytes(self) -> [u8; 32] {
+                self.0
+            }
+        }
+
+        impl From<$small> for $name {
+            fn from(value: $small) -> Self {
+                let fill: fn($small) -> u8 = $fill;
+                let mut bytes = [fill(value); 32];
+                bytes[..16].copy_from_slice(&value.to_le_bytes());
+                Self(bytes)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let hex: String = self.0.iter().rev().map(|b| format!("{:02x}", b)).collect();
+                serializer.collect_str(&format_args!("0x{}", hex))
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = <String as serde::Deserialize>::deserialize(deserializer)?;
+                let hex = s.strip_prefix("0x").unwrap_or(&s);
+                if hex.len() != 64 || !hex.is_ascii() {
+                    return Err(serde::de::Error::custom("expected 64 hex digits"));
+                }
+                let mut bytes = [0u8; 32];
+                for (i, byte) in bytes.iter_mut().rev().enumerate() {
+                    *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                        .map_err(serde::de::Error::custom)?;
+                }
+                Ok(Self(bytes))
+            }
+        }
+    };
+}
+
+wide_int!(
+    /// Unsigned 256-bit integer (IDL `u256`), stored as 32 little-endian bytes
+    ///
+    /// Serializes to serde as a `0x`-prefixed big-endian hex string.
+    U256,
+    u128,
+    |_| 0
+);
+
+wide_int!(
+    /// Signed 256-bit two's-complement integer (IDL `i256`), stored as 32 little-endian bytes
+    ///
+    /// Serializes to serde as a `0x`-prefixed big-endian hex string of the raw bits.
+    I256,
+    i128,
+    |v| if v < 0 { 0xff } else { 0 }
+);
+
+/// `Option` with a 4-byte tag (IDL `coption`), as used by SPL programs
+///
+/// Borsh encodes it as a little-endian `u32` tag, 0 for `None` and 1 for `Some`,
+/// followed by the value when present. Serde treats it like `Option`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum COption<T> {
+    #[default]
+    None,
+    Some(T),
+}
+
+impl<T> COption<T> {
+    pub fn is_some(&self) -> bool {
+        matches!(self, COption::Some(_))
+    }
+
+    pub fn is_none(&self) -> bool {
+        matches!(self, COption::None)
+    }
+
+    pub fn as_ref(&self) -> COption<&T> {
+        match self {
+            COption::Some(v) => COption::Some(v),
+            COption::None => COption::None,
+        }
+    }
+
+    pub fn into_option(self) -> Option<T> {
+        self.into()
     }
 }
 
-/// Check if a struct has any large arrays
-fn has_large_arrays_in_struct(fields: &StructFields) -> bool {
-    match fields {
-        StructFields::Named(fields) => fields.iter().any(|f| is_large_array(&f.ty)),
-        StructFields::Tuple(types) => types.iter().any(is_large_array),
+impl<T> From<Option<T>> for COption<T> {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(v) => COption::Some(v),
+            None => COption::None,
+        }
     }
 }
 
-fn generate_type_def(ty: &TypeDef) -> Result<TokenStream> {
-    let name = format_ident!("{}", ty.name);
-    let docs = generate_docs(ty.docs.as_ref());
+impl<T> From<COption<T>> for Option<T> {
+    fn from(value: COption<T>) -> Self {
+        match value {
+            COption::Some(v) => Some(v),
+            COption::None => None,
+        }
+    }
+}
 
-    // Determine serialization type
-    let use_bytemuck = ty
-        .serialization
-        .as_ref()
-        .map(|s| s == "bytemuckunsafe" || s == "bytemuck")
-        .unwrap_or(false);
+impl<T: borsh::BorshSerialize> borsh::BorshSerialize for COption<T> {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        match self {
+            COption::None => borsh::BorshSerialize::serialize(&0u32, writer),
+            COption::Some(v) => {
+                borsh::BorshSerialize::serialize(&1u32, writer)?;
+                v.serialize(writer)
+            }
+        }
+    }
+}
 
-    // Check if type is packed (for repr attribute)
-    let is_packed = ty.repr.as_ref().and_then(|r| r.packed).unwrap_or(false);
+impl<T: borsh::BorshDeserialize> borsh::BorshDeserialize for COption<T> {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        match <u32 as borsh::BorshDeserialize>::deserialize_reader(reader)? {
+            0 => Ok(COption::None),
+            1 => Ok(COption::Some(T::deserialize_reader(reader)?)),
+            tag => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("invalid COption tag: {}", tag),
+            )),
+        }
+    }
+}
 
-    let repr_attr = if use_bytemuck && is_packed {
-        quote! { #[repr(C, packed)] }
-    } else if use_bytemuck {
-        quote! { #[repr(C)] }
-    } else {
-        quote! {}
-    };
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for COption<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&Option::<&T>::from(self.as_ref()), serializer)
+    }
+}
 
-    match &ty.ty {
-        TypeDefType::Struct { fields } => {
-            // Check if this struct has large arrays (> 32 elements)
-            // If so, we can't derive serde automatically
-            let has_large_arrays = has_large_arrays_in_struct(fields);
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for COption<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        <Option<T> as serde::Deserialize>::deserialize(deserializer).map(Into::into)
+    }
+}
+"#;
+
+/// Serde helpers emitted into the generated `lib.rs`.
+///
+/// Generated fields holding `Pubkey`, `Option<Pubkey>`, `Vec<Pubkey>` or
+/// `[Pubkey; N]` point at these functions through `#[serde(with = ...)]`, so
+/// JSON output uses base58 strings. Deserialization also accepts the raw
+/// 32-byte array form for compatibility with previously dumped JSON.
+const SERDE_PUBKEY_MODULE: &str = r#"
+/// Serde helpers that encode `Pubkey` values as base58 strings
+#[cfg(feature = "serde")]
+pub mod serde_pubkey {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use crate::solana_program::pubkey::Pubkey;
+    use std::str::FromStr;
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum PubkeyRepr {
+        Base58(String),
+        Bytes([u8; 32]),
+    }
 
-            match fields {
-                StructFields::Named(fields) => {
-                    let field_tokens: Vec<_> = fields
-                        .iter()
-                        .map(|f| {
-                            let field_name = format_ident!("{}", f.name.to_snake_case());
-                            let field_type = map_idl_type(&f.ty);
-                            let field_docs = generate_docs(f.docs.as_ref());
+    struct AsBase58<'a>(&'a Pubkey);
 
-                            quote! {
-                                #field_docs
-                                pub #field_name: #field_type
-                            }
-                        })
-                        .collect();
+    impl Serialize for AsBase58<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize(self.0, serializer)
+        }
+    }
 
-                    if use_bytemuck {
-                        // For bytemuck types, we need unsafe implementations for Pod and Zeroable
-                        let safety_doc = concat!(
-                            "SAFETY: Pod and Zeroable require unsafe impl because they make guarantees about memory layout.\n",
-                            "This is safe because:\n",
-                            "1. The struct is #[repr(C)] or #[repr(C, packed)], ensuring predictable memory layout\n",
-                            "2. All fields are themselves Pod types (verified by IDL)\n",
-                            "3. No padding bytes contain uninitialized data\n",
-                            "4. The type can be safely transmuted to/from bytes\n",
-                            "\n",
-                            "These traits enable zero-copy deserialization of blockchain account data,\n",
-                            "which is critical for performance when processing large numbers of accounts."
-                        );
-                        Ok(quote! {
-                            #docs
-                            #repr_attr
-                            #[derive(Debug, Clone, Copy, PartialEq)]
-                            pub struct #name {
-                                #(#field_tokens),*
-                            }
+    struct FromBase58(Pubkey);
 
-                            #[doc = #safety_doc]
-                            unsafe impl bytemuck::Pod for #name {}
-                            unsafe impl bytemuck::Zeroable for #name {}
+    impl<'de> Deserialize<'de> for FromBase58 {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserialize(deserializer).map(FromBase58)
+        }
+    }
+
+    pub fn serialize<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(pubkey)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        match PubkeyRepr::deserialize(deserializer)? {
+            PubkeyRepr::Base58(s) => Pubkey::from_str(&s).map_err(serde::de::Error::custom),
+            PubkeyRepr::Bytes(bytes) => Ok(Pubkey::new_from_array(bytes)),
+        }
+    }
+
+    /// `Option<Pubkey>` as an optional base58 string
+    pub mod option {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            value: &Option<Pubkey>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            value.as_ref().map(AsBase58).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Pubkey>, D::Error> {
+            Ok(Option::<FromBase58>::deserialize(deserializer)?.map(|k| k.0))
+        }
+    }
+
+    /// `COption<Pubkey>` as an optional base58 string
+    pub mod coption {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(
+            value: &crate::COption<Pubkey>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            super::option::serialize(&value.into_option(), serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<crate::COption<Pubkey>, D::Error> {
+            super::option::deserialize(deserializer).map(crate::COption::from)
+        }
+    }
+
+    /// `Vec<Pubkey>` as a list of base58 strings
+    pub mod vec {
+        use super::*;
+
+        pub fn serialize<S: Serializer>(value: &[Pubkey], serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(value.iter().map(AsBase58))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<Pubkey>, D::Error> {
+            Ok(Vec::<FromBase58>::deserialize(deserializer)?
+                .into_iter()
+                .map(|k| k.0)
+                .collect())
+        }
+    }
+
+    /// `HashSet<Pubkey>` or `BTreeSet<Pubkey>` as a list of base58 strings
+    pub mod set {
+        use super::*;
+
+        pub fn serialize<'a, S: Serializer, C>(value: &'a C, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            &'a C: IntoIterator<Item = &'a Pubkey>,
+        {
+            serializer.collect_seq(value.into_iter().map(AsBase58))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, C: FromIterator<Pubkey>>(
+            deserializer: D,
+        ) -> Result<C, D::Error> {
+            Ok(super::vec::deserialize(deserializer)?.into_iter().collect())
+        }
+    }
+
+    /// `HashMap<Pubkey, V>` or `BTreeMap<Pubkey, V>` as an object keyed by base58 strings
+    pub mod map {
+        use super::*;
+
+        pub fn serialize<'a, S: Serializer, M, V: Serialize + 'a>(
+            value: &'a M,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            &'a M: IntoIterator<Item = (&'a Pubkey, &'a V)>,
+        {
+            serializer.collect_map(value.into_iter().map(|(k, v)| (AsBase58(k), v)))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, M, V: Deserialize<'de>>(
+            deserializer: D,
+        ) -> Result<M, D::Error>
+        where
+            M: FromIterator<(Pubkey, V)>,
+        {
+            std::collections::BTreeMap::<String, V>::deserialize(deserializer)?
+                .into_iter()
+                .map(|(k, v)| {
+                    Pubkey::from_str(&k)
+                        .map(|k| (k, v))
+                        .map_err(serde::de::Error::custom)
+                })
+                .collect()
+        }
+    }
+
+    /// `[Pubkey; N]` as a list of base58 strings
+    pub mod array {
+        use super::*;
+
+        pub fn serialize<S: Serializer, const N: usize>(
+            value: &[Pubkey; N],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(value.iter().map(AsBase58))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+            deserializer: D,
+        ) -> Result<[Pubkey; N], D::Error> {
+            let keys = super::vec::deserialize(deserializer)?;
+            let len = keys.len();
+            <[Pubkey; N]>::try_from(keys).map_err(|_| {
+                serde::de::Error::invalid_length(len, &format!("an array of {} pubkeys", N).as_str())
+            })
+        }
+    }
+}
+"#;
+
+/// Serde helpers for fixed-size arrays, emitted into the generated `lib.rs`.
+///
+/// `serde_array` covers arrays longer than the 32 elements serde supports
+/// natively. `serde_bytes` covers `[u8; N]`, rendered as a list of numbers by
+/// default or as a hex/base64 string when the generated crate is built with
+/// the `serde-bytes-hex`/`serde-bytes-base64` feature.
+const SERDE_ARRAY_MODULES: &str = r#"
+/// Serde helpers for arrays of any length
+#[cfg(feature = "serde")]
+pub mod serde_array {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer, T: Serialize, const N: usize>(
+        value: &[T; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(value.iter())
+    }
+
+    pub fn deserialize<'de, D, T, const N: usize>(deserializer: D) -> Result<[T; N], D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        let items = Vec::<T>::deserialize(deserializer)?;
+        let len = items.len();
+        <[T; N]>::try_from(items).map_err(|_| {
+            serde::de::Error::invalid_length(len, &format!("an array of {} elements", N).as_str())
+        })
+    }
+}
+
+/// Serde helpers for `[u8; N]`
+///
+/// Byte arrays serialize as a list of numbers by default, as a hex string with the
+/// `serde-bytes-hex` feature, or as a base64 string with the `serde-bytes-base64`
+/// feature (which takes precedence if both are enabled). Deserialization accepts
+/// the list form as well as the string form selected by the enabled feature.
+#[cfg(feature = "serde")]
+pub mod serde_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BytesRepr {
+        Encoded(String),
+        Bytes(Vec<u8>),
+    }
+
+    #[cfg(feature = "serde-bytes-base64")]
+    fn encode(bytes: &[u8]) -> Option<String> {
+        use base64::Engine;
+        Some(base64::engine::general_purpose::STANDARD.encode(bytes))
+    }
+
+    #[cfg(feature = "serde-bytes-base64")]
+    fn decode(encoded: &str) -> Result<Vec<u8>, String> {
+        use base64::Engine;
+        base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .map_err(|e| e.to_string())
+    }
+
+    #[cfg(all(feature = "serde-bytes-hex", not(feature = "serde-bytes-base64")))]
+    fn encode(bytes: &[u8]) -> Option<String> {
+        Some(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    #[cfg(all(feature = "serde-bytes-hex", not(feature = "serde-bytes-base64")))]
+    fn decode(encoded: &str) -> Result<Vec<u8>, String> {
+        let encoded = encoded.strip_prefix("0x").unwrap_or(encoded);
+        if encoded.len() % 2 != 0 {
+            return Err("hex string has an odd number of digits".to_string());
+        }
+        (0..encoded.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&encoded[i..i + 2], 16).map_err(|e| e.to_string()))
+            .collect()
+    }
+
+    #[cfg(not(any(feature = "serde-bytes-hex", feature = "serde-bytes-base64")))]
+    fn encode(_bytes: &[u8]) -> Option<String> {
+        None
+    }
+
+    #[cfg(not(any(feature = "serde-bytes-hex", feature = "serde-bytes-base64")))]
+    fn decode(_encoded: &str) -> Result<Vec<u8>, String> {
+        Err("string-encoded bytes require the serde-bytes-hex or serde-bytes-base64 feature"
+            .to_string())
+    }
+
+    pub fn serialize<S: Serializer, const N: usize>(
+        bytes: &[u8; N],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match encode(bytes) {
+            Some(encoded) => serializer.serialize_str(&encoded),
+            None => serializer.collect_seq(bytes.iter()),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<[u8; N], D::Error> {
+        let bytes = match BytesRepr::deserialize(deserializer)? {
+            BytesRepr::Encoded(encoded) => decode(&encoded).map_err(serde::de::Error::custom)?,
+            BytesRepr::Bytes(bytes) => bytes,
+        };
+        let len = bytes.len();
+        <[u8; N]>::try_from(bytes).map_err(|_| {
+            serde::de::Error::invalid_length(len, &format!("{} bytes", N).as_str())
+        })
+    }
+}
+"#;
+
+/// Serde helpers for 64-bit and 128-bit integers, emitted into the generated `lib.rs`.
+///
+/// JavaScript loses precision above 2^53, so with the `serde-int-string` feature
+/// these integers serialize as decimal strings and deserialize from either form.
+const SERDE_INT_MODULE: &str = r#"
+/// Serde helpers for `u64`, `i64`, `u128` and `i128` fields
+///
+/// Without the `serde-int-string` feature these are plain numbers. With it, values
+/// serialize as decimal strings and deserialize from either a number or a string.
+#[cfg(feature = "serde")]
+pub mod serde_int {
+    use serde::de::DeserializeOwned;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    /// Integer types handled by these helpers
+    pub trait Int:
+        Copy
+        + Display
+        + FromStr
+        + Serialize
+        + DeserializeOwned
+        + TryFrom<u64>
+        + TryFrom<i64>
+        + TryFrom<u128>
+        + TryFrom<i128>
+    {
+    }
+
+    impl<T> Int for T where
+        T: Copy
+            + Display
+            + FromStr
+            + Serialize
+            + DeserializeOwned
+            + TryFrom<u64>
+            + TryFrom<i64>
+            + TryFrom<u128>
+            + TryFrom<i128>
+    {
+    }
+
+    pub fn serialize<S: Serializer, T: Int>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        if cfg!(feature = "serde-int-string") {
+            serializer.collect_str(value)
+        } else {
+            value.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: Int>(deserializer: D) -> Result<T, D::Error> {
+        if cfg!(feature = "serde-int-string") {
+            deserializer.deserialize_any(IntVisitor(std::marker::PhantomData))
+        } else {
+            T::deserialize(deserializer)
+        }
+    }
+
+    struct IntVisitor<T>(std::marker::PhantomData<T>);
+
+    impl<T: Int> serde::de::Visitor<'_> for IntVisitor<T> {
+        type Value = T;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("an integer or a decimal string")
+        }
+
+        fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<T, E> {
+            T::try_from(v).map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
+        }
+
+        fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<T, E> {
+            T::try_from(v).map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(v), &self))
+        }
+
+        fn visit_u128<E: serde::de::Error>(self, v: u128) -> Result<T, E> {
+            T::try_from(v).map_err(|_| E::custom(format!("integer {} out of range", v)))
+        }
+
+        fn visit_i128<E: serde::de::Error>(self, v: i128) -> Result<T, E> {
+            T::try_from(v).map_err(|_| E::custom(format!("integer {} out of range", v)))
+        }
+
+        fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<T, E> {
+            v.trim()
+                .parse()
+                .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self))
+        }
+    }
+
+    struct AsInt<'a, T>(&'a T);
+
+    impl<T: Int> Serialize for AsInt<'_, T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            super::serde_int::serialize(self.0, serializer)
+        }
+    }
+
+    struct FromInt<T>(T);
+
+    impl<'de, T: Int> Deserialize<'de> for FromInt<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            super::serde_int::deserialize(deserializer).map(FromInt)
+        }
+    }
+
+    pub mod option {
+        use super::{AsInt, FromInt, Int};
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer, T: Int>(
+            value: &Option<T>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match value {
+                Some(v) => serializer.serialize_some(&AsInt(v)),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, T: Int>(
+            deserializer: D,
+        ) -> Result<Option<T>, D::Error> {
+            Ok(Option::<FromInt<T>>::deserialize(deserializer)?.map(|FromInt(v)| v))
+        }
+    }
+
+    pub mod coption {
+        use super::Int;
+        use serde::{Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer, T: Int>(
+            value: &crate::COption<T>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            super::option::serialize(&value.into_option(), serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, T: Int>(
+            deserializer: D,
+        ) -> Result<crate::COption<T>, D::Error> {
+            super::option::deserialize(deserializer).map(crate::COption::from)
+        }
+    }
+
+    pub mod vec {
+        use super::{AsInt, FromInt, Int};
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer, T: Int>(
+            value: &[T],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(value.iter().map(AsInt))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, T: Int>(
+            deserializer: D,
+        ) -> Result<Vec<T>, D::Error> {
+            Ok(Vec::<FromInt<T>>::deserialize(deserializer)?
+                .into_iter()
+                .map(|FromInt(v)| v)
+                .collect())
+        }
+    }
+
+    pub mod array {
+        use super::{AsInt, Int};
+        use serde::{Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer, T: Int, const N: usize>(
+            value: &[T; N],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(value.iter().map(AsInt))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, T: Int, const N: usize>(
+            deserializer: D,
+        ) -> Result<[T; N], D::Error> {
+            let items = super::vec::deserialize::<D, T>(deserializer)?;
+            let len = items.len();
+            <[T; N]>::try_from(items).map_err(|_| {
+                serde::de::Error::invalid_length(len, &format!("an array of {} elements", N).as_str())
+            })
+        }
+    }
+
+    /// Values that may hold 64/128-bit integers at any depth
+    ///
+    /// Containers delegate to their elements (map keys included), so `Option<Vec<u64>>`,
+    /// `HashMap<String, u128>` or a generic `Pair<u64>` all render their integers like
+    /// a bare field would. `Pubkey`s inside go through `serde_pubkey`.
+    pub trait Repr: Sized {
+        fn serialize_repr<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+        fn deserialize_repr<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+    }
+
+    /// Types without integers of their own to convert; generated types implement this
+    pub trait Plain: Serialize + DeserializeOwned {}
+
+    impl<T: Plain> Repr for T {
+        fn serialize_repr<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.serialize(serializer)
+        }
+
+        fn deserialize_repr<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            T::deserialize(deserializer)
+        }
+    }
+
+    impl Plain for bool {}
+    impl Plain for u8 {}
+    impl Plain for u16 {}
+    impl Plain for u32 {}
+    impl Plain for i8 {}
+    impl Plain for i16 {}
+    impl Plain for i32 {}
+    impl Plain for f32 {}
+    impl Plain for f64 {}
+    impl Plain for String {}
+    impl Plain for crate::U256 {}
+    impl Plain for crate::I256 {}
+
+    macro_rules! wide_int_repr {
+        ($($ty:ty),*) => {$(
+            impl Repr for $ty {
+                fn serialize_repr<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serialize(self, serializer)
+                }
+
+                fn deserialize_repr<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    deserialize(deserializer)
+                }
+            }
+        )*};
+    }
+
+    wide_int_repr!(u64, i64, u128, i128);
+
+    impl Repr for crate::solana_program::pubkey::Pubkey {
+        fn serialize_repr<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            crate::serde_pubkey::serialize(self, serializer)
+        }
+
+        fn deserialize_repr<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            crate::serde_pubkey::deserialize(deserializer)
+        }
+    }
+
+    struct AsRepr<'a, T>(&'a T);
+
+    impl<T: Repr> Serialize for AsRepr<'_, T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.serialize_repr(serializer)
+        }
+    }
+
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash)]
+    struct FromRepr<T>(T);
+
+    impl<'de, T: Repr> Deserialize<'de> for FromRepr<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            T::deserialize_repr(deserializer).map(FromRepr)
+        }
+    }
+
+    // Boxes only wrap generated (recursive) types, which already render their own fields
+    impl<T: Plain> Plain for Box<T> {}
+
+    impl<T: Repr> Repr for Option<T> {
+        fn serialize_repr<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match self {
+                Some(v) => serializer.serialize_some(&AsRepr(v)),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        fn deserialize_repr<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Ok(Option::<FromRepr<T>>::deserialize(deserializer)?.map(|FromRepr(v)| v))
+        }
+    }
+
+    impl<T: Repr + Clone> Repr for crate::COption<T> {
+        fn serialize_repr<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.clone().into_option().serialize_repr(serializer)
+        }
+
+        fn deserialize_repr<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Option::<T>::deserialize_repr(deserializer).map(crate::COption::from)
+        }
+    }
+
+    impl<T: Repr> Repr for Vec<T> {
+        fn serialize_repr<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.iter().map(AsRepr))
+        }
+
+        fn deserialize_repr<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Ok(Vec::<FromRepr<T>>::deserialize(deserializer)?
+                .into_iter()
+                .map(|FromRepr(v)| v)
+                .collect())
+        }
+    }
+
+    impl<T: Repr, const N: usize> Repr for [T; N] {
+        fn serialize_repr<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.iter().map(AsRepr))
+        }
+
+        fn deserialize_repr<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let items = Vec::<T>::deserialize_repr(deserializer)?;
+            let len = items.len();
+            <[T; N]>::try_from(items).map_err(|_| {
+                serde::de::Error::invalid_length(len, &format!("an array of {} elements", N).as_str())
+            })
+        }
+    }
+
+    impl<T: Repr + Eq + std::hash::Hash> Repr for std::collections::HashSet<T> {
+        fn serialize_repr<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.iter().map(AsRepr))
+        }
+
+        fn deserialize_repr<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Vec::<T>::deserialize_repr(deserializer).map(|items| items.into_iter().collect())
+        }
+    }
+
+    impl<T: Repr + Ord> Repr for std::collections::BTreeSet<T> {
+        fn serialize_repr<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(self.iter().map(AsRepr))
+        }
+
+        fn deserialize_repr<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Vec::<T>::deserialize_repr(deserializer).map(|items| items.into_iter().collect())
+        }
+    }
+
+    impl<K: Repr + Eq + std::hash::Hash, V: Repr> Repr for std::collections::HashMap<K, V> {
+        fn serialize_repr<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_map(self.iter().map(|(k, v)| (AsRepr(k), AsRepr(v))))
+        }
+
+        fn deserialize_repr<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Ok(
+                std::collections::HashMap::<FromRepr<K>, FromRepr<V>>::deserialize(deserializer)?
+                    .into_iter()
+                    .map(|(FromRepr(k), FromRepr(v))| (k, v))
+                    .collect(),
+            )
+        }
+    }
+
+    impl<K: Repr + Ord, V: Repr> Repr for std::collections::BTreeMap<K, V> {
+        fn serialize_repr<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_map(self.iter().map(|(k, v)| (AsRepr(k), AsRepr(v))))
+        }
+
+        fn deserialize_repr<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Ok(
+                std::collections::BTreeMap::<FromRepr<K>, FromRepr<V>>::deserialize(deserializer)?
+                    .into_iter()
+                    .map(|(FromRepr(k), FromRepr(v))| (k, v))
+                    .collect(),
+            )
+        }
+    }
+
+    /// `#[serde(with)]` entry point for fields whose integers are nested in containers
+    /// or generic types
+    pub mod repr {
+        use super::Repr;
+        use serde::{Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer, T: Repr>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+            value.serialize_repr(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, T: Repr>(deserializer: D) -> Result<T, D::Error> {
+            T::deserialize_repr(deserializer)
+        }
+    }
+}
+"#;
+
+/// Check if a type is an array with more than 32 elements
+/// (serde only supports arrays up to size 32 by default)
+///
+/// Arrays with a const-generic length count as large, since serde's built-in
+/// impls don't cover an arbitrary `N`.
+fn is_large_array(ty: &IdlType) -> bool {
+    match ty {
+        IdlType::Array { array } => match array {
+            ArrayType::Tuple((_, size)) => *size > 32,
+            ArrayType::Generic(_) => true,
+        },
+        _ => false,
+    }
+}
+
+/// Check if a type contains a large array anywhere (including at the top level)
+fn contains_large_array(ty: &IdlType) -> bool {
+    match ty {
+        IdlType::Array {
+            array: ArrayType::Tuple((inner, size)),
+        } => *size > 32 || contains_large_array(inner),
+        IdlType::Array {
+            array: ArrayType::Generic(_),
+        } => true,
+        IdlType::Vec { vec: inner }
+        | IdlType::Option { option: inner }
+        | IdlType::COption { coption: inner }
+        | IdlType::HashSet { hash_set: inner }
+        | IdlType::BTreeSet { btree_set: inner } => contains_large_array(inner),
+        IdlType::HashMap {
+            hash_map: (key, value),
+        }
+        | IdlType::BTreeMap {
+            btree_map: (key, value),
+        } => contains_large_array(key) || contains_large_array(value),
+        _ => false,
+    }
+}
+
+/// Check if a type has a large array that `serde_field_attr` can't route through a helper
+///
+/// Top-level arrays of any length are handled by the generated `serde_array`,
+/// `serde_bytes` and `serde_pubkey` helpers; only arrays nested inside another
+/// container are left without a serde implementation.
+fn has_unsupported_large_array(ty: &IdlType) -> bool {
+    match ty {
+        IdlType::Array { array } => contains_large_array(array.element()),
+        IdlType::Simple(_) | IdlType::Defined { .. } | IdlType::Generic { .. } => false,
+        _ => contains_large_array(ty),
+    }
+}
+
+/// Check if a struct has any large arrays serde can't handle
+fn has_large_arrays_in_struct(fields: &StructFields) -> bool {
+    match fields {
+        StructFields::Named(fields) => fields.iter().any(|f| has_unsupported_large_array(&f.ty)),
+        StructFields::Tuple(types) => types.iter().any(has_unsupported_large_array),
+    }
+}
+
+/// Check if an enum has any large arrays serde can't handle
+fn has_large_arrays_in_enum(variants: &[EnumVariant]) -> bool {
+    variants.iter().any(|v| match &v.fields {
+        Some(EnumFields::Named(fields)) => {
+            fields.iter().any(|f| has_unsupported_large_array(&f.ty))
+        }
+        Some(EnumFields::Tuple(types)) => types.iter().any(has_unsupported_large_array),
+        None => false,
+    })
+}
+
+/// Check if a type is a Pubkey
+fn is_pubkey_type(ty: &IdlType) -> bool {
+    match ty {
+        IdlType::Simple(s) => matches!(s.as_str(), "publicKey" | "pubkey" | "Pubkey"),
+        _ => false,
+    }
+}
+
+/// Check if a type is a single byte
+fn is_u8_type(ty: &IdlType) -> bool {
+    matches!(ty, IdlType::Simple(s) if s == "u8")
+}
+
+/// Check if a type is a 64-bit or 128-bit integer, which JSON can't hold losslessly
+fn is_wide_int_type(ty: &IdlType) -> bool {
+    matches!(ty, IdlType::Simple(s) if matches!(s.as_str(), "u64" | "i64" | "u128" | "i128"))
+}
+
+/// Check if a wide integer or a generic type parameter appears anywhere in a type,
+/// including the generic arguments of defined types
+fn needs_int_repr(ty: &IdlType) -> bool {
+    match ty {
+        IdlType::Simple(_) => is_wide_int_type(ty),
+        IdlType::Generic { .. } => true,
+        IdlType::Vec { vec: inner }
+        | IdlType::Option { option: inner }
+        | IdlType::COption { coption: inner }
+        | IdlType::HashSet { hash_set: inner }
+        | IdlType::BTreeSet { btree_set: inner } => needs_int_repr(inner),
+        IdlType::HashMap {
+            hash_map: (key, value),
+        }
+        | IdlType::BTreeMap {
+            btree_map: (key, value),
+        } => needs_int_repr(key) || needs_int_repr(value),
+        IdlType::Array { array } => needs_int_repr(array.element()),
+        IdlType::Defined { defined } => match defined {
+            DefinedTypeOrString::Nested(DefinedType {
+                generics: Some(args),
+                ..
+            }) => args
+                .iter()
+                .any(|arg| matches!(arg, GenericArg::Type { ty } if needs_int_repr(ty))),
+            _ => false,
+        },
+    }
+}
+
+/// Build the `#[serde(with = ...)]` attribute for a field, if its type needs one
+///
+/// Pubkeys (bare, optional, in a Vec or in a fixed array) are routed through the
+/// generated `serde_pubkey` helpers so JSON uses base58 strings in both directions.
+/// Byte arrays go through `serde_bytes` (array, hex or base64 depending on the
+/// crate features) and other arrays longer than 32 through `serde_array`.
+/// 64/128-bit integers go through `serde_int` so the `serde-int-string` feature can
+/// render them as strings: the common shapes through dedicated helpers, anything
+/// nested deeper (map values, `Option<Vec<u64>>`, `Pair<u64>`, generic parameters)
+/// through `serde_int::repr`. Only use this on items that derive serde.
+fn serde_field_attr(ty: &IdlType) -> TokenStream {
+    let helper = match ty {
+        ty if is_pubkey_type(ty) => "crate::serde_pubkey",
+        IdlType::Option { option } if is_pubkey_type(option) => "crate::serde_pubkey::option",
+        IdlType::COption { coption } if is_pubkey_type(coption) => "crate::serde_pubkey::coption",
+        IdlType::Vec { vec } if is_pubkey_type(vec) => "crate::serde_pubkey::vec",
+        IdlType::HashSet { hash_set: inner } | IdlType::BTreeSet { btree_set: inner }
+            if is_pubkey_type(inner) =>
+        {
+            "crate::serde_pubkey::set"
+        }
+        IdlType::HashMap {
+            hash_map: (key, value),
+        }
+        | IdlType::BTreeMap {
+            btree_map: (key, value),
+        } if is_pubkey_type(key) && !needs_int_repr(value) => "crate::serde_pubkey::map",
+        IdlType::Array { array } if is_pubkey_type(array.element()) => "crate::serde_pubkey::array",
+        ty if is_wide_int_type(ty) => "crate::serde_int",
+        IdlType::Option { option } if is_wide_int_type(option) => "crate::serde_int::option",
+        IdlType::COption { coption } if is_wide_int_type(coption) => "crate::serde_int::coption",
+        IdlType::Vec { vec } if is_wide_int_type(vec) => "crate::serde_int::vec",
+        IdlType::Array { array } if is_wide_int_type(array.element()) => "crate::serde_int::array",
+        IdlType::Array { array } if is_u8_type(array.element()) => "crate::serde_bytes",
+        ty if needs_int_repr(ty) => "crate::serde_int::repr",
+        ty if is_large_array(ty) => "crate::serde_array",
+        _ => return TokenStream::new(),
+    };
+    quote! {
+        #[cfg_attr(feature = "serde", serde(with = #helper))]
+    }
+}
+
+/// Rust keywords (strict and reserved, across editions) that can't be plain identifiers
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Turn an already-cased IDL name into a valid Rust identifier
+///
+/// Keywords become raw identifiers (`r#type`), except those that can't be raw
+/// (`self`, `Self`, `super`, `crate`), which get a trailing underscore. Names
+/// starting with a digit get a leading underscore.
+fn rust_ident(name: &str) -> Ident {
+    if name.is_empty() {
+        return format_ident!("_");
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        return format_ident!("_{}", name);
+    }
+    match name {
+        "self" | "Self" | "super" | "crate" => format_ident!("{}_", name),
+        _ if RUST_KEYWORDS.contains(&name) => format_ident!("r#{}", name),
+        _ => format_ident!("{}", name),
+    }
+}
+
+/// Field, argument and account identifier for an IDL name
+fn snake_ident(name: &str) -> Ident {
+    rust_ident(&name.to_snake_case())
+}
+
+/// Variant identifier for an IDL name
+fn pascal_ident(name: &str) -> Ident {
+    rust_ident(&name.to_pascal_case())
+}
+
+/// Constant identifier for an IDL name
+fn shouty_ident(name: &str) -> Ident {
+    rust_ident(&name.to_shouty_snake_case())
+}
+
+/// Keep the serde name of a field or variant whose identifier had to be altered
+///
+/// `cased` is the snake/pascal-cased IDL name the item would normally serialize as.
+/// Raw identifiers need nothing since serde already strips the `r#` prefix.
+fn serde_rename_attr(cased: &str) -> TokenStream {
+    if rust_ident(cased).to_string().trim_start_matches("r#") == cased {
+        return TokenStream::new();
+    }
+    quote! {
+        #[cfg_attr(feature = "serde", serde(rename = #cased))]
+    }
+}
+
+/// Fail when two IDL names map to the same Rust identifier
+///
+/// `kind` and `owner` only feed the error message, e.g. "field" in "type `Pool`".
+fn check_ident_collisions<'a>(
+    kind: &str,
+    owner: &str,
+    names: impl IntoIterator<Item = &'a str>,
+    to_ident: fn(&str) -> Ident,
+) -> Result<()> {
+    let mut seen: std::collections::HashMap<String, &str> = std::collections::HashMap::new();
+    for name in names {
+        let ident = to_ident(name).to_string();
+        if ident == "_" {
+            anyhow::bail!(
+                "{} `{}` in {} has no characters usable in a Rust identifier",
+                kind,
+                name,
+                owner
+            );
+        }
+        if let Some(previous) = seen.insert(ident.clone(), name) {
+            anyhow::bail!(
+                "{} names `{}` and `{}` in {} both map to the Rust identifier `{}`",
+                kind,
+                previous,
+                name,
+                owner,
+                ident
+            );
+        }
+    }
+    Ok(())
+}
+
+/// `boxed` lists the types this one reaches by value through a cycle; references to
+/// them are wrapped in `Box` (see [`map_field_type`])
+///
+/// Types that derive serde also implement `serde_int::Plain`, so they can sit inside
+/// containers or generic types whose integers go through `serde_int::repr`.
+fn generate_type_def(ty: &TypeDef, boxed: &HashSet<&str>) -> Result<TokenStream> {
+    let mut tokens = generate_type_def_items(ty, boxed)?;
+    let derives_serde = match &ty.ty {
+        TypeDefType::Struct { fields } => !has_large_arrays_in_struct(fields),
+        TypeDefType::Enum { variants } => !has_large_arrays_in_enum(variants),
+        TypeDefType::Alias { .. } => false,
+    };
+    if derives_serde {
+        let name = rust_ident(&ty.name);
+        let (generics_decl, ty_generics) = type_def_generics(
+            ty.generics.as_ref(),
+            Some(quote! { crate::serde_int::Repr }),
+        );
+        tokens.extend(quote! {
+            #[cfg(feature = "serde")]
+            impl #generics_decl crate::serde_int::Plain for #name #ty_generics {}
+        });
+    }
+    Ok(tokens)
+}
+
+fn generate_type_def_items(ty: &TypeDef, boxed: &HashSet<&str>) -> Result<TokenStream> {
+    let name = rust_ident(&ty.name);
+    let owner = format!("type `{}`", ty.name);
+    let (generics_decl, ty_generics) = type_def_generics(ty.generics.as_ref(), None);
+    let (pod_generics, _) = type_def_generics(ty.generics.as_ref(), Some(quote! { bytemuck::Pod }));
+    let serde_bound = serde_generic_bound(ty.generics.as_ref());
+    let docs = generate_docs(ty.docs.as_ref());
+
+    // Determine serialization type
+    let use_bytemuck = ty
+        .serialization
+        .as_ref()
+        .map(|s| s == "bytemuckunsafe" || s == "bytemuck")
+        .unwrap_or(false);
+
+    // Check if type is packed (for repr attribute)
+    let is_packed = ty.repr.as_ref().and_then(|r| r.packed).unwrap_or(false);
+
+    // bytemuck types are reinterpreted in place and can't hold a `Box`
+    let no_boxing = HashSet::new();
+    let boxed = if use_bytemuck { &no_boxing } else { boxed };
+
+    let repr_attr = if use_bytemuck && is_packed {
+        quote! { #[repr(C, packed)] }
+    } else if use_bytemuck {
+        quote! { #[repr(C)] }
+    } else {
+        quote! {}
+    };
+
+    match &ty.ty {
+        TypeDefType::Struct { fields } => {
+            // Large arrays (> 32 elements) at the top level of a field go through
+            // the serde helpers; only nested ones prevent deriving serde
+            let derives_serde = !has_large_arrays_in_struct(fields);
+            let serde_derive = if derives_serde {
+                quote! {
+                    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+                    #serde_bound
+                }
+            } else {
+                TokenStream::new()
+            };
+
+            match fields {
+                StructFields::Named(fields) => {
+                    check_ident_collisions(
+                        "field",
+                        &owner,
+                        fields.iter().map(|f| f.name.as_str()),
+                        snake_ident,
+                    )?;
+                    let field_tokens: Vec<_> = fields
+                        .iter()
+                        .map(|f| {
+                            let field_name = snake_ident(&f.name);
+                            let field_type = map_field_type(&f.ty, boxed);
+                            let field_docs = generate_docs(f.docs.as_ref());
+                            let serde_attr = if derives_serde {
+                                let rename = serde_rename_attr(&f.name.to_snake_case());
+                                let with = serde_field_attr(&f.ty);
+                                quote! { #rename #with }
+                            } else {
+                                TokenStream::new()
+                            };
+
+                            quote! {
+                                #field_docs
+                                #serde_attr
+                                pub #field_name: #field_type
+                            }
                         })
-                    } else if has_large_arrays {
-                        // Skip serde for structs with large arrays
+                        .collect();
+
+                    if use_bytemuck {
+                        // For bytemuck types, we need unsafe implementations for Pod and Zeroable
+                        let safety_doc = concat!(
+                            "SAFETY: Pod and Zeroable require unsafe impl because they make guarantees about memory layout.\n",
+                            "This is safe because:\n",
+                            "1. The struct is #[repr(C)] or #[repr(C, packed)], ensuring predictable memory layout\n",
+                            "2. All fields are themselves Pod types (verified by IDL)\n",
+                            "3. No padding bytes contain uninitialized data\n",
+                            "4. The type can be safely transmuted to/from bytes\n",
+                            "\n",
+                            "These traits enable zero-copy deserialization of blockchain account data,\n",
+                            "which is critical for performance when processing large numbers of accounts."
+                        );
                         Ok(quote! {
                             #docs
                             #repr_attr
-                            #[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
-                            pub struct #name {
+                            #[derive(Debug, Clone, Copy, PartialEq)]
+                            #serde_derive
+                            pub struct #name #generics_decl {
                                 #(#field_tokens),*
                             }
+
+                            #[doc = #safety_doc]
+                            unsafe impl #pod_generics bytemuck::Pod for #name #ty_generics {}
+                            unsafe impl #pod_generics bytemuck::Zeroable for #name #ty_generics {}
                         })
                     } else {
                         Ok(quote! {
                             #docs
                             #repr_attr
                             #[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
-                            #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-                            pub struct #name {
+                            #serde_derive
+                            pub struct #name #generics_decl {
                                 #(#field_tokens),*
                             }
                         })
                     }
                 }
-                StructFields::Tuple(types) => {
-                    let field_types: Vec<_> = types.iter().map(map_idl_type).collect();
+                StructFields::Tuple(types) => {
+                    let field_types: Vec<_> = types
+                        .iter()
+                        .map(|t| {
+                            let field_type = map_field_type(t, boxed);
+                            if derives_serde {
+                                let serde_attr = serde_field_attr(t);
+                                quote! { #serde_attr pub #field_type }
+                            } else {
+                                quote! { pub #field_type }
+                            }
+                        })
+                        .collect();
+
+                    if use_bytemuck {
+                        // For bytemuck types, we need unsafe implementations for Pod and Zeroable
+                        let safety_doc = concat!(
+                            "SAFETY: Pod and Zeroable require unsafe impl because they make guarantees about memory layout.\n",
+                            "This is safe because:\n",
+                            "1. The struct is #[repr(C)] or #[repr(C, packed)], ensuring predictable memory layout\n",
+                            "2. All fields are themselves Pod types (verified by IDL)\n",
+                            "3. No padding bytes contain uninitialized data\n",
+                            "4. The type can be safely transmuted to/from bytes\n",
+                            "\n",
+                            "These traits enable zero-copy deserialization of blockchain account data,\n",
+                            "which is critical for performance when processing large numbers of accounts."
+                        );
+                        Ok(quote! {
+                            #docs
+                            #repr_attr
+                            #[derive(Debug, Clone, Copy, PartialEq)]
+                            #serde_derive
+                            pub struct #name #generics_decl (#(#field_types),*);
+
+                            #[doc = #safety_doc]
+                            unsafe impl #pod_generics bytemuck::Pod for #name #ty_generics {}
+                            unsafe impl #pod_generics bytemuck::Zeroable for #name #ty_generics {}
+                        })
+                    } else {
+                        Ok(quote! {
+                            #docs
+                            #repr_attr
+                            #[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
+                            #serde_derive
+                            pub struct #name #generics_decl (#(#field_types),*);
+                        })
+                    }
+                }
+            }
+        }
+        TypeDefType::Alias { alias } => {
+            let target = map_idl_type(alias);
+            Ok(quote! {
+                #docs
+                pub type #name #generics_decl = #target;
+            })
+        }
+        TypeDefType::Enum { variants } => {
+            let derives_serde = !has_large_arrays_in_enum(variants);
+            let serde_derive = if derives_serde {
+                quote! {
+                    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+                    #serde_bound
+                }
+            } else {
+                TokenStream::new()
+            };
+
+            check_ident_collisions(
+                "variant",
+                &owner,
+                variants.iter().map(|v| v.name.as_str()),
+                pascal_ident,
+            )?;
+            for v in variants {
+                if let Some(EnumFields::Named(fields)) = &v.fields {
+                    check_ident_collisions(
+                        "field",
+                        &format!("variant `{}` of {}", v.name, owner),
+                        fields.iter().map(|f| f.name.as_str()),
+                        snake_ident,
+                    )?;
+                }
+                if let Some(value) = v.value {
+                    if value > u8::MAX as u64 {
+                        anyhow::bail!(
+                            "variant `{}` of {} has discriminant {}, which does not fit in a u8",
+                            v.name,
+                            owner,
+                            value
+                        );
+                    }
+                }
+            }
+
+            // Explicit variant values (Shank) become Rust discriminants, and Borsh is
+            // told to write them instead of the variant index
+            let has_explicit_values = variants.iter().any(|v| v.value.is_some());
+            let discriminant_attrs = if has_explicit_values && !use_bytemuck {
+                quote! {
+                    #[repr(u8)]
+                    #[borsh(use_discriminant = true)]
+                }
+            } else {
+                TokenStream::new()
+            };
+
+            let variant_tokens: Vec<_> = variants
+                .iter()
+                .map(|v| {
+                    let variant_name = pascal_ident(&v.name);
+                    let variant_rename = if derives_serde {
+                        serde_rename_attr(&v.name.to_pascal_case())
+                    } else {
+                        TokenStream::new()
+                    };
+                    let variant_tokens = match &v.fields {
+                        Some(EnumFields::Named(fields)) => {
+                            let field_tokens: Vec<_> = fields
+                                .iter()
+                                .map(|f| {
+                                    let field_name = snake_ident(&f.name);
+                                    let field_type = map_field_type(&f.ty, boxed);
+                                    let serde_attr = if derives_serde {
+                                        let rename = serde_rename_attr(&f.name.to_snake_case());
+                                        let with = serde_field_attr(&f.ty);
+                                        quote! { #rename #with }
+                                    } else {
+                                        TokenStream::new()
+                                    };
+                                    quote! { #serde_attr #field_name: #field_type }
+                                })
+                                .collect();
+                            quote! { #variant_name { #(#field_tokens),* } }
+                        }
+                        Some(EnumFields::Tuple(types)) => {
+                            let type_tokens: Vec<_> = types
+                                .iter()
+                                .map(|t| {
+                                    let field_type = map_field_type(t, boxed);
+                                    let serde_attr = if derives_serde {
+                                        serde_field_attr(t)
+                                    } else {
+                                        TokenStream::new()
+                                    };
+                                    quote! { #serde_attr #field_type }
+                                })
+                                .collect();
+                            quote! { #variant_name(#(#type_tokens),*) }
+                        }
+                        None => quote! { #variant_name },
+                    };
+                    let discriminant = v.value.map(|value| {
+                        let value = proc_macro2::Literal::u8_unsuffixed(value as u8);
+                        quote! { = #value }
+                    });
+                    let variant_docs = generate_docs(v.docs.as_ref());
+                    quote! { #variant_docs #variant_rename #variant_tokens #discriminant }
+                })
+                .collect();
+
+            if use_bytemuck {
+                // For bytemuck enums, we need unsafe implementations
+                let safety_doc = concat!(
+                    "SAFETY: Pod and Zeroable require unsafe impl because they make guarantees about memory layout.\n",
+                    "This is safe because:\n",
+                    "1. The enum is #[repr(C)] or #[repr(C, packed)], ensuring predictable memory layout\n",
+                    "2. All variant fields are themselves Pod types (verified by IDL)\n",
+                    "3. No padding bytes contain uninitialized data\n",
+                    "4. The type can be safely transmuted to/from bytes\n",
+                    "\n",
+                    "These traits enable zero-copy deserialization of blockchain account data,\n",
+                    "which is critical for performance when processing large numbers of accounts."
+                );
+                Ok(quote! {
+                    #docs
+                    #repr_attr
+                    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+                    #serde_derive
+                    pub enum #name #generics_decl {
+                        #(#variant_tokens),*
+                    }
+
+                    #[doc = #safety_doc]
+                    unsafe impl #pod_generics bytemuck::Pod for #name #ty_generics {}
+                    unsafe impl #pod_generics bytemuck::Zeroable for #name #ty_generics {}
+                })
+            } else {
+                Ok(quote! {
+                    #docs
+                    #repr_attr
+                    #[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
+                    #discriminant_attrs
+                    #serde_derive
+                    pub enum #name #generics_decl {
+                        #(#variant_tokens),*
+                    }
+                })
+            }
+        }
+    }
+}
+
+fn generate_account(account: &Account, boxed: &HashSet<&str>) -> Result<TokenStream> {
+    // In old format IDLs, accounts can have type definitions
+    // In new format IDLs, they're just references (discriminators added to types directly)
+    if let Some(ty) = &account.ty {
+        let mut tokens = generate_type_def(
+            &TypeDef {
+                name: account.name.clone(),
+                docs: account.docs.clone(),
+                ty: ty.clone(),
+                serialization: None,
+                repr: None,
+                generics: None,
+            },
+            boxed,
+        )?;
+
+        // Add discriminator methods if discriminator is present
+        if let Some(disc) = &account.discriminator {
+            let name = rust_ident(&account.name);
+            let disc_len = proc_macro2::Literal::usize_unsuffixed(disc.len());
+            let disc_bytes = disc.iter().map(|b| quote! { #b });
+
+            tokens.extend(quote! {
+                impl #name {
+                    pub const DISCRIMINATOR: [u8; #disc_len] = [#(#disc_bytes),*];
+
+                    pub fn try_from_slice_with_discriminator(data: &[u8]) -> std::io::Result<Self> {
+                        solana_idl_codegen_runtime::try_from_slice_with_discriminator(&Self::DISCRIMINATOR, data)
+                    }
+
+                    pub fn serialize_with_discriminator<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                        solana_idl_codegen_runtime::serialize_with_discriminator(&Self::DISCRIMINATOR, self, writer)
+                    }
+                }
+            });
+        }
+
+        Ok(tokens)
+    } else {
+        // In new format, accounts are references to types defined elsewhere
+        // Discriminators are added directly to the type definitions
+        Ok(TokenStream::new())
+    }
+}
+
+fn generate_account_validation_helpers(idl: &Idl) -> Result<TokenStream> {
+    let program_id_expr = if let Some(_addr) = idl.get_address() {
+        quote! { crate::ID }
+    } else {
+        return Ok(TokenStream::new()); // Can't validate without program ID
+    };
+
+    // Collect all account types with discriminators
+    let mut account_validations = Vec::new();
+    // Track which account names have already been processed to avoid duplicates
+    let mut processed_accounts = std::collections::HashSet::new();
+
+    // Check accounts from accounts array (old format)
+    if let Some(accounts) = &idl.accounts {
+        for account in accounts {
+            // Only generate validation methods if account has a discriminator
+            // (validation methods reference DISCRIMINATOR and try_from_slice_with_discriminator)
+            if account.discriminator.is_some() {
+                let name = rust_ident(&account.name);
+                let docs = generate_docs(account.docs.as_ref());
+
+                // Track that we've processed this account
+                processed_accounts.insert(account.name.clone());
+
+                account_validations.push(quote! {
+                    #docs
+                    impl #name {
+                        /// Validate that an AccountInfo matches this account type
+                        ///
+                        /// This function checks:
+                        /// - The account owner matches the program ID
+                        /// - The account data starts with the correct discriminator
+                        /// - The account data is long enough to contain the discriminator
+                        ///
+                        /// # Example
+                        /// ```no_run
+                        /// use crate::solana_program::account_info::AccountInfo;
+                        /// use crate::accounts::*;
+                        ///
+                        /// fn validate_account(account_info: &AccountInfo) -> Result<(), ValidationError> {
+                        ///     #name::validate_account_info(account_info)?;
+                        ///     Ok(())
+                        /// }
+                        /// ```
+                        pub fn validate_account_info(
+                            account_info: &crate::solana_program::account_info::AccountInfo,
+                        ) -> Result<(), ValidationError> {
+                            Self::validate_account_info_with_program_id(account_info, &#program_id_expr)
+                        }
+
+                        /// Like `validate_account_info`, for a deployment at `program_id`
+                        pub fn validate_account_info_with_program_id(
+                            account_info: &crate::solana_program::account_info::AccountInfo,
+                            program_id: &crate::solana_program::pubkey::Pubkey,
+                        ) -> Result<(), ValidationError> {
+                            solana_idl_codegen_runtime::validate_account_info::<Self>(account_info, program_id)
+                        }
+
+                        /// Validate and deserialize an account from AccountInfo
+                        ///
+                        /// This is a convenience method that combines validation and deserialization.
+                        ///
+                        /// # Example
+                        /// ```no_run
+                        /// use crate::solana_program::account_info::AccountInfo;
+                        /// use crate::accounts::*;
+                        ///
+                        /// fn load_account(account_info: &AccountInfo) -> Result<#name, ValidationError> {
+                        ///     #name::try_from_account_info(account_info)
+                        /// }
+                        /// ```
+                        pub fn try_from_account_info(
+                            account_info: &crate::solana_program::account_info::AccountInfo,
+                        ) -> Result<Self, ValidationError> {
+                            Self::try_from_account_info_with_program_id(account_info, &#program_id_expr)
+                        }
+
+                        /// Like `try_from_account_info`, for a deployment at `program_id`
+                        pub fn try_from_account_info_with_program_id(
+                            account_info: &crate::solana_program::account_info::AccountInfo,
+                            program_id: &crate::solana_program::pubkey::Pubkey,
+                        ) -> Result<Self, ValidationError> {
+                            solana_idl_codegen_runtime::try_from_account_info(account_info, program_id)
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    // Note: Types with discriminators that are referenced in the accounts array
+    // are already handled above. We don't need to process them again here.
+
+    if account_validations.is_empty() {
+        return Ok(TokenStream::new());
+    }
+
+    Ok(quote! {
+        pub use solana_idl_codegen_runtime::ValidationError;
+
+        #(#account_validations)*
+    })
+}
+
+/// Discriminator bytes identifying an instruction
+///
+/// Explicit `discriminator` bytes win, then a Shank `discriminant`. Without either the
+/// instruction index is used: a `u8` for Shank IDLs, a little-endian `u64` for old
+/// Anchor IDLs.
+pub(crate) fn instruction_discriminator(
+    ix: &Instruction,
+    idx: usize,
+    shank: bool,
+) -> Result<Vec<u8>> {
+    if let Some(disc) = &ix.discriminator {
+        return Ok(disc.clone());
+    }
+    if let Some(discriminant) = &ix.discriminant {
+        return discriminant
+            .to_bytes()
+            .map_err(|e| anyhow::anyhow!("instruction `{}`: {}", ix.name, e));
+    }
+    if shank {
+        let tag = u8::try_from(idx).map_err(|_| {
+            anyhow::anyhow!(
+                "instruction `{}`: index {} does not fit in a u8 tag",
+                ix.name,
+                idx
+            )
+        })?;
+        return Ok(vec![tag]);
+    }
+    Ok((idx as u64).to_le_bytes().to_vec())
+}
+
+fn generate_instructions(
+    instructions: &[Instruction],
+    has_program_id: bool,
+    shank: bool,
+) -> Result<TokenStream> {
+    let mut tokens = TokenStream::new();
+
+    check_ident_collisions(
+        "instruction",
+        "the program",
+        instructions.iter().map(|ix| ix.name.as_str()),
+        pascal_ident,
+    )?;
+    for ix in instructions {
+        let owner = format!("instruction `{}`", ix.name);
+        check_ident_collisions(
+            "argument",
+            &owner,
+            ix.args.iter().map(|a| a.name.as_str()),
+            snake_ident,
+        )?;
+        check_ident_collisions(
+            "account",
+            &owner,
+            ix.accounts.iter().map(|a| a.name.as_str()),
+            snake_ident,
+        )?;
+    }
+
+    let discriminators = instructions
+        .iter()
+        .enumerate()
+        .map(|(idx, ix)| instruction_discriminator(ix, idx, shank))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Generate module-level discriminator constants and IxData wrapper structs for each instruction
+    for (ix, discriminator_bytes) in instructions.iter().zip(&discriminators) {
+        let ix_name_snake = ix.name.to_snake_case();
+        let ix_name_pascal = ix.name.to_pascal_case();
+        let discm_const_name = format_ident!("{}_IX_DISCM", ix_name_snake.to_uppercase());
+        let ix_data_struct = format_ident!("{}IxData", ix_name_pascal);
+
+        let disc_len = proc_macro2::Literal::usize_unsuffixed(discriminator_bytes.len());
+        let disc_bytes = discriminator_bytes.iter().map(|b| quote! { #b });
+
+        // Generate module-level discriminator constant
+        tokens.extend(quote! {
+            pub const #discm_const_name: [u8; #disc_len] = [#(#disc_bytes),*];
+        });
+
+        // Generate IxData wrapper struct
+        if ix.args.is_empty() {
+            // No-args instruction
+            tokens.extend(quote! {
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct #ix_data_struct;
+
+                impl #ix_data_struct {
+                    pub fn deserialize(buf: &[u8]) -> std::io::Result<Self> {
+                        solana_idl_codegen_runtime::strip_discriminator(&#discm_const_name, buf)?;
+                        Ok(Self)
+                    }
+
+                    pub fn serialize<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+                        writer.write_all(&#discm_const_name)
+                    }
+
+                    pub fn try_to_vec(&self) -> std::io::Result<Vec<u8>> {
+                        let mut data = Vec::new();
+                        self.serialize(&mut data)?;
+                        Ok(data)
+                    }
+                }
+            });
+        } else {
+            // Instruction with args
+            let args_struct = format_ident!("{}IxArgs", ix_name_pascal);
 
-                    if use_bytemuck {
-                        // For bytemuck types, we need unsafe implementations for Pod and Zeroable
-                        let safety_doc = concat!(
-                            "SAFETY: Pod and Zeroable require unsafe impl because they make guarantees about memory layout.\n",
-                            "This is safe because:\n",
-                            "1. The struct is #[repr(C)] or #[repr(C, packed)], ensuring predictable memory layout\n",
-                            "2. All fields are themselves Pod types (verified by IDL)\n",
-                            "3. No padding bytes contain uninitialized data\n",
-                            "4. The type can be safely transmuted to/from bytes\n",
-                            "\n",
-                            "These traits enable zero-copy deserialization of blockchain account data,\n",
-                            "which is critical for performance when processing large numbers of accounts."
-                        );
-                        Ok(quote! {
-                            #docs
-                            #repr_attr
-                            #[derive(Debug, Clone, Copy, PartialEq)]
-                            pub struct #name(#(pub #field_types),*);
+            tokens.extend(quote! {
+                #[derive(Clone, Debug, PartialEq)]
+                pub struct #ix_data_struct(pub #args_struct);
 
-                            #[doc = #safety_doc]
-                            unsafe impl bytemuck::Pod for #name {}
-                            unsafe impl bytemuck::Zeroable for #name {}
-                        })
-                    } else if has_large_arrays {
-                        // Skip serde for structs with large arrays
-                        Ok(quote! {
-                            #docs
-                            #repr_attr
-                            #[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
-                            pub struct #name(#(pub #field_types),*);
-                        })
-                    } else {
-                        Ok(quote! {
-                            #docs
-                            #repr_attr
-                            #[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
-                            #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-                            pub struct #name(#(pub #field_types),*);
-                        })
+                impl From<#args_struct> for #ix_data_struct {
+                    fn from(args: #args_struct) -> Self {
+                        Self(args)
+                    }
+                }
+
+                impl #ix_data_struct {
+                    pub fn deserialize(mut buf: &[u8]) -> std::io::Result<Self> {
+                        solana_idl_codegen_runtime::deserialize_with_discriminator(
+                            &#discm_const_name,
+                            &mut buf,
+                        )
+                        .map(Self)
+                    }
+
+                    pub fn serialize<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+                        solana_idl_codegen_runtime::serialize_with_discriminator(
+                            &#discm_const_name,
+                            &self.0,
+                            &mut writer,
+                        )
+                    }
+
+                    pub fn try_to_vec(&self) -> std::io::Result<Vec<u8>> {
+                        let mut data = Vec::new();
+                        self.serialize(&mut data)?;
+                        Ok(data)
+                    }
+                }
+            });
+        }
+    }
+
+    // Generate instruction enum
+    let instruction_variants: Vec<_> = instructions
+        .iter()
+        .map(|ix| {
+            let variant_name = pascal_ident(&ix.name);
+            if ix.args.is_empty() {
+                quote! { #variant_name }
+            } else {
+                let args_struct = format_ident!("{}IxArgs", ix.name.to_pascal_case());
+                quote! { #variant_name(#args_struct) }
+            }
+        })
+        .collect();
+
+    // Generate discriminator match arms for serialization
+    let serialize_arms: Vec<_> = instructions
+        .iter()
+        .map(|ix| {
+            let variant_name = pascal_ident(&ix.name);
+            let discm_const_name =
+                format_ident!("{}_IX_DISCM", ix.name.to_snake_case().to_uppercase());
+
+            if ix.args.is_empty() {
+                quote! {
+                    Self::#variant_name => {
+                        writer.write_all(&#discm_const_name)?;
+                        Ok(())
+                    }
+                }
+            } else {
+                quote! {
+                    Self::#variant_name(args) => {
+                        writer.write_all(&#discm_const_name)?;
+                        args.serialize(writer)
+                    }
+                }
+            }
+        })
+        .collect();
+
+    // Generate discriminator match for deserialization
+    // Discriminators may differ in length, so each arm matches a prefix of the data
+    let deserialize_arms: Vec<_> = instructions
+        .iter()
+        .zip(&discriminators)
+        .map(|(ix, discriminator_bytes)| {
+            let variant_name = pascal_ident(&ix.name);
+            let disc_pattern = discriminator_bytes.iter().map(|b| quote! { #b });
+
+            if ix.args.is_empty() {
+                quote! {
+                    [#(#disc_pattern,)* ..] => Ok(Self::#variant_name)
+                }
+            } else {
+                let args_struct = format_ident!("{}IxArgs", ix.name.to_pascal_case());
+                quote! {
+                    [#(#disc_pattern,)* rest @ ..] => {
+                        let mut buf = rest;
+                        let args = #args_struct::deserialize(&mut buf)?;
+                        Ok(Self::#variant_name(args))
                     }
                 }
             }
+        })
+        .collect();
+    let min_disc_len = proc_macro2::Literal::usize_unsuffixed(
+        discriminators.iter().map(Vec::len).min().unwrap_or(0),
+    );
+
+    tokens.extend(quote! {
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum Instruction {
+            #(#instruction_variants),*
         }
-        TypeDefType::Enum { variants } => {
-            let variant_tokens: Vec<_> = variants
+
+        impl Instruction {
+            pub fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                match self {
+                    #(#serialize_arms),*
+                }
+            }
+
+            pub fn try_from_slice(data: &[u8]) -> std::io::Result<Self> {
+                if data.len() < #min_disc_len {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Data too short for instruction discriminator",
+                    ));
+                }
+
+                use borsh::BorshDeserialize;
+
+                match data {
+                    #(#deserialize_arms),*,
+                    _ => Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Unknown instruction discriminator",
+                    )),
+                }
+            }
+        }
+    });
+
+    // Generate args structs for each instruction
+    for ix in instructions {
+        if !ix.args.is_empty() {
+            let args_struct = format_ident!("{}IxArgs", ix.name.to_pascal_case());
+            let field_tokens: Vec<_> = ix
+                .args
                 .iter()
-                .map(|v| {
-                    let variant_name = format_ident!("{}", v.name.to_pascal_case());
-                    match &v.fields {
-                        Some(EnumFields::Named(fields)) => {
-                            let field_tokens: Vec<_> = fields
-                                .iter()
-                                .map(|f| {
-                                    let field_name = format_ident!("{}", f.name.to_snake_case());
-                                    let field_type = map_idl_type(&f.ty);
-                                    quote! { #field_name: #field_type }
-                                })
-                                .collect();
-                            quote! { #variant_name { #(#field_tokens),* } }
-                        }
-                        Some(EnumFields::Tuple(types)) => {
-                            let type_tokens: Vec<_> = types.iter().map(map_idl_type).collect();
-                            quote! { #variant_name(#(#type_tokens),*) }
-                        }
-                        None => quote! { #variant_name },
+                .map(|arg| {
+                    let field_name = snake_ident(&arg.name);
+                    let field_type = map_idl_type(&arg.ty);
+                    let field_docs = generate_docs(arg.docs.as_ref());
+                    let serde_rename = serde_rename_attr(&arg.name.to_snake_case());
+                    let serde_attr = serde_field_attr(&arg.ty);
+                    quote! {
+                        #field_docs
+                        #serde_rename
+                        #serde_attr
+                        pub #field_name: #field_type
+                    }
+                })
+                .collect();
+
+            tokens.extend(quote! {
+                #[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
+                #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+                pub struct #args_struct {
+                    #(#field_tokens),*
+                }
+            });
+        }
+
+        // Generate Keys struct for each instruction (for building instructions)
+        let keys_struct = format_ident!("{}Keys", ix.name.to_pascal_case());
+        let account_fields: Vec<_> = ix
+            .accounts
+            .iter()
+            .map(|acc| {
+                let field_name = snake_ident(&acc.name);
+                let docs = generate_docs(acc.docs.as_ref());
+                quote! {
+                    #docs
+                    pub #field_name: Pubkey
+                }
+            })
+            .collect();
+
+        // Generate const for accounts length
+        let accounts_len_const =
+            format_ident!("{}_IX_ACCOUNTS_LEN", ix.name.to_snake_case().to_uppercase());
+        let accounts_len = ix.accounts.len();
+
+        tokens.extend(quote! {
+            pub const #accounts_len_const: usize = #accounts_len;
+
+            #[derive(Debug, Clone, PartialEq)]
+            pub struct #keys_struct {
+                #(#account_fields),*
+            }
+        });
+
+        // Generate From<Keys> for [AccountMeta; N] conversion
+        let account_metas: Vec<_> = ix
+            .accounts
+            .iter()
+            .map(|acc| {
+                let field_name = snake_ident(&acc.name);
+                let is_signer = acc.signer;
+                let is_writable = acc.writable;
+                quote! {
+                    AccountMeta {
+                        pubkey: keys.#field_name,
+                        is_signer: #is_signer,
+                        is_writable: #is_writable,
                     }
-                })
-                .collect();
+                }
+            })
+            .collect();
 
-            if use_bytemuck {
-                // For bytemuck enums, we need unsafe implementations
-                let safety_doc = concat!(
-                    "SAFETY: Pod and Zeroable require unsafe impl because they make guarantees about memory layout.\n",
-                    "This is safe because:\n",
-                    "1. The enum is #[repr(C)] or #[repr(C, packed)], ensuring predictable memory layout\n",
-                    "2. All variant fields are themselves Pod types (verified by IDL)\n",
-                    "3. No padding bytes contain uninitialized data\n",
-                    "4. The type can be safely transmuted to/from bytes\n",
-                    "\n",
-                    "These traits enable zero-copy deserialization of blockchain account data,\n",
-                    "which is critical for performance when processing large numbers of accounts."
-                );
-                Ok(quote! {
-                    #docs
-                    #repr_attr
-                    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-                    pub enum #name {
-                        #(#variant_tokens),*
+        if !ix.accounts.is_empty() {
+            tokens.extend(quote! {
+                impl From<#keys_struct> for [AccountMeta; #accounts_len_const] {
+                    fn from(keys: #keys_struct) -> Self {
+                        [
+                            #(#account_metas),*
+                        ]
                     }
+                }
+            });
+        }
 
-                    #[doc = #safety_doc]
-                    unsafe impl bytemuck::Pod for #name {}
-                    unsafe impl bytemuck::Zeroable for #name {}
-                })
-            } else {
-                Ok(quote! {
-                    #docs
-                    #repr_attr
-                    #[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
-                    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-                    pub enum #name {
-                        #(#variant_tokens),*
+        // Named accounts decoder; anything past them is returned as remaining accounts
+        let account_idents: Vec<_> = ix.accounts.iter().map(|a| snake_ident(&a.name)).collect();
+        let account_indices = 0..ix.accounts.len();
+        tokens.extend(quote! {
+            impl #keys_struct {
+                /// Split an instruction's account keys into the named accounts and the
+                /// remaining accounts that follow them
+                pub fn from_account_keys(accounts: &[Pubkey]) -> std::io::Result<(Self, &[Pubkey])> {
+                    if accounts.len() < #accounts_len_const {
+                        return Err(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            format!(
+                                "expected at least {} accounts, got {}",
+                                #accounts_len_const,
+                                accounts.len()
+                            ),
+                        ));
                     }
+                    let keys = Self {
+                        #(#account_idents: accounts[#account_indices]),*
+                    };
+                    Ok((keys, &accounts[#accounts_len_const..]))
+                }
+            }
+        });
+
+        // Generate instruction builder functions
+        let ix_name_snake = ix.name.to_snake_case();
+        let ix_fn = format_ident!("{}_ix", ix_name_snake);
+        let ix_with_program_id_fn = format_ident!("{}_ix_with_program_id", ix_name_snake);
+        let ix_with_remaining_fn = format_ident!("{}_ix_with_remaining_accounts", ix_name_snake);
+        let ix_with_program_id_and_remaining_fn = format_ident!(
+            "{}_ix_with_program_id_and_remaining_accounts",
+            ix_name_snake
+        );
+        let ix_data_struct = format_ident!("{}IxData", ix.name.to_pascal_case());
+
+        let (args_param, args_arg, data) = if ix.args.is_empty() {
+            (
+                TokenStream::new(),
+                TokenStream::new(),
+                quote! { #ix_data_struct },
+            )
+        } else {
+            let args_struct = format_ident!("{}IxArgs", ix.name.to_pascal_case());
+            (
+                quote! { args: #args_struct, },
+                quote! { args, },
+                quote! { #ix_data_struct::from(args) },
+            )
+        };
+
+        tokens.extend(quote! {
+            pub fn #ix_with_program_id_fn(
+                program_id: Pubkey,
+                keys: #keys_struct,
+                #args_param
+            ) -> std::io::Result<crate::solana_program::instruction::Instruction> {
+                #ix_with_program_id_and_remaining_fn(program_id, keys, #args_arg &[])
+            }
+
+            /// Like the plain builder, with `remaining_accounts` appended after the named
+            /// accounts in order, e.g. tick arrays or multisig signers
+            pub fn #ix_with_program_id_and_remaining_fn(
+                program_id: Pubkey,
+                keys: #keys_struct,
+                #args_param
+                remaining_accounts: &[AccountMeta],
+            ) -> std::io::Result<crate::solana_program::instruction::Instruction> {
+                let metas: [AccountMeta; #accounts_len_const] = keys.into();
+                let mut accounts = Vec::with_capacity(metas.len() + remaining_accounts.len());
+                accounts.extend(metas);
+                accounts.extend_from_slice(remaining_accounts);
+                Ok(crate::solana_program::instruction::Instruction {
+                    program_id,
+                    accounts,
+                    data: #data.try_to_vec()?,
                 })
             }
+        });
+
+        // Only generate the versions without program_id if we have a program ID
+        if has_program_id {
+            tokens.extend(quote! {
+                pub fn #ix_fn(
+                    keys: #keys_struct,
+                    #args_param
+                ) -> std::io::Result<crate::solana_program::instruction::Instruction> {
+                    #ix_with_program_id_fn(crate::ID, keys, #args_arg)
+                }
+
+                pub fn #ix_with_remaining_fn(
+                    keys: #keys_struct,
+                    #args_param
+                    remaining_accounts: &[AccountMeta],
+                ) -> std::io::Result<crate::solana_program::instruction::Instruction> {
+                    #ix_with_program_id_and_remaining_fn(crate::ID, keys, #args_arg remaining_accounts)
+                }
+            });
         }
     }
+
+    tokens.extend(generate_return_data_helpers(instructions));
+
+    Ok(tokens)
 }
 
-fn generate_account(account: &Account) -> Result<TokenStream> {
-    // In old format IDLs, accounts can have type definitions
-    // In new format IDLs, they're just references (discriminators added to types directly)
-    if let Some(ty) = &account.ty {
-        let mut tokens = generate_type_def(&TypeDef {
-            name: account.name.clone(),
-            docs: account.docs.clone(),
-            ty: ty.clone(),
-            serialization: None,
-            repr: None,
-        })?;
+/// `<Ix>ReturnData` aliases and decoders for instructions with a `returns` type
+///
+/// Anchor writes the Borsh-encoded value with `set_return_data`, and the runtime
+/// logs it as `Program return: <program id> <base64 data>`.
+fn generate_return_data_helpers(instructions: &[Instruction]) -> TokenStream {
+    let mut tokens = TokenStream::new();
+    for ix in instructions {
+        let Some(returns) = &ix.returns else {
+            continue;
+        };
+        let return_type = map_idl_type(returns);
+        let alias = format_ident!("{}ReturnData", ix.name.to_pascal_case());
+        let decode_fn = format_ident!("decode_{}_return_data", ix.name.to_snake_case());
+        let alias_doc = format!("Data returned by the `{}` instruction", ix.name);
+        let decode_doc = format!(
+            "Decode the return data of `{}`, e.g. from a simulated transaction",
+            ix.name
+        );
+        tokens.extend(quote! {
+            #[doc = #alias_doc]
+            pub type #alias = #return_type;
 
-        // Add discriminator methods if discriminator is present
-        if let Some(disc) = &account.discriminator {
-            let name = format_ident!("{}", account.name);
-            let disc_bytes = disc.iter().map(|b| quote! { #b });
+            #[doc = #decode_doc]
+            pub fn #decode_fn(data: &[u8]) -> std::io::Result<#alias> {
+                solana_idl_codegen_runtime::decode_return_data(data)
+            }
+        });
+    }
+    if tokens.is_empty() {
+        return tokens;
+    }
 
-            tokens.extend(quote! {
-                impl #name {
-                    pub const DISCRIMINATOR: [u8; 8] = [#(#disc_bytes),*];
+    tokens.extend(quote! {
+        /// Parse a `Program return: <program id> <base64 data>` log line
+        ///
+        /// Returns `None` for any other line.
+        pub fn parse_return_data_log(log: &str) -> Option<(Pubkey, Vec<u8>)> {
+            use base64::Engine;
+            let (program_id, data) = log.strip_prefix("Program return: ")?.split_once(' ')?;
+            let program_id = program_id.parse().ok()?;
+            let data = base64::engine::general_purpose::STANDARD
+                .decode(data.trim_end())
+                .ok()?;
+            Some((program_id, data))
+        }
 
-                    pub fn try_from_slice_with_discriminator(data: &[u8]) -> std::io::Result<Self> {
-                        if data.len() < 8 {
-                            return Err(std::io::Error::new(
-                                std::io::ErrorKind::InvalidData,
-                                "Data too short for discriminator",
-                            ));
-                        }
-                        if data[..8] != Self::DISCRIMINATOR {
-                            return Err(std::io::Error::new(
-                                std::io::ErrorKind::InvalidData,
-                                "Invalid discriminator",
-                            ));
-                        }
-                        borsh::BorshDeserialize::try_from_slice(&data[8..])
-                    }
+        /// Return data set by `program_id`, from the last `Program return:` line it logged
+        pub fn return_data_from_logs<S: AsRef<str>>(
+            logs: &[S],
+            program_id: &Pubkey,
+        ) -> Option<Vec<u8>> {
+            logs.iter()
+                .rev()
+                .filter_map(|log| parse_return_data_log(log.as_ref()))
+                .find(|(id, _)| id == program_id)
+                .map(|(_, data)| data)
+        }
+    });
+    tokens
+}
 
-                    pub fn serialize_with_discriminator<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-                        writer.write_all(&Self::DISCRIMINATOR)?;
-                        borsh::BorshSerialize::serialize(self, writer)
-                    }
+/// Program accounts with a discriminator, with the names of their pubkey fields
+pub(crate) fn account_pubkey_fields(idl: &Idl) -> Vec<(&str, HashSet<String>)> {
+    idl.accounts
+        .iter()
+        .flatten()
+        .filter(|account| account.discriminator.is_some())
+        .filter_map(|account| {
+            let ty = match &account.ty {
+                Some(ty) => ty,
+                None => {
+                    &idl.types
+                        .iter()
+                        .flatten()
+                        .find(|t| t.name == account.name)?
+                        .ty
                 }
-            });
-        }
+            };
+            let TypeDefType::Struct {
+                fields: StructFields::Named(fields),
+            } = ty
+            else {
+                return None;
+            };
+            let pubkey_fields = fields
+                .iter()
+                .filter(|f| is_pubkey_type(&f.ty))
+                .map(|f| f.name.to_snake_case())
+                .collect();
+            Some((account.name.as_str(), pubkey_fields))
+        })
+        .collect()
+}
 
-        Ok(tokens)
-    } else {
-        // In new format, accounts are references to types defined elsewhere
-        // Discriminators are added directly to the type definitions
-        Ok(TokenStream::new())
-    }
+/// Account types with a pubkey field named `field`
+fn pubkey_field_holders(account_fields: &[(&str, HashSet<String>)], field: &str) -> Vec<Ident> {
+    let field = field.to_snake_case();
+    account_fields
+        .iter()
+        .filter(|(_, fields)| fields.contains(&field))
+        .map(|(name, _)| rust_ident(name))
+        .collect()
 }
 
-fn generate_account_validation_helpers(idl: &Idl) -> Result<TokenStream> {
-    let program_id_expr = if let Some(_addr) = idl.get_address() {
-        quote! { crate::ID }
-    } else {
-        return Ok(TokenStream::new()); // Can't validate without program ID
-    };
+/// `<ix>_verify_relations` helpers checking `has_one` relations against loaded accounts
+///
+/// The IDL doesn't say which account type the holder is, so its data is decoded as
+/// each program account with a pubkey field of the right name until a discriminator
+/// matches. Relations that no account type can satisfy are left out; `validate`
+/// reports them as `unchecked-relation`.
+fn generate_relation_helpers(idl: &Idl, relations: &[Vec<Relation>]) -> TokenStream {
+    let account_fields = account_pubkey_fields(idl);
+    let mut tokens = TokenStream::new();
+    for (ix, relations) in idl.instructions.iter().zip(relations) {
+        let has_account = |name: &str| ix.accounts.iter().any(|a| a.name == name);
+        let checks: Vec<_> = relations
+            .iter()
+            .filter(|relation| has_account(&relation.holder) && has_account(&relation.target))
+            .filter_map(|relation| {
+                let candidates = pubkey_field_holders(&account_fields, &relation.field);
+                if candidates.is_empty() {
+                    return None;
+                }
+                let holder = snake_ident(&relation.holder);
+                let target = snake_ident(&relation.target);
+                let field_ident = snake_ident(&relation.field);
+                let field_name = relation.field.to_snake_case();
+                let holder_name = relation.holder.to_snake_case();
+                let target_name = relation.target.to_snake_case();
+                Some(quote! {
+                    let data = loaded_accounts
+                        .get(&keys.#holder)
+                        .ok_or(RelationError::NotLoaded {
+                            account: #holder_name,
+                            pubkey: keys.#holder,
+                        })?;
+                    let expected = #(
+                        if let Ok(account) = #candidates::try_from_slice_with_discriminator(data) {
+                            account.#field_ident
+                        } else
+                    )* {
+                        return Err(RelationError::UnknownAccount {
+                            account: #holder_name,
+                            pubkey: keys.#holder,
+                        });
+                    };
+                    if expected != keys.#target {
+                        return Err(RelationError::Mismatch {
+                            holder: #holder_name,
+                            field: #field_name,
+                            target: #target_name,
+                            expected,
+                            actual: keys.#target,
+                        });
+                    }
+                })
+            })
+            .collect();
+        if checks.is_empty() {
+            continue;
+        }
 
-    // Collect all account types with discriminators
-    let mut account_validations = Vec::new();
-    // Track which account names have already been processed to avoid duplicates
-    let mut processed_accounts = std::collections::HashSet::new();
+        let keys_struct = format_ident!("{}Keys", ix.name.to_pascal_case());
+        let verify_fn = format_ident!("{}_verify_relations", ix.name.to_snake_case());
+        let doc = format!(
+            "Check the `has_one` relations of `{}` against the loaded account data, keyed by pubkey",
+            ix.name
+        );
+        tokens.extend(quote! {
+            #[doc = #doc]
+            pub fn #verify_fn(
+                keys: &#keys_struct,
+                loaded_accounts: &std::collections::HashMap<Pubkey, Vec<u8>>,
+            ) -> Result<(), RelationError> {
+                #(#checks)*
+                Ok(())
+            }
+        });
+    }
+    if tokens.is_empty() {
+        return tokens;
+    }
 
-    // Check accounts from accounts array (old format)
-    if let Some(accounts) = &idl.accounts {
-        for account in accounts {
-            // Only generate validation methods if account has a discriminator
-            // (validation methods reference DISCRIMINATOR and try_from_slice_with_discriminator)
-            if account.discriminator.is_some() {
-                let name = format_ident!("{}", account.name);
-                let docs = generate_docs(account.docs.as_ref());
+    let mut helpers = quote! {
+        /// A `has_one` relation that doesn't hold for a set of instruction keys
+        #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+        pub enum RelationError {
+            #[error("account `{account}` ({pubkey}) is not loaded")]
+            NotLoaded { account: &'static str, pubkey: Pubkey },
+            #[error("account `{account}` ({pubkey}) is not a known program account")]
+            UnknownAccount { account: &'static str, pubkey: Pubkey },
+            #[error("`{holder}.{field}` is {expected}, but the `{target}` key is {actual}")]
+            Mismatch {
+                holder: &'static str,
+                field: &'static str,
+                target: &'static str,
+                expected: Pubkey,
+                actual: Pubkey,
+            },
+        }
+    };
+    helpers.extend(tokens);
+    helpers
+}
 
-                // Track that we've processed this account
-                processed_accounts.insert(account.name.clone());
+/// `<Ix>KeysResolver` builders that derive missing keys the way Anchor's TS client does
+///
+/// Keys come from fixed addresses, PDA seeds (constants, args, other keys and pubkey
+/// fields of loaded accounts) and relations. Derivations run until nothing changes,
+/// since one derived key can unlock another.
+fn generate_keys_resolvers(
+    idl: &Idl,
+    relations: &[Vec<Relation>],
+    has_program_id: bool,
+) -> TokenStream {
+    let account_fields = account_pubkey_fields(idl);
+    let mut tokens = TokenStream::new();
+    for (ix, relations) in idl.instructions.iter().zip(relations) {
+        if ix.accounts.is_empty() {
+            continue;
+        }
+        let pascal = ix.name.to_pascal_case();
+        let keys_struct = format_ident!("{}Keys", pascal);
+        let resolver = format_ident!("{}KeysResolver", pascal);
+        let fields: Vec<_> = ix.accounts.iter().map(|a| snake_ident(&a.name)).collect();
+        let names: Vec<_> = ix.accounts.iter().map(|a| a.name.to_snake_case()).collect();
 
-                account_validations.push(quote! {
-                    #docs
-                    impl #name {
-                        /// Validate that an AccountInfo matches this account type
-                        ///
-                        /// This function checks:
-                        /// - The account owner matches the program ID
-                        /// - The account data starts with the correct discriminator
-                        /// - The account data is long enough to contain the discriminator
-                        ///
-                        /// # Example
-                        /// ```no_run
-                        /// use solana_program::account_info::AccountInfo;
-                        /// use crate::accounts::*;
-                        ///
-                        /// fn validate_account(account_info: &AccountInfo) -> Result<(), ValidationError> {
-                        ///     #name::validate_account_info(account_info)?;
-                        ///     Ok(())
-                        /// }
-                        /// ```
-                        pub fn validate_account_info(
-                            account_info: &solana_program::account_info::AccountInfo,
-                        ) -> Result<(), ValidationError> {
-                            // Check owner
-                            if account_info.owner != &#program_id_expr {
-                                return Err(ValidationError::InvalidOwner {
-                                    expected: #program_id_expr,
-                                    actual: *account_info.owner,
-                                });
-                            }
+        let derivations: Vec<_> = ix
+            .accounts
+            .iter()
+            .filter_map(|account| {
+                let mut strategies = Vec::new();
+                if let Some(address) = account.address.as_deref().and_then(pubkey_tokens) {
+                    strategies.push(quote! { Some(#address) });
+                }
+                if let Some(pda) = &account.pda {
+                    strategies.extend(pda_derivation(ix, pda, &account_fields));
+                }
+                for relation in relations.iter().filter(|r| r.target == account.name) {
+                    if !ix.accounts.iter().any(|a| a.name == relation.holder) {
+                        continue;
+                    }
+                    let holder = snake_ident(&relation.holder);
+                    let data = quote! { &fetcher.fetch_account(&self.#holder?)? };
+                    strategies.extend(read_pubkey_field(&account_fields, data, &relation.field));
+                }
+                let (first, rest) = strategies.split_first()?;
+                let field = snake_ident(&account.name);
+                Some(quote! {
+                    if self.#field.is_none() {
+                        self.#field = (|| -> Option<Pubkey> { #first })()
+                            #(.or_else(|| -> Option<Pubkey> { #rest }))*;
+                        progress |= self.#field.is_some();
+                    }
+                })
+            })
+            .collect();
+        let derive_loop = if derivations.is_empty() {
+            TokenStream::new()
+        } else {
+            quote! {
+                loop {
+                    let mut progress = false;
+                    #(#derivations)*
+                    if !progress {
+                        break;
+                    }
+                }
+            }
+        };
+        let optional_defaults: Vec<_> = ix
+            .accounts
+            .iter()
+            .filter(|a| a.optional == Some(true))
+            .map(|a| {
+                let field = snake_ident(&a.name);
+                quote! { self.#field.get_or_insert(program_id); }
+            })
+            .collect();
+        // Plain accounts are only checked, so the resolver isn't mutated
+        let self_param = if derivations.is_empty() && optional_defaults.is_empty() {
+            quote! { self }
+        } else {
+            quote! { mut self }
+        };
 
-                            // Check discriminator
-                            let data = account_info.data.borrow();
-                            if data.len() < 8 {
-                                return Err(ValidationError::DataTooShort {
-                                    expected: 8,
-                                    actual: data.len(),
-                                });
-                            }
+        let (args_param, args_arg) = if ix.args.is_empty() {
+            (TokenStream::new(), TokenStream::new())
+        } else {
+            let args_struct = format_ident!("{}IxArgs", pascal);
+            (quote! { args: &#args_struct, }, quote! { args, })
+        };
+        let resolve_fn = has_program_id.then(|| {
+            quote! {
+                /// Resolve against the program's declared ID
+                pub fn resolve(
+                    self,
+                    #args_param
+                    fetcher: &impl AccountFetcher,
+                ) -> Result<#keys_struct, UnresolvedAccounts> {
+                    self.resolve_with_program_id(crate::ID, #args_arg fetcher)
+                }
+            }
+        });
 
-                            if data[..8] != Self::DISCRIMINATOR {
-                                return Err(ValidationError::InvalidDiscriminator {
-                                    expected: Self::DISCRIMINATOR,
-                                    actual: <[u8; 8]>::try_from(&data[..8])
-                                        .map_err(|_| ValidationError::DataTooShort {
-                                            expected: 8,
-                                            actual: data.len(),
-                                        })?,
-                                });
-                            }
+        let doc = format!(
+            "Builds [`{}`] from the keys you know, deriving the rest from the IDL",
+            keys_struct
+        );
+        tokens.extend(quote! {
+            #[doc = #doc]
+            #[derive(Debug, Clone, Default, PartialEq)]
+            pub struct #resolver {
+                #(pub #fields: Option<Pubkey>),*
+            }
 
-                            Ok(())
-                        }
+            impl #resolver {
+                pub fn new() -> Self {
+                    Self::default()
+                }
 
-                        /// Validate and deserialize an account from AccountInfo
-                        ///
-                        /// This is a convenience method that combines validation and deserialization.
-                        ///
-                        /// # Example
-                        /// ```no_run
-                        /// use solana_program::account_info::AccountInfo;
-                        /// use crate::accounts::*;
-                        ///
-                        /// fn load_account(account_info: &AccountInfo) -> Result<#name, ValidationError> {
-                        ///     #name::try_from_account_info(account_info)
-                        /// }
-                        /// ```
-                        pub fn try_from_account_info(
-                            account_info: &solana_program::account_info::AccountInfo,
-                        ) -> Result<Self, ValidationError> {
-                            Self::validate_account_info(account_info)?;
-                            let data = account_info.data.borrow();
-                            Self::try_from_slice_with_discriminator(&data)
-                                .map_err(|e| ValidationError::DeserializationError(e.to_string()))
+                #(
+                    pub fn #fields(mut self, pubkey: Pubkey) -> Self {
+                        self.#fields = Some(pubkey);
+                        self
+                    }
+                )*
+
+                /// Derive every key that fixed addresses, PDA seeds and relations allow
+                ///
+                /// Unresolved optional accounts are set to `program_id`, Anchor's
+                /// placeholder for an absent account. Any other key that can't be
+                /// derived is reported by name.
+                #[allow(unused_variables, clippy::redundant_closure_call)]
+                pub fn resolve_with_program_id(
+                    #self_param,
+                    program_id: Pubkey,
+                    #args_param
+                    fetcher: &impl AccountFetcher,
+                ) -> Result<#keys_struct, UnresolvedAccounts> {
+                    #derive_loop
+                    #(#optional_defaults)*
+                    let mut missing = Vec::new();
+                    #(
+                        if self.#fields.is_none() {
+                            missing.push(#names);
                         }
+                    )*
+                    if !missing.is_empty() {
+                        return Err(UnresolvedAccounts(missing));
                     }
-                });
-            }
-        }
-    }
-
-    // Note: Types with discriminators that are referenced in the accounts array
-    // are already handled above. We don't need to process them again here.
+                    Ok(#keys_struct {
+                        #(#fields: self.#fields.unwrap_or_default()),*
+                    })
+                }
 
-    if account_validations.is_empty() {
-        return Ok(TokenStream::new());
+                #resolve_fn
+            }
+        });
+    }
+    if tokens.is_empty() {
+        return tokens;
     }
 
-    Ok(quote! {
-        /// Error type for account validation
-        #[derive(Debug, thiserror::Error)]
-        pub enum ValidationError {
-            #[error("Invalid account owner. Expected: {expected}, Actual: {actual}")]
-            InvalidOwner {
-                expected: solana_program::pubkey::Pubkey,
-                actual: solana_program::pubkey::Pubkey,
-            },
-            #[error("Account data too short. Expected at least {expected} bytes, got {actual}")]
-            DataTooShort {
-                expected: usize,
-                actual: usize,
-            },
-            #[error("Invalid discriminator. Expected: {expected:?}, Actual: {actual:?}")]
-            InvalidDiscriminator {
-                expected: [u8; 8],
-                actual: [u8; 8],
-            },
-            #[error("Deserialization error: {0}")]
-            DeserializationError(String),
+    let mut helpers = quote! {
+        /// Source of account data for key resolution, e.g. an RPC client or a local cache
+        pub trait AccountFetcher {
+            /// Raw data of the account at `pubkey`, or `None` if it isn't available
+            fn fetch_account(&self, pubkey: &Pubkey) -> Option<Vec<u8>>;
         }
 
-        #(#account_validations)*
-    })
+        impl AccountFetcher for std::collections::HashMap<Pubkey, Vec<u8>> {
+            fn fetch_account(&self, pubkey: &Pubkey) -> Option<Vec<u8>> {
+                self.get(pubkey).cloned()
+            }
+        }
+
+        /// Accounts a `*KeysResolver` could neither derive nor was given
+        #[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+        #[error("could not resolve accounts: {}", .0.join(", "))]
+        pub struct UnresolvedAccounts(pub Vec<&'static str>);
+    };
+    helpers.extend(tokens);
+    helpers
 }
 
-fn generate_instructions(
-    instructions: &[Instruction],
-    has_program_id: bool,
-) -> Result<TokenStream> {
+/// Fluent `<Ix>Builder`s with one setter per account and arg
+///
+/// `build()` checks that every non-`Option` arg was set (`Option` args default to
+/// `None`) and fills unset accounts through the instruction's `KeysResolver` (fixed
+/// addresses and PDAs of known keys and args), so it produces the same `Instruction`
+/// as the `*_ix` functions. Instructions without accounts have no resolver and get
+/// no builder.
+fn generate_instruction_builders(idl: &Idl, has_program_id: bool) -> TokenStream {
+    const BUILDER_METHODS: &[&str] =
+        &["new", "build", "build_with_program_id", "remaining_account"];
+
     let mut tokens = TokenStream::new();
+    for ix in idl.instructions.iter().filter(|ix| !ix.accounts.is_empty()) {
+        let pascal = ix.name.to_pascal_case();
+        let builder = format_ident!("{}Builder", pascal);
+        let resolver = format_ident!("{}KeysResolver", pascal);
+        let ix_with_program_id_and_remaining_fn = format_ident!(
+            "{}_ix_with_program_id_and_remaining_accounts",
+            ix.name.to_snake_case()
+        );
 
-    // Generate module-level discriminator constants and IxData wrapper structs for each instruction
-    for (idx, ix) in instructions.iter().enumerate() {
-        let ix_name_snake = ix.name.to_snake_case();
-        let ix_name_pascal = ix.name.to_pascal_case();
-        let discm_const_name = format_ident!("{}_IX_DISCM", ix_name_snake.to_uppercase());
-        let ix_data_struct = format_ident!("{}IxData", ix_name_pascal);
+        let account_names: Vec<_> = ix.accounts.iter().map(|a| a.name.to_snake_case()).collect();
+        let account_fields: Vec<_> = ix.accounts.iter().map(|a| snake_ident(&a.name)).collect();
+        let account_setters = ix.accounts.iter().map(|a| {
+            let name = a.name.to_snake_case();
+            if BUILDER_METHODS.contains(&name.as_str()) {
+                format_ident!("{}_account", name)
+            } else {
+                snake_ident(&a.name)
+            }
+        });
+        let arg_setters = ix.args.iter().map(|arg| {
+            let name = arg.name.to_snake_case();
+            if BUILDER_METHODS.contains(&name.as_str()) || account_names.contains(&name) {
+                format_ident!("{}_arg", name)
+            } else {
+                snake_ident(&arg.name)
+            }
+        });
+        let arg_slots: Vec<_> = ix
+            .args
+            .iter()
+            .map(|arg| format_ident!("arg_{}", arg.name.to_snake_case()))
+            .collect();
+        let arg_types: Vec<_> = ix.args.iter().map(|arg| map_idl_type(&arg.ty)).collect();
+        // `Option` args start out as `None`; only the others have to be set
+        let is_optional = |arg: &Arg| matches!(arg.ty, IdlType::Option { .. });
+        let slot_types = ix.args.iter().zip(&arg_types).map(|(arg, ty)| {
+            if is_optional(arg) {
+                quote! { #ty }
+            } else {
+                quote! { Option<#ty> }
+            }
+        });
+        let slot_values = ix.args.iter().map(|arg| {
+            if is_optional(arg) {
+                quote! { value }
+            } else {
+                quote! { Some(value) }
+            }
+        });
+        let (required, optional): (Vec<_>, Vec<_>) =
+            ix.args.iter().partition(|arg| !is_optional(arg));
+        let required_slots: Vec<_> = required
+            .iter()
+            .map(|arg| format_ident!("arg_{}", arg.name.to_snake_case()))
+            .collect();
+        let required_fields: Vec<_> = required.iter().map(|arg| snake_ident(&arg.name)).collect();
+        let required_names: Vec<_> = required
+            .iter()
+            .map(|arg| arg.name.to_snake_case())
+            .collect();
+        let optional_slots: Vec<_> = optional
+            .iter()
+            .map(|arg| format_ident!("arg_{}", arg.name.to_snake_case()))
+            .collect();
+        let optional_fields: Vec<_> = optional.iter().map(|arg| snake_ident(&arg.name)).collect();
 
-        // Get discriminator bytes
-        let discriminator_bytes: Vec<u8> = if let Some(disc) = &ix.discriminator {
-            disc.clone()
+        let (args, args_param, args_arg) = if ix.args.is_empty() {
+            (TokenStream::new(), TokenStream::new(), TokenStream::new())
         } else {
-            // Use index as discriminator if not provided (old format)
-            (idx as u64).to_le_bytes().to_vec()
+            let args_struct = format_ident!("{}IxArgs", pascal);
+            let args = if required.is_empty() {
+                quote! {
+                    let args = #args_struct {
+                        #(#optional_fields: self.#optional_slots),*
+                    };
+                }
+            } else {
+                quote! {
+                    let args = match (#(self.#required_slots,)*) {
+                        (#(Some(#required_slots),)*) => #args_struct {
+                            #(#required_fields: #required_slots,)*
+                            #(#optional_fields: self.#optional_slots,)*
+                        },
+                        (#(#required_slots,)*) => {
+                            let mut missing = Vec::new();
+                            #(
+                                if #required_slots.is_none() {
+                                    missing.push(#required_names);
+                                }
+                            )*
+                            return Err(BuildError::MissingArgs(missing));
+                        }
+                    };
+                }
+            };
+            (args, quote! { &args, }, quote! { args, })
         };
 
-        let disc_bytes = discriminator_bytes.iter().map(|b| quote! { #b });
+        let build_fn = has_program_id.then(|| {
+            quote! {
+                /// Build against the program's declared ID
+                pub fn build(self) -> Result<crate::solana_program::instruction::Instruction, BuildError> {
+                    self.build_with_program_id(crate::ID)
+                }
+            }
+        });
 
-        // Generate module-level discriminator constant
+        let doc = format!(
+            "Fluent builder for the `{}` instruction; unset PDAs and fixed addresses are derived",
+            ix.name
+        );
         tokens.extend(quote! {
-            pub const #discm_const_name: [u8; 8] = [#(#disc_bytes),*];
-        });
+            #[doc = #doc]
+            #[derive(Debug, Clone, Default)]
+            pub struct #builder {
+                keys: #resolver,
+                #(#arg_slots: #slot_types,)*
+                remaining_accounts: Vec<AccountMeta>,
+            }
 
-        // Generate IxData wrapper struct
-        if ix.args.is_empty() {
-            // No-args instruction
-            tokens.extend(quote! {
-                #[derive(Clone, Debug, PartialEq)]
-                pub struct #ix_data_struct;
+            impl #builder {
+                pub fn new() -> Self {
+                    Self::default()
+                }
 
-                impl #ix_data_struct {
-                    pub fn deserialize(buf: &[u8]) -> std::io::Result<Self> {
-                        use std::io::Read;
-                        let mut reader = buf;
-                        let mut maybe_discm = [0u8; 8];
-                        reader.read_exact(&mut maybe_discm)?;
-                        if maybe_discm != #discm_const_name {
-                            return Err(std::io::Error::new(
-                                std::io::ErrorKind::InvalidData,
-                                format!(
-                                    "discm does not match. Expected: {:?}. Received: {:?}",
-                                    #discm_const_name, maybe_discm
-                                ),
-                            ));
-                        }
-                        Ok(Self)
+                #(
+                    pub fn #account_setters(mut self, pubkey: Pubkey) -> Self {
+                        self.keys.#account_fields = Some(pubkey);
+                        self
                     }
+                )*
 
-                    pub fn serialize<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
-                        writer.write_all(&#discm_const_name)
+                #(
+                    pub fn #arg_setters(mut self, value: #arg_types) -> Self {
+                        self.#arg_slots = #slot_values;
+                        self
                     }
+                )*
 
-                    pub fn try_to_vec(&self) -> std::io::Result<Vec<u8>> {
-                        let mut data = Vec::new();
-                        self.serialize(&mut data)?;
-                        Ok(data)
-                    }
+                /// Append an account after the named ones
+                pub fn remaining_account(mut self, meta: AccountMeta) -> Self {
+                    self.remaining_accounts.push(meta);
+                    self
                 }
-            });
+
+                pub fn build_with_program_id(
+                    self,
+                    program_id: Pubkey,
+                ) -> Result<crate::solana_program::instruction::Instruction, BuildError> {
+                    #args
+                    let keys = self.keys.resolve_with_program_id(
+                        program_id,
+                        #args_param
+                        &std::collections::HashMap::new(),
+                    )?;
+                    Ok(#ix_with_program_id_and_remaining_fn(
+                        program_id,
+                        keys,
+                        #args_arg
+                        &self.remaining_accounts,
+                    )?)
+                }
+
+                #build_fn
+            }
+        });
+    }
+    if tokens.is_empty() {
+        return tokens;
+    }
+
+    let mut helpers = quote! {
+        /// Why an `*Builder` couldn't produce an instruction
+        #[derive(Debug, thiserror::Error)]
+        pub enum BuildError {
+            #[error("missing instruction args: {}", .0.join(", "))]
+            MissingArgs(Vec<&'static str>),
+            #[error(transparent)]
+            MissingAccounts(#[from] UnresolvedAccounts),
+            #[error(transparent)]
+            Io(#[from] std::io::Error),
+        }
+    };
+    helpers.extend(tokens);
+    helpers
+}
+
+/// Names of the functions [`generate_program_handle`] puts on `Program`, in the same
+/// order and under the same conditions
+pub(crate) fn program_handle_methods(idl: &Idl, has_program_id: bool) -> Vec<String> {
+    let mut methods = vec!["new".to_string(), "default".to_string()];
+    for ix in &idl.instructions {
+        let snake = ix.name.to_snake_case();
+        methods.push(format!("{}_ix", snake));
+        methods.push(format!("{}_ix_with_remaining_accounts", snake));
+        if !ix.accounts.is_empty() {
+            methods.push(format!("resolve_{}_keys", snake));
+            methods.push(format!("build_{}", snake));
+        }
+    }
+    if !idl.instructions.is_empty() {
+        methods.push("decode_instruction".to_string());
+    }
+    if idl.instructions.iter().any(|ix| ix.returns.is_some()) {
+        methods.push("return_data_from_logs".to_string());
+    }
+    if has_program_id {
+        for account in idl.accounts.iter().flatten() {
+            if account.discriminator.is_some() {
+                let snake = account.name.to_snake_case();
+                methods.push(format!("validate_{}", snake));
+                methods.push(format!("decode_{}", snake));
+            }
+        }
+    }
+    if idl
+        .events
+        .iter()
+        .flatten()
+        .any(|event| event.discriminator.is_some())
+    {
+        methods.push("parse_event".to_string());
+        methods.push("parse_events_from_data".to_string());
+    }
+    methods
+}
+
+/// Name of the `Program` constructor for a `metadata.deployments` cluster
+///
+/// Clusters whose name clashes with another `Program` function get a `_deployment`
+/// suffix, the way builder setters get `_account`/`_arg`.
+pub(crate) fn deployment_constructor(cluster: &str, methods: &[String]) -> String {
+    let name = cluster.to_snake_case();
+    if methods.contains(&name) {
+        format!("{}_deployment", name)
+    } else {
+        name
+    }
+}
+
+/// `Program { id }` handle with the builders, validators and decoders as methods
+///
+/// Every method passes `self.id` where the free functions would use `crate::ID`, so
+/// one crate can talk to several deployments. `metadata.deployments` entries become
+/// named constructors (`Program::mainnet()`), and `Default` uses the declared ID.
+fn generate_program_handle(idl: &Idl, has_program_id: bool) -> TokenStream {
+    let mut methods = Vec::new();
+
+    for ix in &idl.instructions {
+        let snake = ix.name.to_snake_case();
+        let pascal = ix.name.to_pascal_case();
+        let keys_struct = format_ident!("{}Keys", pascal);
+        let ix_fn = format_ident!("{}_ix", snake);
+        let ix_with_program_id_fn = format_ident!("{}_ix_with_program_id", snake);
+        let ix_with_remaining_fn = format_ident!("{}_ix_with_remaining_accounts", snake);
+        let ix_with_program_id_and_remaining_fn =
+            format_ident!("{}_ix_with_program_id_and_remaining_accounts", snake);
+        let (args_param, args_arg, args_ref_param) = if ix.args.is_empty() {
+            (TokenStream::new(), TokenStream::new(), TokenStream::new())
         } else {
-            // Instruction with args
-            let args_struct = format_ident!("{}IxArgs", ix_name_pascal);
+            let args_struct = format_ident!("{}IxArgs", pascal);
+            (
+                quote! { args: #args_struct, },
+                quote! { args, },
+                quote! { args: &#args_struct, },
+            )
+        };
+        methods.push(quote! {
+            pub fn #ix_fn(
+                &self,
+                keys: #keys_struct,
+                #args_param
+            ) -> std::io::Result<crate::solana_program::instruction::Instruction> {
+                #ix_with_program_id_fn(self.id, keys, #args_arg)
+            }
 
-            tokens.extend(quote! {
-                #[derive(Clone, Debug, PartialEq)]
-                pub struct #ix_data_struct(pub #args_struct);
+            pub fn #ix_with_remaining_fn(
+                &self,
+                keys: #keys_struct,
+                #args_param
+                remaining_accounts: &[AccountMeta],
+            ) -> std::io::Result<crate::solana_program::instruction::Instruction> {
+                #ix_with_program_id_and_remaining_fn(self.id, keys, #args_arg remaining_accounts)
+            }
+        });
 
-                impl From<#args_struct> for #ix_data_struct {
-                    fn from(args: #args_struct) -> Self {
-                        Self(args)
-                    }
+        // Resolvers and builders only exist for instructions with accounts
+        if ix.accounts.is_empty() {
+            continue;
+        }
+        let resolver = format_ident!("{}KeysResolver", pascal);
+        let builder = format_ident!("{}Builder", pascal);
+        let resolve_fn = format_ident!("resolve_{}_keys", snake);
+        let build_fn = format_ident!("build_{}", snake);
+        methods.push(quote! {
+            pub fn #resolve_fn(
+                &self,
+                resolver: #resolver,
+                #args_ref_param
+                fetcher: &impl AccountFetcher,
+            ) -> Result<#keys_struct, UnresolvedAccounts> {
+                resolver.resolve_with_program_id(self.id, #args_arg fetcher)
+            }
+
+            pub fn #build_fn(
+                &self,
+                builder: #builder,
+            ) -> Result<crate::solana_program::instruction::Instruction, BuildError> {
+                builder.build_with_program_id(self.id)
+            }
+        });
+    }
+
+    if !idl.instructions.is_empty() {
+        methods.push(quote! {
+            /// Decode an instruction addressed to this deployment
+            pub fn decode_instruction(
+                &self,
+                instruction: &crate::solana_program::instruction::Instruction,
+            ) -> std::io::Result<Instruction> {
+                if instruction.program_id != self.id {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!("instruction is for program {}", instruction.program_id),
+                    ));
                 }
+                Instruction::try_from_slice(&instruction.data)
+            }
+        });
+    }
 
-                impl #ix_data_struct {
-                    pub fn deserialize(buf: &[u8]) -> std::io::Result<Self> {
-                        use std::io::Read;
-                        let mut reader = buf;
-                        let mut maybe_discm = [0u8; 8];
-                        reader.read_exact(&mut maybe_discm)?;
-                        if maybe_discm != #discm_const_name {
-                            return Err(std::io::Error::new(
-                                std::io::ErrorKind::InvalidData,
-                                format!(
-                                    "discm does not match. Expected: {:?}. Received: {:?}",
-                                    #discm_const_name, maybe_discm
-                                ),
-                            ));
-                        }
-                        Ok(Self(#args_struct::deserialize(&mut reader)?))
-                    }
+    if idl.instructions.iter().any(|ix| ix.returns.is_some()) {
+        methods.push(quote! {
+            /// Data the last `Program return:` log line of this deployment carries
+            pub fn return_data_from_logs<S: AsRef<str>>(&self, logs: &[S]) -> Option<Vec<u8>> {
+                return_data_from_logs(logs, &self.id)
+            }
+        });
+    }
 
-                    pub fn serialize<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
-                        writer.write_all(&#discm_const_name)?;
-                        self.0.serialize(&mut writer)
-                    }
+    // Validators need `ValidationError`, which only exists alongside a declared ID
+    if has_program_id {
+        for account in idl.accounts.iter().flatten() {
+            if account.discriminator.is_none() {
+                continue;
+            }
+            let name = rust_ident(&account.name);
+            let snake = account.name.to_snake_case();
+            let validate_fn = format_ident!("validate_{}", snake);
+            let decode_fn = format_ident!("decode_{}", snake);
+            methods.push(quote! {
+                pub fn #validate_fn(
+                    &self,
+                    account_info: &crate::solana_program::account_info::AccountInfo,
+                ) -> Result<(), ValidationError> {
+                    #name::validate_account_info_with_program_id(account_info, &self.id)
+                }
 
-                    pub fn try_to_vec(&self) -> std::io::Result<Vec<u8>> {
-                        let mut data = Vec::new();
-                        self.serialize(&mut data)?;
-                        Ok(data)
-                    }
+                pub fn #decode_fn(
+                    &self,
+                    account_info: &crate::solana_program::account_info::AccountInfo,
+                ) -> Result<#name, ValidationError> {
+                    #name::try_from_account_info_with_program_id(account_info, &self.id)
                 }
             });
         }
     }
 
-    // Generate instruction enum
-    let instruction_variants: Vec<_> = instructions
+    // The event parser only covers events with discriminators
+    let parses_events = idl
+        .events
         .iter()
-        .map(|ix| {
-            let variant_name = format_ident!("{}", ix.name.to_pascal_case());
-            if ix.args.is_empty() {
-                quote! { #variant_name }
-            } else {
-                let args_struct = format_ident!("{}IxArgs", ix.name.to_pascal_case());
-                quote! { #variant_name(#args_struct) }
+        .flatten()
+        .any(|event| event.discriminator.is_some());
+    if parses_events {
+        methods.push(quote! {
+            pub fn parse_event(
+                &self,
+                data: &[u8],
+            ) -> Result<crate::events::ParsedEvent, crate::events::EventParseError> {
+                crate::events::parse_event(data)
             }
-        })
-        .collect();
 
-    // Generate discriminator match arms for serialization
-    let serialize_arms: Vec<_> = instructions
-        .iter()
-        .map(|ix| {
-            let variant_name = format_ident!("{}", ix.name.to_pascal_case());
-            let discm_const_name =
-                format_ident!("{}_IX_DISCM", ix.name.to_snake_case().to_uppercase());
+            pub fn parse_events_from_data(
+                &self,
+                data: &[u8],
+            ) -> Vec<Result<crate::events::ParsedEvent, crate::events::EventParseError>> {
+                crate::events::parse_events_from_data(data)
+            }
+        });
+    }
 
-            if ix.args.is_empty() {
-                quote! {
-                    Self::#variant_name => {
-                        writer.write_all(&#discm_const_name)?;
-                        Ok(())
-                    }
+    let handle_methods = program_handle_methods(idl, has_program_id);
+    let deployments = idl
+        .metadata
+        .as_ref()
+        .and_then(|m| m.deployments.as_ref())
+        .into_iter()
+        .flatten()
+        .filter_map(|(cluster, address)| {
+            let constructor = snake_ident(&deployment_constructor(cluster, &handle_methods));
+            let id = pubkey_tokens(address)?;
+            let doc = format!("The `{}` deployment", cluster);
+            Some(quote! {
+                #[doc = #doc]
+                pub const fn #constructor() -> Self {
+                    Self::new(#id)
                 }
-            } else {
-                quote! {
-                    Self::#variant_name(args) => {
-                        writer.write_all(&#discm_const_name)?;
-                        args.serialize(writer)
-                    }
+            })
+        });
+
+    let default_impl = has_program_id.then(|| {
+        quote! {
+            impl Default for Program {
+                fn default() -> Self {
+                    Self::new(crate::ID)
                 }
             }
-        })
-        .collect();
+        }
+    });
 
-    // Generate discriminator match for deserialization
-    let deserialize_arms: Vec<_> = instructions
-        .iter()
-        .enumerate()
-        .map(|(idx, ix)| {
-            let variant_name = format_ident!("{}", ix.name.to_pascal_case());
-            let discriminator_bytes = if let Some(disc) = &ix.discriminator {
-                disc.clone()
-            } else {
-                (idx as u64).to_le_bytes().to_vec()
-            };
+    quote! {
+        /// Handle for one deployment of the program
+        ///
+        /// Builders, validators and decoders on the handle use `id` as the program ID.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct Program {
+            pub id: Pubkey,
+        }
 
-            let disc_pattern = discriminator_bytes.iter().map(|b| quote! { #b });
+        #default_impl
 
-            if ix.args.is_empty() {
-                quote! {
-                    [#(#disc_pattern),*] => Ok(Self::#variant_name)
+        impl Program {
+            pub const fn new(id: Pubkey) -> Self {
+                Self { id }
+            }
+
+            #(#deployments)*
+
+            #(#methods)*
+        }
+    }
+}
+
+/// `ProgramAccount`, `ProgramInstruction` and `ProgramEvent` impls, returned as
+/// (accounts, instructions, events) tokens
+///
+/// `NAME` is the name in the input IDL, before any rename to avoid a clash, and
+/// `PROGRAM_ID` is `None` without a declared address. Each impl delegates to the item's
+/// inherent discriminator-aware methods.
+fn generate_program_trait_impls(
+    idl: &Idl,
+    renamed_types: &[(String, String)],
+    has_program_id: bool,
+) -> (TokenStream, TokenStream, TokenStream) {
+    let program_id = if has_program_id {
+        quote! { Some(crate::ID) }
+    } else {
+        quote! { None }
+    };
+    let idl_name = |name: &str| {
+        renamed_types
+            .iter()
+            .find(|(_, new)| new == name)
+            .map_or(name, |(original, _)| original.as_str())
+            .to_string()
+    };
+
+    let mut accounts = TokenStream::new();
+    for account in idl.accounts.iter().flatten() {
+        if account.discriminator.is_none() {
+            continue;
+        }
+        let name = rust_ident(&account.name);
+        let idl_name = idl_name(&account.name);
+        accounts.extend(quote! {
+            impl crate::ProgramAccount for #name {
+                const DISCRIMINATOR: &'static [u8] = &#name::DISCRIMINATOR;
+                const NAME: &'static str = #idl_name;
+                const PROGRAM_ID: Option<Pubkey> = #program_id;
+
+                fn decode(data: &[u8]) -> std::io::Result<Self> {
+                    Self::try_from_slice_with_discriminator(data)
                 }
-            } else {
-                let args_struct = format_ident!("{}IxArgs", ix.name.to_pascal_case());
-                quote! {
-                    [#(#disc_pattern),*] => {
-                        let args = #args_struct::deserialize(&mut buf)?;
-                        Ok(Self::#variant_name(args))
-                    }
+
+                fn encode(&self) -> std::io::Result<Vec<u8>> {
+                    let mut data = Vec::new();
+                    self.serialize_with_discriminator(&mut data)?;
+                    Ok(data)
+                }
+            }
+        });
+    }
+
+    let mut instructions = TokenStream::new();
+    for ix in &idl.instructions {
+        let ix_data_struct = format_ident!("{}IxData", ix.name.to_pascal_case());
+        let discm_const = format_ident!("{}_IX_DISCM", ix.name.to_snake_case().to_uppercase());
+        let ix_name = &ix.name;
+        instructions.extend(quote! {
+            impl crate::ProgramInstruction for #ix_data_struct {
+                const DISCRIMINATOR: &'static [u8] = &#discm_const;
+                const NAME: &'static str = #ix_name;
+                const PROGRAM_ID: Option<Pubkey> = #program_id;
+
+                fn decode(data: &[u8]) -> std::io::Result<Self> {
+                    Self::deserialize(data)
+                }
+
+                fn encode(&self) -> std::io::Result<Vec<u8>> {
+                    self.try_to_vec()
+                }
+            }
+        });
+    }
+
+    let mut events = TokenStream::new();
+    for event in idl.events.iter().flatten() {
+        if event.discriminator.is_none() {
+            continue;
+        }
+        let wrapper_name = format_ident!("{}Event", event.name);
+        let discm_const =
+            format_ident!("{}_EVENT_DISCM", event.name.to_snake_case().to_uppercase());
+        let idl_name = idl_name(&event.name);
+        events.extend(quote! {
+            impl crate::ProgramEvent for #wrapper_name {
+                const DISCRIMINATOR: &'static [u8] = &#discm_const;
+                const NAME: &'static str = #idl_name;
+                const PROGRAM_ID: Option<Pubkey> = #program_id;
+
+                fn decode(mut data: &[u8]) -> std::io::Result<Self> {
+                    Self::deserialize(&mut data)
+                }
+
+                fn encode(&self) -> std::io::Result<Vec<u8>> {
+                    borsh::to_vec(self)
                 }
             }
+        });
+    }
+
+    (accounts, instructions, events)
+}
+
+/// `anchor_lang` trait impls as `(accounts, instructions)` token streams
+///
+/// Accounts with a discriminator get `Discriminator`, `AccountDeserialize` and
+/// `AccountSerialize` (plus `Owner` when the IDL declares a program ID), so they work
+/// as `Account<'info, T>`. `*IxData` gets `Discriminator` and `InstructionData`, `*Keys`
+/// gets `ToAccountMetas`, and each instruction gets an `*Accounts<'info>` struct of
+/// `AccountInfo`s for `CpiContext`. Like Anchor's own CPI structs, `to_account_metas`
+/// ignores its `is_signer` override.
+fn generate_anchor_impls(idl: &Idl, has_program_id: bool) -> (TokenStream, TokenStream) {
+    let pod_accounts: HashSet<&str> = idl
+        .types
+        .iter()
+        .flatten()
+        .filter(|ty| {
+            ty.serialization
+                .as_deref()
+                .is_some_and(|s| s == "bytemuckunsafe" || s == "bytemuck")
         })
+        .map(|ty| ty.name.as_str())
         .collect();
 
-    tokens.extend(quote! {
-        #[derive(Debug, Clone, PartialEq)]
-        pub enum Instruction {
-            #(#instruction_variants),*
+    let mut accounts = TokenStream::new();
+    for account in idl.accounts.iter().flatten() {
+        if account.discriminator.is_none() {
+            continue;
         }
-
-        impl Instruction {
-            pub fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-                match self {
-                    #(#serialize_arms),*
-                }
+        let name = rust_ident(&account.name);
+        // Inline (legacy) account layouts are always Borsh
+        let deserialize_unchecked =
+            if account.ty.is_none() && pod_accounts.contains(account.name.as_str()) {
+                quote! { pod_try_deserialize_unchecked }
+            } else {
+                quote! { try_deserialize_unchecked }
+            };
+        accounts.extend(quote! {
+            impl anchor_lang::Discriminator for #name {
+                const DISCRIMINATOR: &'static [u8] = &#name::DISCRIMINATOR;
             }
 
-            pub fn try_from_slice(data: &[u8]) -> std::io::Result<Self> {
-                if data.len() < 8 {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        "Data too short for instruction discriminator",
-                    ));
+            impl anchor_lang::AccountDeserialize for #name {
+                fn try_deserialize(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+                    solana_idl_codegen_runtime::anchor::check_account_discriminator(
+                        &Self::DISCRIMINATOR,
+                        buf,
+                    )?;
+                    Self::try_deserialize_unchecked(buf)
                 }
 
-                use borsh::BorshDeserialize;
-                let mut buf = &data[8..];
-
-                match &data[..8] {
-                    #(#deserialize_arms),*,
-                    _ => Err(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        "Unknown instruction discriminator",
-                    )),
+                fn try_deserialize_unchecked(buf: &mut &[u8]) -> anchor_lang::Result<Self> {
+                    solana_idl_codegen_runtime::anchor::#deserialize_unchecked(
+                        Self::DISCRIMINATOR.len(),
+                        buf,
+                    )
                 }
             }
-        }
-    });
 
-    // Generate args structs for each instruction
-    for ix in instructions {
-        if !ix.args.is_empty() {
-            let args_struct = format_ident!("{}IxArgs", ix.name.to_pascal_case());
-            let field_tokens: Vec<_> = ix
-                .args
-                .iter()
-                .map(|arg| {
-                    let field_name = format_ident!("{}", arg.name.to_snake_case());
-                    let field_type = map_idl_type(&arg.ty);
-                    quote! {
-                        pub #field_name: #field_type
+            impl anchor_lang::AccountSerialize for #name {
+                fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> anchor_lang::Result<()> {
+                    self.serialize_with_discriminator(writer)
+                        .map_err(solana_idl_codegen_runtime::anchor::serialize_error)
+                }
+            }
+        });
+        if has_program_id {
+            accounts.extend(quote! {
+                impl anchor_lang::Owner for #name {
+                    fn owner() -> Pubkey {
+                        crate::ID
                     }
-                })
-                .collect();
-
-            tokens.extend(quote! {
-                #[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
-                #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-                pub struct #args_struct {
-                    #(#field_tokens),*
                 }
             });
         }
+    }
 
-        // Generate Keys struct for each instruction (for building instructions)
-        let keys_struct = format_ident!("{}Keys", ix.name.to_pascal_case());
-        let account_fields: Vec<_> = ix
-            .accounts
-            .iter()
-            .map(|acc| {
-                let field_name = format_ident!("{}", acc.name.to_snake_case());
-                let docs = generate_docs(acc.docs.as_ref());
-                quote! {
-                    #docs
-                    pub #field_name: Pubkey
+    let mut instructions = TokenStream::new();
+    for ix in &idl.instructions {
+        let pascal = ix.name.to_pascal_case();
+        let ix_data_struct = format_ident!("{}IxData", pascal);
+        let keys_struct = format_ident!("{}Keys", pascal);
+        let accounts_struct = format_ident!("{}Accounts", pascal);
+        let discm_const = format_ident!("{}_IX_DISCM", ix.name.to_snake_case().to_uppercase());
+
+        // `InstructionData::data` writes the discriminator itself, so only the args go here
+        let serialize_args = if ix.args.is_empty() {
+            quote! {
+                fn serialize<W: std::io::Write>(&self, _writer: &mut W) -> std::io::Result<()> {
+                    Ok(())
                 }
-            })
-            .collect();
+            }
+        } else {
+            quote! {
+                fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+                    borsh::BorshSerialize::serialize(&self.0, writer)
+                }
+            }
+        };
 
-        // Generate const for accounts length
-        let accounts_len_const =
-            format_ident!("{}_IX_ACCOUNTS_LEN", ix.name.to_snake_case().to_uppercase());
-        let accounts_len = ix.accounts.len();
+        let fields: Vec<_> = ix.accounts.iter().map(|a| snake_ident(&a.name)).collect();
+        let field_docs = ix.accounts.iter().map(|a| generate_docs(a.docs.as_ref()));
+        let metas = ix.accounts.iter().map(|acc| {
+            let field_name = snake_ident(&acc.name);
+            let is_signer = acc.signer;
+            let is_writable = acc.writable;
+            quote! {
+                AccountMeta {
+                    pubkey: self.#field_name,
+                    is_signer: #is_signer,
+                    is_writable: #is_writable,
+                }
+            }
+        });
+        let optional_doc = if ix.accounts.iter().any(|a| a.optional == Some(true)) {
+            quote! {
+                ///
+                /// Pass the program's own account for an optional account that is absent.
+            }
+        } else {
+            TokenStream::new()
+        };
+        // Anchor's CPI structs drop the lifetime when there are no accounts
+        let (generics, accounts_param) = if ix.accounts.is_empty() {
+            (TokenStream::new(), quote! { _accounts })
+        } else {
+            (quote! { <'info> }, quote! { accounts })
+        };
 
-        tokens.extend(quote! {
-            pub const #accounts_len_const: usize = #accounts_len;
+        instructions.extend(quote! {
+            impl anchor_lang::Discriminator for #ix_data_struct {
+                const DISCRIMINATOR: &'static [u8] = &#discm_const;
+            }
 
-            #[derive(Debug, Clone, PartialEq)]
-            pub struct #keys_struct {
-                #(#account_fields),*
+            impl anchor_lang::AnchorSerialize for #ix_data_struct {
+                #serialize_args
             }
-        });
 
-        // Generate From<Keys> for [AccountMeta; N] conversion
-        let account_metas: Vec<_> = ix
-            .accounts
-            .iter()
-            .map(|acc| {
-                let field_name = format_ident!("{}", acc.name.to_snake_case());
-                let is_signer = acc.signer;
-                let is_writable = acc.writable;
-                quote! {
-                    AccountMeta {
-                        pubkey: keys.#field_name,
-                        is_signer: #is_signer,
-                        is_writable: #is_writable,
-                    }
-                }
-            })
-            .collect();
+            impl anchor_lang::InstructionData for #ix_data_struct {}
 
-        if !ix.accounts.is_empty() {
-            tokens.extend(quote! {
-                impl From<#keys_struct> for [AccountMeta; #accounts_len_const] {
-                    fn from(keys: #keys_struct) -> Self {
-                        [
-                            #(#account_metas),*
-                        ]
-                    }
+            impl anchor_lang::ToAccountMetas for #keys_struct {
+                fn to_account_metas(&self, _is_signer: Option<bool>) -> Vec<AccountMeta> {
+                    vec![#(#metas),*]
                 }
-            });
-        }
+            }
 
-        // Generate instruction builder functions
-        let ix_name_snake = ix.name.to_snake_case();
-        let ix_fn = format_ident!("{}_ix", ix_name_snake);
-        let ix_with_program_id_fn = format_ident!("{}_ix_with_program_id", ix_name_snake);
-        let ix_data_struct = format_ident!("{}IxData", ix.name.to_pascal_case());
+            /// Accounts of the instruction as `AccountInfo`s, for `CpiContext`
+            #optional_doc
+            pub struct #accounts_struct #generics {
+                #(
+                    #field_docs
+                    pub #fields: crate::solana_program::account_info::AccountInfo<'info>
+                ),*
+            }
 
-        if ix.args.is_empty() {
-            // No-args instruction builder
-            tokens.extend(quote! {
-                pub fn #ix_with_program_id_fn(
-                    program_id: Pubkey,
-                    keys: #keys_struct,
-                ) -> std::io::Result<solana_program::instruction::Instruction> {
-                    let metas: [AccountMeta; #accounts_len_const] = keys.into();
-                    Ok(solana_program::instruction::Instruction {
-                        program_id,
-                        accounts: Vec::from(metas),
-                        data: #ix_data_struct.try_to_vec()?,
-                    })
+            impl #generics From<&#accounts_struct #generics> for #keys_struct {
+                fn from(#accounts_param: &#accounts_struct #generics) -> Self {
+                    Self {
+                        #(#fields: *accounts.#fields.key),*
+                    }
                 }
-            });
+            }
 
-            // Only generate the version without program_id if we have a program ID
-            if has_program_id {
-                tokens.extend(quote! {
-                    pub fn #ix_fn(keys: #keys_struct) -> std::io::Result<solana_program::instruction::Instruction> {
-                        #ix_with_program_id_fn(crate::ID, keys)
-                    }
-                });
+            impl #generics anchor_lang::ToAccountMetas for #accounts_struct #generics {
+                fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+                    #keys_struct::from(self).to_account_metas(is_signer)
+                }
             }
-        } else {
-            // Instruction with args
-            let args_struct = format_ident!("{}IxArgs", ix.name.to_pascal_case());
 
-            tokens.extend(quote! {
-                pub fn #ix_with_program_id_fn(
-                    program_id: Pubkey,
-                    keys: #keys_struct,
-                    args: #args_struct,
-                ) -> std::io::Result<solana_program::instruction::Instruction> {
-                    let metas: [AccountMeta; #accounts_len_const] = keys.into();
-                    let data: #ix_data_struct = args.into();
-                    Ok(solana_program::instruction::Instruction {
-                        program_id,
-                        accounts: Vec::from(metas),
-                        data: data.try_to_vec()?,
-                    })
+            impl<'info> anchor_lang::ToAccountInfos<'info> for #accounts_struct #generics {
+                fn to_account_infos(&self) -> Vec<crate::solana_program::account_info::AccountInfo<'info>> {
+                    vec![#(self.#fields.clone()),*]
                 }
-            });
+            }
+        });
+    }
 
-            // Only generate the version without program_id if we have a program ID
-            if has_program_id {
-                tokens.extend(quote! {
-                    pub fn #ix_fn(
-                        keys: #keys_struct,
-                        args: #args_struct,
-                    ) -> std::io::Result<solana_program::instruction::Instruction> {
-                        #ix_with_program_id_fn(crate::ID, keys, args)
-                    }
+    (accounts, instructions)
+}
+
+/// `Pubkey` constructor for a base58 address, or `None` if it isn't one
+fn pubkey_tokens(address: &str) -> Option<TokenStream> {
+    let bytes: [u8; 32] = bs58::decode(address).into_vec().ok()?.try_into().ok()?;
+    Some(quote! { Pubkey::new_from_array([#(#bytes),*]) })
+}
+
+/// Statements that evaluate to `Some(field)` when `data` decodes as an account type
+/// with pubkey field `field`, or `None` when no account type has that field
+fn read_pubkey_field(
+    account_fields: &[(&str, HashSet<String>)],
+    data: TokenStream,
+    field: &str,
+) -> Option<TokenStream> {
+    let candidates = pubkey_field_holders(account_fields, field);
+    if candidates.is_empty() {
+        return None;
+    }
+    let field = snake_ident(field);
+    Some(quote! {
+        let data = #data;
+        #(
+            if let Ok(account) = #candidates::try_from_slice_with_discriminator(data) {
+                return Some(account.#field);
+            }
+        )*
+        None
+    })
+}
+
+/// Statements deriving a PDA inside a resolver, or `None` if a seed can't be expressed
+fn pda_derivation(
+    ix: &Instruction,
+    pda: &Pda,
+    account_fields: &[(&str, HashSet<String>)],
+) -> Option<TokenStream> {
+    let mut bindings = Vec::new();
+    let mut seeds = Vec::new();
+    for (i, seed) in pda.seeds.iter().enumerate() {
+        let binding = format_ident!("seed_{}", i);
+        match seed {
+            Seed::Const { value } => seeds.push(quote! { &[#(#value),*][..] }),
+            Seed::Arg { path } => {
+                let arg = ix.args.iter().find(|a| &a.name == path)?;
+                let field = snake_ident(&arg.name);
+                seeds.push(match &arg.ty {
+                    IdlType::Simple(name) => match name.as_str() {
+                        "pubkey" | "publicKey" => quote! { &args.#field.to_bytes()[..] },
+                        "string" => quote! { args.#field.as_bytes() },
+                        "bytes" => quote! { &args.#field[..] },
+                        "bool" => quote! { &[args.#field as u8][..] },
+                        "u8" | "i8" | "u16" | "i16" | "u32" | "i32" | "u64" | "i64" | "u128"
+                        | "i128" => quote! { &args.#field.to_le_bytes()[..] },
+                        _ => return None,
+                    },
+                    IdlType::Array {
+                        array: ArrayType::Tuple((inner, _)),
+                    } if is_u8_type(inner) => quote! { &args.#field[..] },
+                    _ => return None,
                 });
             }
+            Seed::Account { path } => {
+                bindings.push(account_path_binding(ix, path, &binding, account_fields)?);
+                seeds.push(quote! { &#binding.to_bytes()[..] });
+            }
         }
     }
+    let program = match &pda.program {
+        None => quote! { program_id },
+        Some(Program::Const { value }) => {
+            let bytes: [u8; 32] = value.as_slice().try_into().ok()?;
+            quote! { Pubkey::new_from_array([#(#bytes),*]) }
+        }
+        Some(Program::Account { path }) => {
+            let binding = format_ident!("seed_program");
+            bindings.push(account_path_binding(ix, path, &binding, account_fields)?);
+            quote! { #binding }
+        }
+    };
+    Some(quote! {
+        #(#bindings)*
+        Some(Pubkey::find_program_address(&[#(#seeds),*], &#program).0)
+    })
+}
 
-    Ok(tokens)
+/// Bind `binding` to the key behind an account seed path: another key (`pool`) or a
+/// pubkey field of that account's data (`pool.mint`)
+fn account_path_binding(
+    ix: &Instruction,
+    path: &str,
+    binding: &Ident,
+    account_fields: &[(&str, HashSet<String>)],
+) -> Option<TokenStream> {
+    let (account, field) = match path.split_once('.') {
+        Some((account, field)) => (account, Some(field)),
+        None => (path, None),
+    };
+    if !ix.accounts.iter().any(|a| a.name == account) {
+        return None;
+    }
+    let account = snake_ident(account);
+    match field {
+        None => Some(quote! { let #binding = self.#account?; }),
+        Some(field) if !field.contains('.') => {
+            let read = read_pubkey_field(
+                account_fields,
+                quote! { &fetcher.fetch_account(&self.#account?)? },
+                field,
+            )?;
+            Some(quote! { let #binding = (|| -> Option<Pubkey> { #read })()?; })
+        }
+        Some(_) => None,
+    }
 }
 
 fn generate_errors(errors: &[Error]) -> Result<TokenStream> {
+    check_ident_collisions(
+        "error",
+        "the program",
+        errors.iter().map(|e| e.name.as_str()),
+        pascal_ident,
+    )?;
+
     let error_variants: Vec<_> = errors
         .iter()
         .map(|e| {
-            let variant_name = format_ident!("{}", e.name.to_pascal_case());
+            let variant_name = pascal_ident(&e.name);
             let msg = e.msg.as_deref().unwrap_or(&e.name);
             let code = e.code;
+            let docs = generate_docs(e.docs.as_ref());
             quote! {
+                #docs
                 #[error(#msg)]
                 #variant_name = #code
             }
@@ -1096,7 +4113,7 @@ fn generate_errors(errors: &[Error]) -> Result<TokenStream> {
         .collect();
 
     Ok(quote! {
-        use solana_program::program_error::ProgramError;
+        use crate::solana_program::program_error::ProgramError;
         use thiserror::Error;
 
         #[derive(Clone, Copy, Debug, Eq, Error, num_derive::FromPrimitive, PartialEq)]
@@ -1113,33 +4130,94 @@ fn generate_errors(errors: &[Error]) -> Result<TokenStream> {
     })
 }
 
-fn generate_event(event: &Event, types: &Option<Vec<TypeDef>>) -> Result<TokenStream> {
-    // Helper function to check if a type is Pubkey
-    fn is_pubkey_type(ty: &IdlType) -> bool {
-        match ty {
-            IdlType::Simple(s) => matches!(s.as_str(), "publicKey" | "pubkey" | "Pubkey"),
-            _ => false,
+fn generate_constants(constants: &[Constant]) -> Result<TokenStream> {
+    check_ident_collisions(
+        "constant",
+        "the program",
+        constants.iter().map(|c| c.name.as_str()),
+        shouty_ident,
+    )?;
+
+    let mut tokens = TokenStream::new();
+    for constant in constants {
+        // Values with no `const` form (e.g. vecs or user types) are left out
+        let Some((ty, value)) = constant_value(constant) else {
+            continue;
+        };
+        let name = shouty_ident(&constant.name);
+        let docs = generate_docs(constant.docs.as_ref());
+        tokens.extend(quote! {
+            #docs
+            pub const #name: #ty = #value;
+        });
+    }
+    Ok(tokens)
+}
+
+/// Type and value tokens for an IDL constant
+///
+/// Anchor stores the value as the Rust source of the initializer, so numbers, bools
+/// and arrays of them are parsed as expressions. Strings may be quoted or bare,
+/// `bytes` is an array or byte string literal and `pubkey` is base58.
+fn constant_value(constant: &Constant) -> Option<(TokenStream, TokenStream)> {
+    const SCALARS: &[&str] = &[
+        "bool", "u8", "i8", "u16", "i16", "u32", "i32", "u64", "i64", "u128", "i128", "f32", "f64",
+    ];
+    let value = constant.value.trim();
+    let unquoted = || {
+        parse_str::<syn::LitStr>(value)
+            .map(|lit| lit.value())
+            .unwrap_or_else(|_| value.to_string())
+    };
+    match &constant.ty {
+        IdlType::Simple(name) if name == "string" => {
+            let value = unquoted();
+            Some((quote! { &str }, quote! { #value }))
+        }
+        IdlType::Simple(name) if name == "bytes" => match parse_str::<syn::Expr>(value).ok()? {
+            syn::Expr::Array(array) => Some((quote! { &[u8] }, quote! { &#array })),
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::ByteStr(lit),
+                ..
+            }) => Some((quote! { &[u8] }, quote! { #lit })),
+            _ => None,
+        },
+        IdlType::Simple(name) if name == "pubkey" || name == "publicKey" => {
+            let bytes: [u8; 32] = bs58::decode(unquoted()).into_vec().ok()?.try_into().ok()?;
+            Some((
+                quote! { Pubkey },
+                quote! { Pubkey::new_from_array([#(#bytes),*]) },
+            ))
         }
+        IdlType::Simple(name) if SCALARS.contains(&name.as_str()) => {
+            let value = parse_str::<syn::Expr>(value).ok()?;
+            Some((map_idl_type(&constant.ty), quote! { #value }))
+        }
+        IdlType::Array {
+            array: ArrayType::Tuple((inner, _)),
+        } if matches!(&**inner, IdlType::Simple(name) if SCALARS.contains(&name.as_str())) => {
+            let value = parse_str::<syn::Expr>(value).ok()?;
+            Some((map_idl_type(&constant.ty), quote! { #value }))
+        }
+        _ => None,
     }
+}
 
+fn generate_event(event: &Event, types: &Option<Vec<TypeDef>>) -> Result<TokenStream> {
     // Helper function to generate field tokens with Pubkey serialization
     fn generate_field_tokens(fields: &[EventField]) -> Vec<TokenStream> {
         fields
             .iter()
             .map(|f| {
-                let field_name = format_ident!("{}", f.name.to_snake_case());
+                let field_name = snake_ident(&f.name);
                 let field_type = map_idl_type(&f.ty);
 
                 // Add custom serde attribute for Pubkey fields
-                let serde_attr = if is_pubkey_type(&f.ty) {
-                    quote! {
-                        #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize_pubkey_as_string"))]
-                    }
-                } else {
-                    quote! {}
-                };
+                let serde_rename = serde_rename_attr(&f.name.to_snake_case());
+                let serde_attr = serde_field_attr(&f.ty);
 
                 quote! {
+                    #serde_rename
                     #serde_attr
                     pub #field_name: #field_type
                 }
@@ -1154,19 +4232,17 @@ fn generate_event(event: &Event, types: &Option<Vec<TypeDef>>) -> Result<TokenSt
                 named_fields
                     .iter()
                     .map(|f| {
-                        let field_name = format_ident!("{}", f.name.to_snake_case());
+                        let field_name = snake_ident(&f.name);
                         let field_type = map_idl_type(&f.ty);
+                        let field_docs = generate_docs(f.docs.as_ref());
 
                         // Add custom serde attribute for Pubkey fields
-                        let serde_attr = if is_pubkey_type(&f.ty) {
-                            quote! {
-                                #[cfg_attr(feature = "serde", serde(serialize_with = "crate::serialize_pubkey_as_string"))]
-                            }
-                        } else {
-                            quote! {}
-                        };
+                        let serde_rename = serde_rename_attr(&f.name.to_snake_case());
+                        let serde_attr = serde_field_attr(&f.ty);
 
                         quote! {
+                            #field_docs
+                            #serde_rename
                             #serde_attr
                             pub #field_name: #field_type
                         }
@@ -1180,10 +4256,22 @@ fn generate_event(event: &Event, types: &Option<Vec<TypeDef>>) -> Result<TokenSt
         }
     }
 
-    let name = format_ident!("{}", event.name);
+    let owner = format!("event `{}`", event.name);
+    if let Some(fields) = &event.fields {
+        check_ident_collisions(
+            "field",
+            &owner,
+            fields.iter().map(|f| f.name.as_str()),
+            snake_ident,
+        )?;
+    }
+
+    let name = rust_ident(&event.name);
     let wrapper_name = format_ident!("{}Event", event.name);
 
-    // Determine if we have fields to generate
+    // Determine if we have fields to generate; an event aliasing a non-struct type
+    // becomes a newtype around it instead
+    let mut newtype_field = None;
     let field_tokens = if let Some(fields) = &event.fields {
         // Old format: fields are directly in the event
         generate_field_tokens(fields)
@@ -1197,6 +4285,30 @@ fn generate_event(event: &Event, types: &Option<Vec<TypeDef>>) -> Result<TokenSt
                     // Enums as events are unusual, skip them
                     return Ok(TokenStream::new());
                 }
+                // Aliases are already expanded, so this is the underlying type
+                TypeDefType::Alias { alias } => {
+                    let target = match alias {
+                        IdlType::Defined { defined } => {
+                            types.iter().find(|t| t.name == defined.name())
+                        }
+                        _ => None,
+                    };
+                    match target {
+                        Some(TypeDef {
+                            ty: TypeDefType::Struct { fields },
+                            generics: None,
+                            ..
+                        }) => generate_field_tokens_from_struct_fields(fields),
+                        Some(TypeDef {
+                            ty: TypeDefType::Enum { .. },
+                            ..
+                        }) => return Ok(TokenStream::new()),
+                        _ => {
+                            newtype_field = Some(alias);
+                            vec![]
+                        }
+                    }
+                }
             }
         } else {
             // No fields and no matching type definition
@@ -1208,7 +4320,7 @@ fn generate_event(event: &Event, types: &Option<Vec<TypeDef>>) -> Result<TokenSt
     };
 
     // If we have no fields, return empty
-    if field_tokens.is_empty() {
+    if field_tokens.is_empty() && newtype_field.is_none() {
         return Ok(TokenStream::new());
     }
 
@@ -1228,13 +4340,30 @@ fn generate_event(event: &Event, types: &Option<Vec<TypeDef>>) -> Result<TokenSt
     // Generate data struct with enhanced documentation
     let enhanced_docs = format!("Event: {}\n///\n/// # Usage\n/// ```no_run\n/// use crate::events::*;\n///\n/// // Parse event from transaction data\n/// let event = parse_event(&event_data)?;\n/// match event {{\n///     ParsedEvent::{}(e) => println!(\"Event: {{:?}}\", e),\n///     _ => {{}}\n/// }}\n/// ```", event.name, event.name.to_pascal_case());
 
+    let struct_body = match newtype_field {
+        Some(inner) => {
+            let inner_type = map_idl_type(inner);
+            let serde_attr = serde_field_attr(inner);
+            quote! { (#serde_attr pub #inner_type); }
+        }
+        None => quote! { { #(#field_tokens),* } },
+    };
+
+    // IDL docs on the event, falling back to those on its type definition
+    let event_docs = generate_docs(event.docs.as_ref().or_else(|| {
+        types
+            .iter()
+            .flatten()
+            .find(|t| t.name == event.name)
+            .and_then(|t| t.docs.as_ref())
+    }));
+
     tokens.extend(quote! {
+        #event_docs
         #[doc = #enhanced_docs]
         #[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
         #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-        pub struct #name {
-            #(#field_tokens),*
-        }
+        pub struct #name #struct_body
     });
 
     // Generate wrapper struct with discriminator handling
@@ -1249,24 +4378,13 @@ fn generate_event(event: &Event, types: &Option<Vec<TypeDef>>) -> Result<TokenSt
 
             impl borsh::BorshSerialize for #wrapper_name {
                 fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
-                    #discm_const.serialize(writer)?;
-                    self.0.serialize(writer)
+                    solana_idl_codegen_runtime::serialize_with_discriminator(&#discm_const, &self.0, writer)
                 }
             }
 
             impl #wrapper_name {
                 pub fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
-                    let maybe_discm = <[u8; 8]>::deserialize(buf)?;
-                    if maybe_discm != #discm_const {
-                        return Err(std::io::Error::new(
-                            std::io::ErrorKind::InvalidData,
-                            format!(
-                                "discm does not match. Expected: {:?}. Received: {:?}",
-                                #discm_const, maybe_discm
-                            ),
-                        ));
-                    }
-                    Ok(Self(#name::deserialize(buf)?))
+                    solana_idl_codegen_runtime::deserialize_with_discriminator(&#discm_const, buf).map(Self)
                 }
             }
         });
@@ -1333,16 +4451,7 @@ fn generate_event_parsing_helpers(events: &[Event]) -> Result<TokenStream> {
             #(#event_variants),*
         }
 
-        /// Error type for event parsing
-        #[derive(Debug, thiserror::Error)]
-        pub enum EventParseError {
-            #[error("Data too short for discriminator")]
-            DataTooShort,
-            #[error("Unknown event discriminator: {0:?}")]
-            UnknownDiscriminator([u8; 8]),
-            #[error("Deserialization error: {0}")]
-            DeserializationError(String),
-        }
+        pub use solana_idl_codegen_runtime::EventParseError;
 
         /// Parse an event from raw bytes (including discriminator)
         ///
@@ -1459,10 +4568,12 @@ fn map_idl_type(ty: &IdlType) -> TokenStream {
             "f32" => quote! { f32 },
             "f64" => quote! { f64 },
             "string" => quote! { String },
+            "u256" => quote! { crate::U256 },
+            "i256" => quote! { crate::I256 },
             "publicKey" | "pubkey" | "Pubkey" => quote! { Pubkey },
             "bytes" => quote! { Vec<u8> },
             _ => {
-                let ident = format_ident!("{}", s);
+                let ident = rust_ident(s);
                 quote! { #ident }
             }
         },
@@ -1474,19 +4585,183 @@ fn map_idl_type(ty: &IdlType) -> TokenStream {
             let inner = map_idl_type(option);
             quote! { Option<#inner> }
         }
+        IdlType::COption { coption } => {
+            let inner = map_idl_type(coption);
+            quote! { crate::COption<#inner> }
+        }
+        IdlType::HashMap {
+            hash_map: (key, value),
+        } => {
+            let key = map_idl_type(key);
+            let value = map_idl_type(value);
+            quote! { std::collections::HashMap<#key, #value> }
+        }
+        IdlType::BTreeMap {
+            btree_map: (key, value),
+        } => {
+            let key = map_idl_type(key);
+            let value = map_idl_type(value);
+            quote! { std::collections::BTreeMap<#key, #value> }
+        }
+        IdlType::HashSet { hash_set } => {
+            let inner = map_idl_type(hash_set);
+            quote! { std::collections::HashSet<#inner> }
+        }
+        IdlType::BTreeSet { btree_set } => {
+            let inner = map_idl_type(btree_set);
+            quote! { std::collections::BTreeSet<#inner> }
+        }
         IdlType::Array { array } => match array {
             ArrayType::Tuple((inner, size)) => {
                 let inner_ty = map_idl_type(inner);
                 quote! { [#inner_ty; #size] }
             }
+            ArrayType::Generic((inner, len)) => {
+                let inner_ty = map_idl_type(inner);
+                let len = rust_ident(len);
+                quote! { [#inner_ty; #len] }
+            }
         },
         IdlType::Defined { defined } => {
-            let ident = format_ident!("{}", defined.name());
+            let ident = rust_ident(defined.name());
+            let generic_args: Vec<_> = match defined {
+                DefinedTypeOrString::Nested(DefinedType {
+                    generics: Some(args),
+                    ..
+                }) => args.iter().map(map_generic_arg).collect(),
+                _ => vec![],
+            };
+            if generic_args.is_empty() {
+                quote! { #ident }
+            } else {
+                quote! { #ident<#(#generic_args),*> }
+            }
+        }
+        IdlType::Generic { generic } => {
+            let ident = rust_ident(generic);
             quote! { #ident }
         }
     }
 }
 
+/// Like [`map_idl_type`], but wraps by-value references to the types in `boxed` in
+/// `Box` so that recursive types have a finite size
+///
+/// Only references stored inline need it: `Vec`s, maps and sets already allocate.
+/// `Box<T>` has the same Borsh and serde encoding as `T`.
+fn map_field_type(ty: &IdlType, boxed: &HashSet<&str>) -> TokenStream {
+    if boxed.is_empty() {
+        return map_idl_type(ty);
+    }
+    match ty {
+        IdlType::Simple(name) if boxed.contains(name.as_str()) => {
+            let inner = map_idl_type(ty);
+            quote! { Box<#inner> }
+        }
+        IdlType::Defined { defined } if boxed.contains(defined.name()) => {
+            let inner = map_idl_type(ty);
+            quote! { Box<#inner> }
+        }
+        IdlType::Option { option } => {
+            let inner = map_field_type(option, boxed);
+            quote! { Option<#inner> }
+        }
+        IdlType::COption { coption } => {
+            let inner = map_field_type(coption, boxed);
+            quote! { crate::COption<#inner> }
+        }
+        IdlType::Array {
+            array: ArrayType::Tuple((inner, size)),
+        } => {
+            let inner_ty = map_field_type(inner, boxed);
+            quote! { [#inner_ty; #size] }
+        }
+        IdlType::Array {
+            array: ArrayType::Generic((inner, len)),
+        } => {
+            let inner_ty = map_field_type(inner, boxed);
+            let len = rust_ident(len);
+            quote! { [#inner_ty; #len] }
+        }
+        _ => map_idl_type(ty),
+    }
+}
+
+/// Map a generic argument of a defined type reference
+///
+/// Const arguments are either integer literals or the name of a const parameter
+/// of the enclosing type, which Rust needs wrapped in braces.
+fn map_generic_arg(arg: &GenericArg) -> TokenStream {
+    match arg {
+        GenericArg::Type { ty } => map_idl_type(ty),
+        GenericArg::Const { value } => match value.parse::<u64>() {
+            Ok(n) => {
+                let lit = proc_macro2::Literal::u64_unsuffixed(n);
+                quote! { #lit }
+            }
+            Err(_) => {
+                let ident = rust_ident(value);
+                quote! { { #ident } }
+            }
+        },
+    }
+}
+
+/// Build the `<...>` parameter lists for a generic type definition
+///
+/// Returns the declaration generics (`<T, const N: usize>`, with `type_bound` on
+/// every type parameter if given) and the generics used to name the type in impls
+/// (`<T, N>`); both are empty for non-generic types.
+fn type_def_generics(
+    generics: Option<&Vec<GenericParam>>,
+    type_bound: Option<TokenStream>,
+) -> (TokenStream, TokenStream) {
+    let params = match generics {
+        Some(params) if !params.is_empty() => params,
+        _ => return (TokenStream::new(), TokenStream::new()),
+    };
+    let decls = params.iter().map(|p| {
+        let name = rust_ident(p.name());
+        match p {
+            GenericParam::Type { .. } => match &type_bound {
+                Some(bound) => quote! { #name: #bound },
+                None => quote! { #name },
+            },
+            GenericParam::Const { ty, .. } => {
+                let ty = map_idl_type(&IdlType::Simple(ty.clone()));
+                quote! { const #name: #ty }
+            }
+        }
+    });
+    let names = params.iter().map(|p| rust_ident(p.name()));
+    (quote! { <#(#decls),*> }, quote! { <#(#names),*> })
+}
+
+/// Serde bounds for a generic type definition
+///
+/// Fields routed through the `serde(with = ...)` helpers don't contribute to serde's
+/// inferred bounds, so every type parameter is bound explicitly instead, to
+/// `serde_int::Repr` since fields of a parameter type go through `serde_int::repr`.
+fn serde_generic_bound(generics: Option<&Vec<GenericParam>>) -> TokenStream {
+    let type_params: Vec<_> = generics
+        .into_iter()
+        .flatten()
+        .filter(|p| matches!(p, GenericParam::Type { .. }))
+        .map(|p| rust_ident(p.name()).to_string())
+        .collect();
+    if type_params.is_empty() {
+        return TokenStream::new();
+    }
+    let bound = type_params
+        .iter()
+        .map(|p| format!("{}: crate::serde_int::Repr", p))
+        .collect::<Vec<_>>()
+        .join(", ");
+    quote! {
+        #[cfg_attr(feature = "serde", serde(bound = #bound))]
+    }
+}
+
 fn generate_docs(docs: Option<&Vec<String>>) -> TokenStream {
     if let Some(doc_lines) = docs {
         let docs: Vec<_> = doc_lines
@@ -1623,12 +4898,546 @@ mod tests {
         let defined_type = IdlType::Defined {
             defined: DefinedTypeOrString::Nested(DefinedType {
                 name: "MyStruct".to_string(),
+                generics: None,
             }),
         };
         let result = map_idl_type(&defined_type);
         assert_eq!(result.to_string(), quote! { MyStruct }.to_string());
     }
 
+    #[test]
+    fn test_serde_field_attr_pubkey_shapes() {
+        let pubkey = || Box::new(IdlType::Simple("pubkey".to_string()));
+        let test_cases = vec![
+            (
+                IdlType::Simple("publicKey".to_string()),
+                "\"crate::serde_pubkey\"",
+            ),
+            (
+                IdlType::Option { option: pubkey() },
+                "\"crate::serde_pubkey::option\"",
+            ),
+            (
+                IdlType::Vec { vec: pubkey() },
+                "\"crate::serde_pubkey::vec\"",
+            ),
+            (
+                IdlType::Array {
+                    array: ArrayType::Tuple((pubkey(), 64)),
+                },
+                "\"crate::serde_pubkey::array\"",
+            ),
+        ];
+
+        for (input, expected) in test_cases {
+            let result = serde_field_attr(&input).to_string();
+            assert!(
+                result.contains(expected),
+                "Expected {} for {:?}, got: {}",
+                expected,
+                input,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_serde_field_attr_non_pubkey() {
+        let ty = IdlType::Option {
+            option: Box::new(IdlType::Simple("u32".to_string())),
+        };
+        assert_eq!(serde_field_attr(&ty).to_string(), "");
+    }
+
+    #[test]
+    fn test_serde_field_attr_large_arrays() {
+        let array = |elem: &str, len: usize| IdlType::Array {
+            array: ArrayType::Tuple((Box::new(IdlType::Simple(elem.to_string())), len)),
+        };
+
+        assert!(serde_field_attr(&array("u8", 64))
+            .to_string()
+            .contains("\"crate::serde_bytes\""));
+        // Byte arrays use the bytes helper at any length so the encoding is uniform
+        assert!(serde_field_attr(&array("u8", 16))
+            .to_string()
+            .contains("\"crate::serde_bytes\""));
+        assert!(serde_field_attr(&array("u32", 60))
+            .to_string()
+            .contains("\"crate::serde_array\""));
+        assert_eq!(serde_field_attr(&array("u32", 4)).to_string(), "");
+    }
+
+    #[test]
+    fn test_map_idl_type_shank_types() {
+        let simple = |name: &str| Box::new(IdlType::Simple(name.to_string()));
+        let test_cases = vec![
+            (*simple("u256"), quote! { crate::U256 }),
+            (*simple("i256"), quote! { crate::I256 }),
+            (
+                IdlType::COption {
+                    coption: simple("pubkey"),
+                },
+                quote! { crate::COption<Pubkey> },
+            ),
+            (
+                IdlType::HashMap {
+                    hash_map: (simple("string"), simple("u64")),
+                },
+                quote! { std::collections::HashMap<String, u64> },
+            ),
+            (
+                IdlType::BTreeMap {
+                    btree_map: (simple("u32"), simple("bool")),
+                },
+                quote! { std::collections::BTreeMap<u32, bool> },
+            ),
+            (
+                IdlType::HashSet {
+                    hash_set: simple("u8"),
+                },
+                quote! { std::collections::HashSet<u8> },
+            ),
+            (
+                IdlType::BTreeSet {
+                    btree_set: simple("string"),
+                },
+                quote! { std::collections::BTreeSet<String> },
+            ),
+        ];
+
+        for (input, expected) in test_cases {
+            assert_eq!(map_idl_type(&input).to_string(), expected.to_string());
+        }
+    }
+
+    #[test]
+    fn test_serde_field_attr_shank_containers() {
+        let simple = |name: &str| Box::new(IdlType::Simple(name.to_string()));
+        let test_cases = vec![
+            (
+                IdlType::COption {
+                    coption: simple("pubkey"),
+                },
+                "\"crate::serde_pubkey::coption\"",
+            ),
+            (
+                IdlType::COption {
+                    coption: simple("u64"),
+                },
+                "\"crate::serde_int::coption\"",
+            ),
+            (
+                IdlType::HashMap {
+                    hash_map: (simple("pubkey"), simple("u32")),
+                },
+                "\"crate::serde_pubkey::map\"",
+            ),
+            // Wide values need `repr`, which also renders the keys as base58
+            (
+                IdlType::HashMap {
+                    hash_map: (simple("pubkey"), simple("u64")),
+                },
+                "\"crate::serde_int::repr\"",
+            ),
+            (
+                IdlType::BTreeSet {
+                    btree_set: simple("publicKey"),
+                },
+                "\"crate::serde_pubkey::set\"",
+            ),
+        ];
+        for (input, expected) in test_cases {
+            let result = serde_field_attr(&input).to_string();
+            assert!(result.contains(expected), "{:?}: {}", input, result);
+        }
+
+        // Containers of plain values need no helper
+        let plain = IdlType::HashMap {
+            hash_map: (simple("string"), simple("u32")),
+        };
+        assert_eq!(serde_field_attr(&plain).to_string(), "");
+    }
+
+    #[test]
+    fn test_serde_field_attr_wide_ints() {
+        let int = |name: &str| Box::new(IdlType::Simple(name.to_string()));
+        let test_cases = vec![
+            (IdlType::Simple("u64".to_string()), "\"crate::serde_int\""),
+            (IdlType::Simple("i128".to_string()), "\"crate::serde_int\""),
+            (
+                IdlType::Option {
+                    option: int("u128"),
+                },
+                "\"crate::serde_int::option\"",
+            ),
+            (
+                IdlType::Vec { vec: int("i64") },
+                "\"crate::serde_int::vec\"",
+            ),
+            (
+                IdlType::Array {
+                    array: ArrayType::Tuple((int("u64"), 60)),
+                },
+                "\"crate::serde_int::array\"",
+            ),
+        ];
+
+        for (input, expected) in test_cases {
+            let result = serde_field_attr(&input).to_string();
+            assert!(
+                result.contains(expected),
+                "Expected {} for {:?}, got: {}",
+                expected,
+                input,
+                result
+            );
+        }
+    }
+
+    #[test]
+    fn test_serde_field_attr_nested_wide_ints() {
+        let int = |name: &str| Box::new(IdlType::Simple(name.to_string()));
+        let nested = vec![
+            // Map values
+            IdlType::HashMap {
+                hash_map: (int("string"), int("u64")),
+            },
+            IdlType::BTreeMap {
+                btree_map: (int("u8"), int("i128")),
+            },
+            // Nested containers
+            IdlType::Option {
+                option: Box::new(IdlType::Vec { vec: int("u64") }),
+            },
+            IdlType::Vec {
+                vec: Box::new(IdlType::Option {
+                    option: int("u128"),
+                }),
+            },
+            // Set elements
+            IdlType::HashSet {
+                hash_set: int("u64"),
+            },
+            // Generic instantiations and parameters
+            IdlType::Defined {
+                defined: DefinedTypeOrString::Nested(DefinedType {
+                    name: "Pair".to_string(),
+                    generics: Some(vec![GenericArg::Type {
+                        ty: IdlType::Simple("u64".to_string()),
+                    }]),
+                }),
+            },
+            IdlType::Generic {
+                generic: "T".to_string(),
+            },
+        ];
+        for input in nested {
+            let result = serde_field_attr(&input).to_string();
+            assert!(
+                result.contains("\"crate::serde_int::repr\""),
+                "{:?}: {}",
+                input,
+                result
+            );
+        }
+
+        // Narrow integers anywhere in the tree need no helper
+        let plain = vec![
+            IdlType::Option {
+                option: Box::new(IdlType::Vec { vec: int("u32") }),
+            },
+            IdlType::Defined {
+                defined: DefinedTypeOrString::Nested(DefinedType {
+                    name: "Pair".to_string(),
+                    generics: Some(vec![GenericArg::Type {
+                        ty: IdlType::Simple("u16".to_string()),
+                    }]),
+                }),
+            },
+        ];
+        for input in plain {
+            assert_eq!(serde_field_attr(&input).to_string(), "", "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_bytemuck_struct_derives_serde() {
+        let type_def = TypeDef {
+            name: "TickArray".to_string(),
+            docs: None,
+            ty: TypeDefType::Struct {
+                fields: StructFields::Named(vec![Field {
+                    name: "ticks".to_string(),
+                    ty: IdlType::Array {
+                        array: ArrayType::Tuple((Box::new(IdlType::Simple("u64".to_string())), 60)),
+                    },
+                    docs: None,
+                }]),
+            },
+            serialization: Some("bytemuckunsafe".to_string()),
+            repr: Some(crate::idl::Repr {
+                kind: "c".to_string(),
+                packed: Some(true),
+            }),
+            generics: None,
+        };
+        let result = generate_type_def(&type_def, &HashSet::new())
+            .unwrap()
+            .to_string();
+        assert!(result.contains("Pod"));
+        assert!(result.contains("serde :: Serialize"));
+        assert!(result.contains("crate::serde_int::array"));
+    }
+
+    #[test]
+    fn test_nested_large_array_skips_serde() {
+        let type_def = TypeDef {
+            name: "Nested".to_string(),
+            docs: None,
+            ty: TypeDefType::Struct {
+                fields: StructFields::Named(vec![Field {
+                    name: "rows".to_string(),
+                    ty: IdlType::Vec {
+                        vec: Box::new(IdlType::Array {
+                            array: ArrayType::Tuple((
+                                Box::new(IdlType::Simple("u64".to_string())),
+                                40,
+                            )),
+                        }),
+                    },
+                    docs: None,
+                }]),
+            },
+            serialization: None,
+            repr: None,
+            generics: None,
+        };
+        let result = generate_type_def(&type_def, &HashSet::new())
+            .unwrap()
+            .to_string();
+        assert!(!result.contains("serde :: Serialize"));
+    }
+
+    #[test]
+    fn test_pubkey_serde_applied_to_types_and_args() {
+        let type_def = TypeDef {
+            name: "Config".to_string(),
+            docs: None,
+            ty: TypeDefType::Struct {
+                fields: StructFields::Named(vec![Field {
+                    name: "admins".to_string(),
+                    ty: IdlType::Vec {
+                        vec: Box::new(IdlType::Simple("pubkey".to_string())),
+                    },
+                    docs: None,
+                }]),
+            },
+            serialization: None,
+            repr: None,
+            generics: None,
+        };
+        let result = generate_type_def(&type_def, &HashSet::new())
+            .unwrap()
+            .to_string();
+        assert!(result.contains("crate::serde_pubkey::vec"));
+
+        let instructions = vec![Instruction {
+            name: "set_admin".to_string(),
+            docs: None,
+            discriminator: Some(vec![1, 2, 3, 4, 5, 6, 7, 8]),
+            accounts: vec![],
+            args: vec![Arg {
+                name: "new_admin".to_string(),
+                ty: IdlType::Option {
+                    option: Box::new(IdlType::Simple("pubkey".to_string())),
+                },
+                docs: None,
+            }],
+            discriminant: None,
+            returns: None,
+        }];
+        let result = generate_instructions(&instructions, true, false)
+            .unwrap()
+            .to_string();
+        assert!(result.contains("crate::serde_pubkey::option"));
+    }
+
+    #[test]
+    fn test_rust_ident_sanitization() {
+        let test_cases = vec![
+            ("amount", "amount"),
+            ("type", "r#type"),
+            ("move", "r#move"),
+            ("ref", "r#ref"),
+            ("struct", "r#struct"),
+            ("self", "self_"),
+            ("Self", "Self_"),
+            ("super", "super_"),
+            ("crate", "crate_"),
+            ("2nd", "_2nd"),
+        ];
+
+        for (input, expected) in test_cases {
+            assert_eq!(rust_ident(input).to_string(), expected, "for {}", input);
+        }
+        assert_eq!(snake_ident("minAmountOut").to_string(), "min_amount_out");
+        assert_eq!(pascal_ident("self").to_string(), "Self_");
+    }
+
+    #[test]
+    fn test_serde_rename_attr() {
+        // Raw identifiers already serialize under their plain name
+        assert_eq!(serde_rename_attr("type").to_string(), "");
+        assert_eq!(serde_rename_attr("amount").to_string(), "");
+        assert!(serde_rename_attr("self")
+            .to_string()
+            .contains("rename = \"self\""));
+        assert!(serde_rename_attr("Self")
+            .to_string()
+            .contains("rename = \"Self\""));
+    }
+
+    #[test]
+    fn test_keyword_fields_generate_valid_code() {
+        let type_def = TypeDef {
+            name: "Order".to_string(),
+            docs: None,
+            ty: TypeDefType::Struct {
+                fields: StructFields::Named(vec![
+                    Field {
+                        name: "type".to_string(),
+                        ty: IdlType::Simple("u8".to_string()),
+                        docs: None,
+                    },
+                    Field {
+                        name: "self".to_string(),
+                        ty: IdlType::Simple("bool".to_string()),
+                        docs: None,
+                    },
+                ]),
+            },
+            serialization: None,
+            repr: None,
+            generics: None,
+        };
+        let result = generate_type_def(&type_def, &HashSet::new()).unwrap();
+        syn::parse2::<syn::File>(result.clone()).expect("generated code should parse");
+        let result = result.to_string();
+        assert!(result.contains("pub r#type : u8"));
+        assert!(result.contains("pub self_ : bool"));
+        assert!(result.contains("rename = \"self\""));
+    }
+
+    #[test]
+    fn test_bare_string_reference_to_keyword_type() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "metadata": { "name": "t", "version": "0.1.0", "spec": "0.1.0" },
+                "instructions": [{
+                    "name": "play",
+                    "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                    "accounts": [],
+                    "args": [{ "name": "round", "type": "match" }]
+                }],
+                "types": [
+                    {
+                        "name": "match",
+                        "type": { "kind": "struct", "fields": [{ "name": "id", "type": "u8" }] }
+                    },
+                    {
+                        "name": "Game",
+                        "type": {
+                            "kind": "struct",
+                            "fields": [
+                                { "name": "current", "type": "match" },
+                                { "name": "history", "type": { "vec": "match" } }
+                            ]
+                        }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let code = generate(&idl, "test_program").unwrap();
+        assert!(code.types.contains("pub struct r#match"));
+        assert!(code.types.contains("pub current: r#match,"));
+        assert!(code.types.contains("pub history: Vec<r#match>,"));
+        assert!(code.instructions.contains("pub round: r#match,"));
+    }
+
+    #[test]
+    fn test_field_collision_is_an_error() {
+        let type_def = TypeDef {
+            name: "Pool".to_string(),
+            docs: None,
+            ty: TypeDefType::Struct {
+                fields: StructFields::Named(vec![
+                    Field {
+                        name: "feeRate".to_string(),
+                        ty: IdlType::Simple("u16".to_string()),
+                        docs: None,
+                    },
+                    Field {
+                        name: "fee_rate".to_string(),
+                        ty: IdlType::Simple("u16".to_string()),
+                        docs: None,
+                    },
+                ]),
+            },
+            serialization: None,
+            repr: None,
+            generics: None,
+        };
+        let err = generate_type_def(&type_def, &HashSet::new())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("`feeRate` and `fee_rate`"), "{}", err);
+        assert!(err.contains("type `Pool`"), "{}", err);
+    }
+
+    #[test]
+    fn test_instruction_collisions_are_errors() {
+        let instruction = |name: &str, args: Vec<&str>| Instruction {
+            name: name.to_string(),
+            docs: None,
+            discriminator: Some(vec![1, 2, 3, 4, 5, 6, 7, 8]),
+            accounts: vec![],
+            args: args
+                .into_iter()
+                .map(|a| Arg {
+                    name: a.to_string(),
+                    ty: IdlType::Simple("u64".to_string()),
+                    docs: None,
+                })
+                .collect(),
+            discriminant: None,
+            returns: None,
+        };
+
+        let err = generate_instructions(
+            &[
+                instruction("swapBase", vec![]),
+                instruction("swap_base", vec![]),
+            ],
+            true,
+            false,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("instruction names"), "{}", err);
+
+        let err = generate_instructions(
+            &[instruction("swap", vec!["amountIn", "amount_in"])],
+            true,
+            false,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("instruction `swap`"), "{}", err);
+    }
+
     #[test]
     fn test_generate_docs_empty() {
         let result = generate_docs(None);
@@ -1695,9 +5504,10 @@ mod tests {
             },
             serialization: None,
             repr: None,
+            generics: None,
         };
 
-        let result = generate_type_def(&type_def).unwrap();
+        let result = generate_type_def(&type_def, &HashSet::new()).unwrap();
         let result_str = result.to_string();
 
         assert!(result_str.contains("pub struct MyStruct"));
@@ -1721,9 +5531,10 @@ mod tests {
             },
             serialization: None,
             repr: None,
+            generics: None,
         };
 
-        let result = generate_type_def(&type_def).unwrap();
+        let result = generate_type_def(&type_def, &HashSet::new()).unwrap();
         let result_str = result.to_string();
 
         assert!(result_str.contains("This is a documented struct"));
@@ -1754,9 +5565,10 @@ mod tests {
                 kind: "C".to_string(),
                 packed: None,
             }),
+            generics: None,
         };
 
-        let result = generate_type_def(&type_def).unwrap();
+        let result = generate_type_def(&type_def, &HashSet::new()).unwrap();
         let result_str = result.to_string();
 
         assert!(result_str.contains("pub struct MyBytemuckStruct"));
@@ -1767,31 +5579,142 @@ mod tests {
     }
 
     #[test]
-    fn test_generate_type_def_bytemuck_packed_struct() {
+    fn test_generate_type_def_bytemuck_packed_struct() {
+        let type_def = TypeDef {
+            name: "PackedStruct".to_string(),
+            docs: None,
+            ty: TypeDefType::Struct {
+                fields: StructFields::Named(vec![Field {
+                    name: "field1".to_string(),
+                    ty: IdlType::Simple("u64".to_string()),
+                    docs: None,
+                }]),
+            },
+            serialization: Some("bytemuckunsafe".to_string()),
+            repr: Some(Repr {
+                kind: "C".to_string(),
+                packed: Some(true),
+            }),
+            generics: None,
+        };
+
+        let result = generate_type_def(&type_def, &HashSet::new()).unwrap();
+        let result_str = result.to_string();
+
+        assert!(
+            result_str.contains("repr")
+                && result_str.contains("C")
+                && result_str.contains("packed")
+        );
+    }
+
+    #[test]
+    fn test_generate_type_def_generic_struct() {
+        let type_def = TypeDef {
+            name: "Buffer".to_string(),
+            docs: None,
+            ty: TypeDefType::Struct {
+                fields: StructFields::Named(vec![
+                    Field {
+                        name: "value".to_string(),
+                        ty: IdlType::Generic {
+                            generic: "T".to_string(),
+                        },
+                        docs: None,
+                    },
+                    Field {
+                        name: "data".to_string(),
+                        ty: IdlType::Array {
+                            array: ArrayType::Generic((
+                                Box::new(IdlType::Simple("u8".to_string())),
+                                "N".to_string(),
+                            )),
+                        },
+                        docs: None,
+                    },
+                ]),
+            },
+            serialization: None,
+            repr: None,
+            generics: Some(vec![
+                GenericParam::Type {
+                    name: "T".to_string(),
+                },
+                GenericParam::Const {
+                    name: "N".to_string(),
+                    ty: "usize".to_string(),
+                },
+            ]),
+        };
+
+        let result = generate_type_def(&type_def, &HashSet::new())
+            .unwrap()
+            .to_string();
+        assert!(result.contains("pub struct Buffer < T , const N : usize >"));
+        assert!(result.contains("pub value : T"));
+        assert!(result.contains("pub data : [u8 ; N]"));
+        assert!(result.contains("crate::serde_bytes"));
+        assert!(result.contains("bound = \"T: crate::serde_int::Repr\""));
+        // Parameter-typed fields may be wide integers once instantiated
+        assert!(result.contains("crate::serde_int::repr"));
+        assert!(result.contains(
+            "impl < T : crate :: serde_int :: Repr , const N : usize > crate :: serde_int :: Plain for Buffer < T , N >"
+        ));
+    }
+
+    #[test]
+    fn test_generate_type_def_generic_bytemuck_bounds() {
         let type_def = TypeDef {
-            name: "PackedStruct".to_string(),
+            name: "Slot".to_string(),
             docs: None,
             ty: TypeDefType::Struct {
                 fields: StructFields::Named(vec![Field {
-                    name: "field1".to_string(),
-                    ty: IdlType::Simple("u64".to_string()),
+                    name: "value".to_string(),
+                    ty: IdlType::Generic {
+                        generic: "T".to_string(),
+                    },
                     docs: None,
                 }]),
             },
-            serialization: Some("bytemuckunsafe".to_string()),
+            serialization: Some("bytemuck".to_string()),
             repr: Some(Repr {
-                kind: "C".to_string(),
-                packed: Some(true),
+                kind: "c".to_string(),
+                packed: None,
             }),
+            generics: Some(vec![GenericParam::Type {
+                name: "T".to_string(),
+            }]),
         };
 
-        let result = generate_type_def(&type_def).unwrap();
-        let result_str = result.to_string();
-
+        let result = generate_type_def(&type_def, &HashSet::new())
+            .unwrap()
+            .to_string();
         assert!(
-            result_str.contains("repr")
-                && result_str.contains("C")
-                && result_str.contains("packed")
+            result.contains("unsafe impl < T : bytemuck :: Pod > bytemuck :: Pod for Slot < T >")
+        );
+    }
+
+    #[test]
+    fn test_map_idl_type_defined_with_generic_args() {
+        let ty = IdlType::Defined {
+            defined: DefinedTypeOrString::Nested(DefinedType {
+                name: "Wrapper".to_string(),
+                generics: Some(vec![
+                    GenericArg::Type {
+                        ty: IdlType::Simple("u64".to_string()),
+                    },
+                    GenericArg::Const {
+                        value: "32".to_string(),
+                    },
+                    GenericArg::Const {
+                        value: "N".to_string(),
+                    },
+                ]),
+            }),
+        };
+        assert_eq!(
+            map_idl_type(&ty).to_string(),
+            "Wrapper < u64 , 32 , { N } >"
         );
     }
 
@@ -1805,9 +5728,10 @@ mod tests {
             },
             serialization: None,
             repr: None,
+            generics: None,
         };
 
-        let result = generate_type_def(&type_def).unwrap();
+        let result = generate_type_def(&type_def, &HashSet::new()).unwrap();
         let result_str = result.to_string();
 
         assert!(result_str.contains("pub struct OptionBool"));
@@ -1826,18 +5750,23 @@ mod tests {
                     EnumVariant {
                         name: "Variant1".to_string(),
                         fields: None,
+                        value: None,
+                        docs: None,
                     },
                     EnumVariant {
                         name: "Variant2".to_string(),
                         fields: None,
+                        value: None,
+                        docs: None,
                     },
                 ],
             },
             serialization: None,
             repr: None,
+            generics: None,
         };
 
-        let result = generate_type_def(&type_def).unwrap();
+        let result = generate_type_def(&type_def, &HashSet::new()).unwrap();
         let result_str = result.to_string();
 
         assert!(result_str.contains("pub enum MyEnum"));
@@ -1846,6 +5775,68 @@ mod tests {
         assert!(result_str.contains("BorshSerialize"));
     }
 
+    #[test]
+    fn test_generate_type_def_enum_variant_docs() {
+        let type_def = TypeDef {
+            name: "Side".to_string(),
+            docs: None,
+            ty: TypeDefType::Enum {
+                variants: vec![EnumVariant {
+                    name: "Bid".to_string(),
+                    fields: None,
+                    value: None,
+                    docs: Some(vec!["Buy order".to_string()]),
+                }],
+            },
+            serialization: None,
+            repr: None,
+            generics: None,
+        };
+
+        let result_str = generate_type_def(&type_def, &HashSet::new())
+            .unwrap()
+            .to_string();
+        assert!(result_str.contains("# [doc = \"Buy order\"] Bid"));
+    }
+
+    #[test]
+    fn test_generate_type_def_enum_with_explicit_values() {
+        let variant = |name: &str, value: Option<u64>| EnumVariant {
+            name: name.to_string(),
+            fields: None,
+            value,
+            docs: None,
+        };
+        let mut type_def = TypeDef {
+            name: "Key".to_string(),
+            docs: None,
+            ty: TypeDefType::Enum {
+                variants: vec![
+                    variant("Uninitialized", Some(0)),
+                    variant("Metadata", Some(4)),
+                ],
+            },
+            serialization: None,
+            repr: None,
+            generics: None,
+        };
+
+        let result_str = generate_type_def(&type_def, &HashSet::new())
+            .unwrap()
+            .to_string();
+        assert!(result_str.contains("# [repr (u8)]"), "{}", result_str);
+        assert!(result_str.contains("# [borsh (use_discriminant = true)]"));
+        assert!(result_str.contains("Metadata = 4"));
+
+        type_def.ty = TypeDefType::Enum {
+            variants: vec![variant("Big", Some(256))],
+        };
+        let err = generate_type_def(&type_def, &HashSet::new())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("does not fit in a u8"), "{}", err);
+    }
+
     #[test]
     fn test_generate_type_def_enum_with_named_fields() {
         let type_def = TypeDef {
@@ -1866,13 +5857,16 @@ mod tests {
                             docs: None,
                         },
                     ])),
+                    value: None,
+                    docs: None,
                 }],
             },
             serialization: None,
             repr: None,
+            generics: None,
         };
 
-        let result = generate_type_def(&type_def).unwrap();
+        let result = generate_type_def(&type_def, &HashSet::new()).unwrap();
         let result_str = result.to_string();
 
         assert!(result_str.contains("VariantWithFields"));
@@ -1892,13 +5886,16 @@ mod tests {
                         IdlType::Simple("u64".to_string()),
                         IdlType::Simple("string".to_string()),
                     ])),
+                    value: None,
+                    docs: None,
                 }],
             },
             serialization: None,
             repr: None,
+            generics: None,
         };
 
-        let result = generate_type_def(&type_def).unwrap();
+        let result = generate_type_def(&type_def, &HashSet::new()).unwrap();
         let result_str = result.to_string();
 
         assert!(result_str.contains("TupleVariant"));
@@ -1920,9 +5917,10 @@ mod tests {
             },
             serialization: None,
             repr: None,
+            generics: None,
         };
 
-        let result = generate_type_def(&type_def).unwrap();
+        let result = generate_type_def(&type_def, &HashSet::new()).unwrap();
         let result_str = result.to_string();
 
         assert!(result_str.contains("camel_case_field"));
@@ -1939,11 +5937,13 @@ mod tests {
                 code: 6000,
                 name: "InvalidAmount".to_string(),
                 msg: Some("The amount is invalid".to_string()),
+                docs: None,
             },
             Error {
                 code: 6001,
                 name: "Unauthorized".to_string(),
                 msg: Some("User is not authorized".to_string()),
+                docs: None,
             },
         ];
 
@@ -1967,6 +5967,7 @@ mod tests {
             code: 6000,
             name: "ErrorWithoutMessage".to_string(),
             msg: None,
+            docs: None,
         }];
 
         let result = generate_errors(&errors).unwrap();
@@ -1986,6 +5987,74 @@ mod tests {
         assert!(result_str.contains("pub enum ErrorCode"));
     }
 
+    #[test]
+    fn test_generate_errors_with_docs() {
+        let errors = vec![Error {
+            code: 6000,
+            name: "Unauthorized".to_string(),
+            msg: Some("Unauthorized".to_string()),
+            docs: Some(vec!["Signer is not the vault authority".to_string()]),
+        }];
+
+        let result_str = generate_errors(&errors).unwrap().to_string();
+        assert!(result_str.contains(
+            "# [doc = \"Signer is not the vault authority\"] # [error (\"Unauthorized\")]"
+        ));
+    }
+
+    // ============================================================================
+    // Constant Generation Tests
+    // ============================================================================
+
+    fn constant(name: &str, ty: IdlType, value: &str) -> Constant {
+        Constant {
+            name: name.to_string(),
+            ty,
+            value: value.to_string(),
+            docs: None,
+        }
+    }
+
+    #[test]
+    fn test_generate_constants() {
+        let mut seed = constant("SEED", IdlType::Simple("bytes".to_string()), "[115, 101]");
+        seed.docs = Some(vec!["PDA seed".to_string()]);
+        let constants = vec![
+            seed,
+            constant("maxItems", IdlType::Simple("u64".to_string()), "10_000"),
+            constant("LABEL", IdlType::Simple("string".to_string()), "\"hello\""),
+            constant(
+                "ADMIN",
+                IdlType::Simple("pubkey".to_string()),
+                "11111111111111111111111111111111",
+            ),
+        ];
+
+        let result_str = generate_constants(&constants).unwrap().to_string();
+        assert!(
+            result_str.contains("# [doc = \"PDA seed\"] pub const SEED : & [u8] = & [115 , 101] ;")
+        );
+        assert!(result_str.contains("pub const MAX_ITEMS : u64 = 10_000 ;"));
+        assert!(result_str.contains("pub const LABEL : & str = \"hello\" ;"));
+        assert!(result_str.contains("pub const ADMIN : Pubkey = Pubkey :: new_from_array ([0u8"));
+    }
+
+    #[test]
+    fn test_generate_constants_skips_non_const_values() {
+        let constants = vec![
+            constant(
+                "LIST",
+                IdlType::Vec {
+                    vec: Box::new(IdlType::Simple("u8".to_string())),
+                },
+                "vec![1]",
+            ),
+            constant("ADMIN", IdlType::Simple("pubkey".to_string()), "not-base58"),
+        ];
+
+        assert!(generate_constants(&constants).unwrap().is_empty());
+    }
+
     // ============================================================================
     // Event Generation Tests
     // ============================================================================
@@ -2012,6 +6081,7 @@ mod tests {
                     index: false,
                 },
             ]),
+            docs: None,
         };
 
         let result = generate_event(&event, &None).unwrap();
@@ -2032,7 +6102,7 @@ mod tests {
         assert!(result_str.contains("pub fn deserialize"));
 
         // Check for custom serde serialization of Pubkey fields
-        assert!(result_str.contains("serialize_pubkey_as_string"));
+        assert!(result_str.contains("crate::serde_pubkey"));
     }
 
     #[test]
@@ -2045,6 +6115,7 @@ mod tests {
                 ty: IdlType::Simple("u64".to_string()),
                 index: false,
             }]),
+            docs: None,
         };
 
         let result = generate_event(&event, &None).unwrap();
@@ -2060,6 +6131,7 @@ mod tests {
             name: "EmptyEvent".to_string(),
             discriminator: Some(vec![1, 2, 3, 4, 5, 6, 7, 8]),
             fields: None,
+            docs: None,
         };
 
         let result = generate_event(&event, &None).unwrap();
@@ -2076,7 +6148,8 @@ mod tests {
         let event = Event {
             name: "AdminSetCreatorEvent".to_string(),
             discriminator: Some(vec![64, 69, 192, 104, 29, 30, 25, 107]),
-            fields: None, // No fields in event itself
+            fields: None, // No fields in event itself,
+            docs: None,
         };
 
         let types = Some(vec![TypeDef {
@@ -2103,6 +6176,7 @@ mod tests {
             },
             serialization: None,
             repr: None,
+            generics: None,
         }]);
 
         let result = generate_event(&event, &types).unwrap();
@@ -2123,7 +6197,7 @@ mod tests {
         assert!(result_str.contains("pub fn deserialize"));
 
         // Check for custom serde serialization of Pubkey fields
-        assert!(result_str.contains("serialize_pubkey_as_string"));
+        assert!(result_str.contains("crate::serde_pubkey"));
     }
 
     // ============================================================================
@@ -2139,6 +6213,8 @@ mod tests {
                 discriminator: Some(vec![175, 175, 109, 31, 13, 152, 155, 237]),
                 accounts: vec![],
                 args: vec![],
+                discriminant: None,
+                returns: None,
             },
             Instruction {
                 name: "transfer".to_string(),
@@ -2148,11 +6224,14 @@ mod tests {
                 args: vec![Arg {
                     name: "amount".to_string(),
                     ty: IdlType::Simple("u64".to_string()),
+                    docs: None,
                 }],
+                discriminant: None,
+                returns: None,
             },
         ];
 
-        let result = generate_instructions(&instructions, true).unwrap();
+        let result = generate_instructions(&instructions, true, false).unwrap();
         let result_str = result.to_string();
 
         assert!(result_str.contains("pub enum Instruction"));
@@ -2167,6 +6246,73 @@ mod tests {
         assert!(result_str.contains("try_from_slice"));
     }
 
+    #[test]
+    fn test_generate_instructions_return_data() {
+        let instructions = vec![Instruction {
+            name: "getPrice".to_string(),
+            docs: None,
+            discriminator: Some(vec![1, 2, 3, 4, 5, 6, 7, 8]),
+            accounts: vec![],
+            args: vec![],
+            discriminant: None,
+            returns: Some(IdlType::Simple("u64".to_string())),
+        }];
+
+        let result_str = generate_instructions(&instructions, true, false)
+            .unwrap()
+            .to_string();
+        assert!(result_str.contains("pub type GetPriceReturnData = u64 ;"));
+        assert!(result_str.contains(
+            "pub fn decode_get_price_return_data (data : & [u8]) -> std :: io :: Result < GetPriceReturnData >"
+        ));
+        // Trailing zero bytes trimmed by the runtime are padded back
+        assert!(result_str.contains("solana_idl_codegen_runtime :: decode_return_data (data)"));
+        assert!(!result_str.contains("try_from_slice (data)"));
+        assert!(result_str.contains("pub fn parse_return_data_log"));
+        assert!(result_str.contains("pub fn return_data_from_logs"));
+    }
+
+    #[test]
+    fn test_generate_instructions_without_returns_has_no_return_helpers() {
+        let instructions = vec![Instruction {
+            name: "initialize".to_string(),
+            docs: None,
+            discriminator: Some(vec![175, 175, 109, 31, 13, 152, 155, 237]),
+            accounts: vec![],
+            args: vec![],
+            discriminant: None,
+            returns: None,
+        }];
+
+        let result_str = generate_instructions(&instructions, true, false)
+            .unwrap()
+            .to_string();
+        assert!(!result_str.contains("ReturnData"));
+        assert!(!result_str.contains("parse_return_data_log"));
+    }
+
+    #[test]
+    fn test_generate_instructions_arg_docs() {
+        let instructions = vec![Instruction {
+            name: "deposit".to_string(),
+            docs: None,
+            discriminator: Some(vec![242, 35, 198, 137, 82, 225, 242, 182]),
+            accounts: vec![],
+            args: vec![Arg {
+                name: "amount".to_string(),
+                ty: IdlType::Simple("u64".to_string()),
+                docs: Some(vec!["Lamports to deposit".to_string()]),
+            }],
+            discriminant: None,
+            returns: None,
+        }];
+
+        let result_str = generate_instructions(&instructions, true, false)
+            .unwrap()
+            .to_string();
+        assert!(result_str.contains("# [doc = \"Lamports to deposit\"]"));
+    }
+
     #[test]
     fn test_generate_instructions_with_accounts() {
         let instructions = vec![Instruction {
@@ -2182,6 +6328,8 @@ mod tests {
                     pda: None,
                     address: None,
                     optional: None,
+                    accounts: None,
+                    relations: None,
                 },
                 AccountArg {
                     name: "pool".to_string(),
@@ -2191,12 +6339,16 @@ mod tests {
                     pda: None,
                     address: None,
                     optional: None,
+                    accounts: None,
+                    relations: None,
                 },
             ],
             args: vec![],
+            discriminant: None,
+            returns: None,
         }];
 
-        let result = generate_instructions(&instructions, true).unwrap();
+        let result = generate_instructions(&instructions, true, false).unwrap();
         let result_str = result.to_string();
 
         assert!(result_str.contains("SwapKeys"));
@@ -2216,21 +6368,26 @@ mod tests {
                 Arg {
                     name: "amount".to_string(),
                     ty: IdlType::Simple("u64".to_string()),
+                    docs: None,
                 },
                 Arg {
                     name: "recipient".to_string(),
                     ty: IdlType::Simple("publicKey".to_string()),
+                    docs: None,
                 },
                 Arg {
                     name: "memo".to_string(),
                     ty: IdlType::Option {
                         option: Box::new(IdlType::Simple("string".to_string())),
                     },
+                    docs: None,
                 },
             ],
+            discriminant: None,
+            returns: None,
         }];
 
-        let result = generate_instructions(&instructions, true).unwrap();
+        let result = generate_instructions(&instructions, true, false).unwrap();
         let result_str = result.to_string();
 
         assert!(result_str.contains("ComplexInstructionIxArgs"));
@@ -2249,6 +6406,8 @@ mod tests {
                 discriminator: None,
                 accounts: vec![],
                 args: vec![],
+                discriminant: None,
+                returns: None,
             },
             Instruction {
                 name: "second".to_string(),
@@ -2256,10 +6415,12 @@ mod tests {
                 discriminator: None,
                 accounts: vec![],
                 args: vec![],
+                discriminant: None,
+                returns: None,
             },
         ];
 
-        let result = generate_instructions(&instructions, true).unwrap();
+        let result = generate_instructions(&instructions, true, false).unwrap();
         let result_str = result.to_string();
 
         // Should generate with index-based discriminators
@@ -2267,6 +6428,63 @@ mod tests {
         assert!(result_str.contains("Second"));
     }
 
+    #[test]
+    fn test_instruction_discriminator_sources() {
+        let mut ix = Instruction {
+            name: "swap".to_string(),
+            docs: None,
+            discriminator: None,
+            accounts: vec![],
+            args: vec![],
+            discriminant: None,
+            returns: None,
+        };
+        assert_eq!(
+            instruction_discriminator(&ix, 2, false).unwrap(),
+            vec![2, 0, 0, 0, 0, 0, 0, 0]
+        );
+        // Shank IDLs fall back to a one-byte index
+        assert_eq!(instruction_discriminator(&ix, 2, true).unwrap(), vec![2]);
+
+        ix.discriminant = Some(crate::idl::Discriminant {
+            ty: "u8".to_string(),
+            value: 9,
+        });
+        assert_eq!(instruction_discriminator(&ix, 2, true).unwrap(), vec![9]);
+
+        ix.discriminator = Some(vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(
+            instruction_discriminator(&ix, 2, true).unwrap(),
+            vec![1, 2, 3, 4, 5, 6, 7, 8]
+        );
+    }
+
+    #[test]
+    fn test_generate_instructions_with_shank_discriminant() {
+        let instructions = vec![Instruction {
+            name: "closeAccount".to_string(),
+            docs: None,
+            discriminator: None,
+            accounts: vec![],
+            args: vec![],
+            discriminant: Some(crate::idl::Discriminant {
+                ty: "u8".to_string(),
+                value: 7,
+            }),
+            returns: None,
+        }];
+
+        let result_str = generate_instructions(&instructions, true, true)
+            .unwrap()
+            .to_string();
+        assert!(
+            result_str.contains("CLOSE_ACCOUNT_IX_DISCM : [u8 ; 1] = [7u8]"),
+            "{}",
+            result_str
+        );
+        assert!(result_str.contains("[7u8 , ..] => Ok (Self :: CloseAccount)"));
+    }
+
     // ============================================================================
     // Account Generation Tests
     // ============================================================================
@@ -2286,7 +6504,7 @@ mod tests {
             }),
         };
 
-        let result = generate_account(&account).unwrap();
+        let result = generate_account(&account, &HashSet::new()).unwrap();
         let result_str = result.to_string();
 
         assert!(result_str.contains("pub struct UserAccount"));
@@ -2305,7 +6523,7 @@ mod tests {
             ty: None,
         };
 
-        let result = generate_account(&account).unwrap();
+        let result = generate_account(&account, &HashSet::new()).unwrap();
         let result_str = result.to_string();
 
         // Should return empty TokenStream for reference accounts
@@ -2330,71 +6548,321 @@ mod tests {
                 discriminator: Some(vec![0, 0, 0, 0, 0, 0, 0, 0]),
                 accounts: vec![],
                 args: vec![],
+                discriminant: None,
+                returns: None,
+            }],
+            accounts: None,
+            types: None,
+            errors: None,
+            events: None,
+            constants: None,
+        };
+
+        let result = generate(&idl, "minimal_program");
+        assert!(
+            result.is_ok(),
+            "Generation should succeed: {:?}",
+            result.err()
+        );
+        let code = result.unwrap();
+        assert!(code.lib.contains("pub mod"));
+        assert!(
+            code.instructions.contains("use borsh")
+                || code.instructions.contains("pub enum Instruction")
+        );
+    }
+
+    #[test]
+    fn test_generate_idl_with_types() {
+        let idl = Idl {
+            address: None,
+            version: None,
+            name: Some("test_program".to_string()),
+            metadata: None,
+            // Include at least one instruction to avoid empty match arms
+            instructions: vec![Instruction {
+                name: "noop".to_string(),
+                docs: None,
+                discriminator: Some(vec![0, 0, 0, 0, 0, 0, 0, 0]),
+                accounts: vec![],
+                args: vec![],
+                discriminant: None,
+                returns: None,
+            }],
+            accounts: None,
+            types: Some(vec![TypeDef {
+                name: "TestStruct".to_string(),
+                docs: None,
+                ty: TypeDefType::Struct {
+                    fields: StructFields::Named(vec![Field {
+                        name: "value".to_string(),
+                        ty: IdlType::Simple("u64".to_string()),
+                        docs: None,
+                    }]),
+                },
+                serialization: None,
+                repr: None,
+                generics: None,
+            }]),
+            errors: None,
+            events: None,
+            constants: None,
+        };
+
+        let result = generate(&idl, "test_program");
+        assert!(
+            result.is_ok(),
+            "Generation should succeed: {:?}",
+            result.err()
+        );
+        let code = result.unwrap();
+        assert!(code.types.contains("pub struct TestStruct"));
+        assert!(code.types.contains("pub value: u64"));
+    }
+
+    #[test]
+    fn test_generate_renames_conflicting_type_names() {
+        let struct_def = |name: &str, field_ty: IdlType| TypeDef {
+            name: name.to_string(),
+            docs: None,
+            ty: TypeDefType::Struct {
+                fields: StructFields::Named(vec![Field {
+                    name: "value".to_string(),
+                    ty: field_ty,
+                    docs: None,
+                }]),
+            },
+            serialization: None,
+            repr: None,
+            generics: None,
+        };
+        let defined = |name: &str| IdlType::Defined {
+            defined: DefinedTypeOrString::Nested(DefinedType {
+                name: name.to_string(),
+                generics: None,
+            }),
+        };
+
+        let idl = Idl {
+            address: None,
+            version: None,
+            name: Some("test_program".to_string()),
+            metadata: None,
+            instructions: vec![Instruction {
+                name: "swap".to_string(),
+                docs: None,
+                discriminator: Some(vec![1, 2, 3, 4, 5, 6, 7, 8]),
+                accounts: vec![],
+                args: vec![Arg {
+                    name: "params".to_string(),
+                    ty: defined("SwapKeys"),
+                    docs: None,
+                }],
+                discriminant: None,
+                returns: None,
             }],
             accounts: None,
-            types: None,
+            types: Some(vec![
+                struct_def("Instruction", IdlType::Simple("u8".to_string())),
+                struct_def("Result", defined("Instruction")),
+                struct_def("SwapKeys", defined("Result")),
+                struct_def("Holder", IdlType::Simple("Pubkey".to_string())),
+            ]),
             errors: None,
             events: None,
             constants: None,
         };
 
-        let result = generate(&idl, "minimal_program");
-        assert!(
-            result.is_ok(),
-            "Generation should succeed: {:?}",
-            result.err()
-        );
-        let code = result.unwrap();
-        assert!(code.lib.contains("pub mod"));
-        assert!(
-            code.instructions.contains("use borsh")
-                || code.instructions.contains("pub enum Instruction")
+        let code = generate(&idl, "test_program").unwrap();
+        assert_eq!(
+            code.renamed_types,
+            vec![
+                ("Instruction".to_string(), "InstructionType".to_string()),
+                ("Result".to_string(), "ResultType".to_string()),
+                ("SwapKeys".to_string(), "SwapKeysType".to_string()),
+            ]
         );
+        assert!(code.types.contains("pub struct InstructionType"));
+        assert!(code.types.contains("IDL name: `Instruction`"));
+        assert!(code.types.contains("pub value: InstructionType"));
+        assert!(code.types.contains("pub value: ResultType"));
+        // The built-in Pubkey is not a reference to the renamed type
+        assert!(code.types.contains("pub value: Pubkey"));
+        assert!(code.instructions.contains("pub params: SwapKeysType"));
+        assert!(code.instructions.contains("pub struct SwapKeys {"));
     }
 
     #[test]
-    fn test_generate_idl_with_types() {
+    fn test_generate_type_aliases() {
+        let alias = |name: &str, ty: IdlType| TypeDef {
+            name: name.to_string(),
+            docs: None,
+            ty: TypeDefType::Alias { alias: ty },
+            serialization: None,
+            repr: None,
+            generics: None,
+        };
+        let defined = |name: &str| IdlType::Defined {
+            defined: DefinedTypeOrString::Nested(DefinedType {
+                name: name.to_string(),
+                generics: None,
+            }),
+        };
+
         let idl = Idl {
             address: None,
             version: None,
             name: Some("test_program".to_string()),
             metadata: None,
-            // Include at least one instruction to avoid empty match arms
             instructions: vec![Instruction {
                 name: "noop".to_string(),
                 docs: None,
                 discriminator: Some(vec![0, 0, 0, 0, 0, 0, 0, 0]),
                 accounts: vec![],
                 args: vec![],
+                discriminant: None,
+                returns: None,
             }],
-            accounts: None,
-            types: Some(vec![TypeDef {
-                name: "TestStruct".to_string(),
+            accounts: Some(vec![Account {
+                name: "Counter".to_string(),
+                discriminator: Some(vec![1, 2, 3, 4, 5, 6, 7, 8]),
                 docs: None,
-                ty: TypeDefType::Struct {
-                    fields: StructFields::Named(vec![Field {
-                        name: "value".to_string(),
-                        ty: IdlType::Simple("u64".to_string()),
-                        docs: None,
-                    }]),
+                ty: None,
+            }]),
+            types: Some(vec![
+                alias("Owner", IdlType::Simple("pubkey".to_string())),
+                alias("Counter", IdlType::Simple("u64".to_string())),
+                alias("OwnerChanged", defined("Owner")),
+                TypeDef {
+                    name: "Vault".to_string(),
+                    docs: None,
+                    ty: TypeDefType::Struct {
+                        fields: StructFields::Named(vec![Field {
+                            name: "owner".to_string(),
+                            ty: defined("Owner"),
+                            docs: None,
+                        }]),
+                    },
+                    serialization: None,
+                    repr: None,
+                    generics: None,
                 },
-                serialization: None,
-                repr: None,
+            ]),
+            errors: None,
+            events: Some(vec![Event {
+                name: "OwnerChanged".to_string(),
+                discriminator: Some(vec![9, 9, 9, 9, 9, 9, 9, 9]),
+                fields: None,
+                docs: None,
             }]),
+            constants: None,
+        };
+
+        let code = generate(&idl, "test_program").unwrap();
+        assert!(code.types.contains("pub type Owner = Pubkey;"));
+        // Fields see through the alias so the Pubkey serde helper applies
+        assert!(code.types.contains("pub owner: Pubkey"));
+        assert!(code.types.contains("crate::serde_pubkey"));
+        // An alias account becomes a newtype carrying the discriminator
+        assert!(code.accounts.contains("pub struct Counter("));
+        assert!(code.accounts.contains("pub u64"));
+        assert!(code.accounts.contains("impl Counter"));
+        assert!(code.accounts.contains("DISCRIMINATOR"));
+        // So does an alias event
+        assert!(code.events.contains("pub struct OwnerChanged("));
+        assert!(code
+            .events
+            .contains("pub struct OwnerChangedEvent(pub OwnerChanged)"));
+    }
+
+    #[test]
+    fn test_expand_alias_refs_substitutes_generics() {
+        let mut aliases = AliasMap::new();
+        aliases.insert(
+            "Pair".to_string(),
+            (
+                vec![
+                    GenericParam::Type {
+                        name: "T".to_string(),
+                    },
+                    GenericParam::Const {
+                        name: "N".to_string(),
+                        ty: "usize".to_string(),
+                    },
+                ],
+                IdlType::Array {
+                    array: ArrayType::Generic((
+                        Box::new(IdlType::Generic {
+                            generic: "T".to_string(),
+                        }),
+                        "N".to_string(),
+                    )),
+                },
+            ),
+        );
+
+        let mut ty = IdlType::Vec {
+            vec: Box::new(IdlType::Defined {
+                defined: DefinedTypeOrString::Nested(DefinedType {
+                    name: "Pair".to_string(),
+                    generics: Some(vec![
+                        GenericArg::Type {
+                            ty: IdlType::Simple("u16".to_string()),
+                        },
+                        GenericArg::Const {
+                            value: "4".to_string(),
+                        },
+                    ]),
+                }),
+            }),
+        };
+        expand_alias_refs(&mut ty, &aliases, 0);
+        assert_eq!(map_idl_type(&ty).to_string(), "Vec < [u16 ; 4usize] >");
+    }
+
+    #[test]
+    fn test_resolve_type_name_conflicts_suffix_is_unique() {
+        let unit_enum = |name: &str| TypeDef {
+            name: name.to_string(),
+            docs: None,
+            ty: TypeDefType::Enum { variants: vec![] },
+            serialization: None,
+            repr: None,
+            generics: None,
+        };
+        let idl = Idl {
+            address: None,
+            version: None,
+            name: None,
+            metadata: None,
+            instructions: vec![],
+            accounts: None,
+            types: Some(vec![unit_enum("OptionType"), unit_enum("Option")]),
             errors: None,
             events: None,
             constants: None,
         };
 
-        let result = generate(&idl, "test_program");
-        assert!(
-            result.is_ok(),
-            "Generation should succeed: {:?}",
-            result.err()
+        let (resolved, renamed) = resolve_type_name_conflicts(&idl, &GenerateOptions::default());
+        assert_eq!(
+            renamed,
+            vec![("Option".to_string(), "OptionType2".to_string())]
         );
-        let code = result.unwrap();
-        assert!(code.types.contains("pub struct TestStruct"));
-        assert!(code.types.contains("pub value: u64"));
+        let names: Vec<_> = resolved
+            .types
+            .iter()
+            .flatten()
+            .map(|t| t.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["OptionType", "OptionType2"]);
+
+        let clean = Idl {
+            types: Some(vec![unit_enum("Side")]),
+            ..idl
+        };
+        let (resolved, renamed) = resolve_type_name_conflicts(&clean, &GenerateOptions::default());
+        assert!(renamed.is_empty());
+        assert!(matches!(resolved, std::borrow::Cow::Borrowed(_)));
     }
 
     #[test]
@@ -2411,6 +6879,8 @@ mod tests {
                 discriminator: Some(vec![0, 0, 0, 0, 0, 0, 0, 0]),
                 accounts: vec![],
                 args: vec![],
+                discriminant: None,
+                returns: None,
             }],
             accounts: Some(vec![Account {
                 name: "TestAccount".to_string(),
@@ -2430,6 +6900,7 @@ mod tests {
                 },
                 serialization: None,
                 repr: None,
+                generics: None,
             }]),
             errors: None,
             events: None,
@@ -2461,6 +6932,8 @@ mod tests {
                 discriminator: Some(vec![0, 0, 0, 0, 0, 0, 0, 0]),
                 accounts: vec![],
                 args: vec![],
+                discriminant: None,
+                returns: None,
             }],
             accounts: Some(vec![Account {
                 name: "BytemuckAccount".to_string(),
@@ -2483,6 +6956,7 @@ mod tests {
                     kind: "C".to_string(),
                     packed: None,
                 }),
+                generics: None,
             }]),
             errors: None,
             events: None,
@@ -2496,8 +6970,676 @@ mod tests {
             result.err()
         );
         let code = result.unwrap();
-        assert!(code.accounts.contains("bytemuck::try_from_bytes"));
-        assert!(code.accounts.contains("bytemuck::bytes_of"));
+        assert!(code
+            .accounts
+            .contains("solana_idl_codegen_runtime::pod_from_slice_with_discriminator"));
+        assert!(code
+            .accounts
+            .contains("solana_idl_codegen_runtime::pod_serialize_with_discriminator"));
+    }
+
+    #[test]
+    fn test_generate_flattens_account_groups() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "version": "0.1.0",
+                "name": "nested",
+                "instructions": [{
+                    "name": "swap",
+                    "accounts": [
+                        { "name": "user", "isMut": false, "isSigner": true },
+                        { "name": "pool", "accounts": [
+                            { "name": "state", "isMut": true, "isSigner": false },
+                            { "name": "tokenVault", "isMut": true, "isSigner": false }
+                        ] },
+                        { "name": "tokenProgram", "isMut": false, "isSigner": false }
+                    ],
+                    "args": []
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let code = generate(&idl, "test_program").unwrap();
+        let fields = [
+            "pub user: Pubkey",
+            "pub pool_state: Pubkey",
+            "pub pool_token_vault: Pubkey",
+            "pub token_program: Pubkey",
+        ];
+        let positions: Vec<_> = fields
+            .iter()
+            .map(|field| code.instructions.find(field).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]));
+        assert!(code
+            .instructions
+            .contains("SWAP_IX_ACCOUNTS_LEN: usize = 4"));
+        assert!(code.instructions.contains("pubkey: keys.pool_token_vault"));
+    }
+
+    #[test]
+    fn test_generate_relation_helpers() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "address": "11111111111111111111111111111111",
+                "metadata": { "name": "rel", "version": "0.1.0", "spec": "0.1.0" },
+                "instructions": [{
+                    "name": "swap",
+                    "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                    "accounts": [
+                        { "name": "pool", "writable": true },
+                        { "name": "tokenMint", "relations": ["pool"] },
+                        { "name": "user", "signer": true, "relations": ["pool"] }
+                    ],
+                    "args": []
+                }],
+                "accounts": [{ "name": "Pool", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] }],
+                "types": [{
+                    "name": "Pool",
+                    "type": { "kind": "struct", "fields": [{ "name": "tokenMint", "type": "pubkey" }] }
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let code = generate(&idl, "test_program").unwrap();
+        assert!(code.instructions.contains("pub enum RelationError"));
+        assert!(code.instructions.contains("pub fn swap_verify_relations("));
+        assert!(code
+            .instructions
+            .contains("Pool::try_from_slice_with_discriminator(data)"));
+        assert!(code.instructions.contains("if expected != keys.token_mint"));
+        // `Pool` has no `user` field, so that relation can't be checked
+        assert!(!code.instructions.contains("expected != keys.user"));
+    }
+
+    #[test]
+    fn test_generate_relation_helpers_legacy_accounts() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "version": "0.1.0",
+                "name": "legacy_program",
+                "metadata": { "address": "11111111111111111111111111111111" },
+                "instructions": [{
+                    "name": "initializeVault",
+                    "accounts": [
+                        { "name": "vault", "isMut": true, "isSigner": false, "relations": ["authority"] },
+                        { "name": "authority", "isMut": false, "isSigner": true }
+                    ],
+                    "args": []
+                }],
+                "accounts": [{
+                    "name": "Vault",
+                    "type": { "kind": "struct", "fields": [{ "name": "authority", "type": "publicKey" }] }
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let code = generate(&idl, "legacy_program").unwrap();
+        // Legacy accounts get the discriminator Anchor derives for them
+        let disc = crate::convert::anchor_discriminator("account", "Vault")
+            .iter()
+            .map(|b| format!("{}u8", b))
+            .collect::<Vec<_>>()
+            .join(",");
+        let accounts: String = code.accounts.split_whitespace().collect();
+        assert!(accounts.contains(&format!("DISCRIMINATOR:[u8;8]=[{}", disc)));
+        assert!(code
+            .instructions
+            .contains("pub fn initialize_vault_verify_relations("));
+        assert!(code
+            .instructions
+            .contains("Vault::try_from_slice_with_discriminator(data)"));
+        assert!(code.instructions.contains("if expected != keys.authority"));
+    }
+
+    #[test]
+    fn test_generate_keys_resolver() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "address": "11111111111111111111111111111111",
+                "metadata": { "name": "res", "version": "0.1.0", "spec": "0.1.0" },
+                "instructions": [{
+                    "name": "swap",
+                    "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                    "accounts": [
+                        { "name": "user", "signer": true },
+                        { "name": "pool", "pda": { "seeds": [
+                            { "kind": "const", "value": [112] },
+                            { "kind": "arg", "path": "index" }
+                        ] } },
+                        { "name": "mint", "relations": ["pool"] },
+                        { "name": "vault", "pda": { "seeds": [{ "kind": "account", "path": "pool.mint" }] } },
+                        { "name": "systemProgram", "address": "11111111111111111111111111111111" },
+                        { "name": "referrer", "optional": true }
+                    ],
+                    "args": [{ "name": "index", "type": "u16" }]
+                }],
+                "accounts": [{ "name": "Pool", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] }],
+                "types": [{
+                    "name": "Pool",
+                    "type": { "kind": "struct", "fields": [{ "name": "mint", "type": "pubkey" }] }
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let code = generate(&idl, "test_program").unwrap();
+        let ix = &code.instructions;
+        assert!(ix.contains("pub trait AccountFetcher"));
+        assert!(ix.contains("impl AccountFetcher for std::collections::HashMap<Pubkey, Vec<u8>>"));
+        assert!(ix.contains("pub struct UnresolvedAccounts(pub Vec<&'static str>);"));
+        assert!(ix.contains("pub struct SwapKeysResolver"));
+        assert!(ix.contains("pub fn pool(mut self, pubkey: Pubkey) -> Self"));
+        assert!(ix.contains("args: &SwapIxArgs,"));
+        assert!(ix.contains("&args.index.to_le_bytes()[..]"));
+        assert!(ix.contains("Pool::try_from_slice_with_discriminator(data)"));
+        assert!(ix.contains("self.referrer.get_or_insert(program_id);"));
+        assert!(ix.contains("missing.push(\"user\");"));
+        assert!(ix.contains("self.resolve_with_program_id(crate::ID, args, fetcher)"));
+        // Derivation chains start from the first strategy
+        assert!(ix.contains("self.system_program = (|| -> Option<Pubkey> {"));
+        assert!(!ix.contains("None.or_else"));
+        assert!(ix.contains("pub fn resolve_with_program_id(\n        mut self,"));
+    }
+
+    #[test]
+    fn test_generate_instruction_builders() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "address": "11111111111111111111111111111111",
+                "metadata": { "name": "b", "version": "0.1.0", "spec": "0.1.0" },
+                "instructions": [
+                    {
+                        "name": "mintTo",
+                        "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                        "accounts": [
+                            { "name": "mint", "writable": true },
+                            { "name": "build" },
+                            { "name": "systemProgram", "address": "11111111111111111111111111111111" }
+                        ],
+                        "args": [{ "name": "mint", "type": "u64" }]
+                    },
+                    { "name": "noop", "discriminator": [0], "accounts": [], "args": [] }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let code = generate(&idl, "test_program").unwrap();
+        let ix = &code.instructions;
+        assert!(ix.contains("pub enum BuildError"));
+        assert!(ix.contains("pub struct MintToBuilder"));
+        assert!(ix.contains("keys: MintToKeysResolver,"));
+        assert!(ix.contains("arg_mint: Option<u64>,"));
+        // Setters that would clash with an account or a builder method get a suffix
+        assert!(ix.contains("pub fn mint(mut self, pubkey: Pubkey) -> Self"));
+        assert!(ix.contains("pub fn build_account(mut self, pubkey: Pubkey) -> Self"));
+        assert!(ix.contains("pub fn mint_arg(mut self, value: u64) -> Self"));
+        assert!(ix.contains("return Err(BuildError::MissingArgs(missing));"));
+        assert!(ix.contains("mint_to_ix_with_program_id_and_remaining_accounts("));
+        assert!(ix.contains("self.build_with_program_id(crate::ID)"));
+        // No accounts means no resolver to fill them, so no builder
+        assert!(!ix.contains("NoopBuilder"));
+    }
+
+    #[test]
+    fn test_generate_instruction_builders_optional_args() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "address": "11111111111111111111111111111111",
+                "metadata": { "name": "b", "version": "0.1.0", "spec": "0.1.0" },
+                "instructions": [
+                    {
+                        "name": "swap",
+                        "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                        "accounts": [{ "name": "pool", "writable": true }],
+                        "args": [
+                            { "name": "amount", "type": "u64" },
+                            { "name": "type", "type": { "option": "u8" } }
+                        ]
+                    },
+                    {
+                        "name": "close",
+                        "discriminator": [9, 9, 9, 9, 9, 9, 9, 9],
+                        "accounts": [{ "name": "pool", "writable": true }],
+                        "args": [{ "name": "memo", "type": { "option": "string" } }]
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let code = generate(&idl, "test_program").unwrap();
+        let ix: String = code.instructions.split_whitespace().collect();
+        // `Option` args default to `None` and are never reported missing
+        assert!(ix.contains("arg_amount:Option<u64>,arg_type:Option<u8>,"));
+        assert!(
+            ix.contains("pubfnr#type(mutself,value:Option<u8>)->Self{self.arg_type=value;self}")
+        );
+        assert!(ix.contains("missing.push(\"amount\");"));
+        assert!(!ix.contains("missing.push(\"type\");"));
+        assert!(ix.contains("amount:arg_amount,r#type:self.arg_type,"));
+        // With only optional args there is nothing to check
+        assert!(ix.contains("letargs=CloseIxArgs{memo:self.arg_memo};"));
+    }
+
+    #[test]
+    fn test_generate_program_handle() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "address": "11111111111111111111111111111111",
+                "metadata": {
+                    "name": "p", "version": "0.1.0", "spec": "0.1.0",
+                    "deployments": {
+                        "mainnet-beta": "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8",
+                        "devnet": "not-an-address"
+                    }
+                },
+                "instructions": [{
+                    "name": "swap",
+                    "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                    "accounts": [{ "name": "pool", "writable": true }],
+                    "args": [{ "name": "amount", "type": "u64" }]
+                }],
+                "accounts": [{ "name": "Pool", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] }],
+                "types": [{
+                    "name": "Pool",
+                    "type": { "kind": "struct", "fields": [{ "name": "mint", "type": "pubkey" }] }
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let code = generate(&idl, "test_program").unwrap();
+        let ix = &code.instructions;
+        assert!(ix.contains("pub struct Program {\n    pub id: Pubkey,\n}"));
+        assert!(ix.contains("Self::new(crate::ID)"));
+        assert!(ix.contains("pub const fn mainnet_beta() -> Self"));
+        // Invalid deployment addresses get no constructor
+        assert!(!ix.contains("fn devnet"));
+        assert!(ix.contains("swap_ix_with_program_id(self.id, keys, args)"));
+        assert!(ix.contains("resolver.resolve_with_program_id(self.id, args, fetcher)"));
+        assert!(ix.contains("builder.build_with_program_id(self.id)"));
+        assert!(ix.contains("pub fn decode_instruction("));
+        assert!(ix.contains("Pool::try_from_account_info_with_program_id(account_info, &self.id)"));
+        assert!(code
+            .accounts
+            .contains("Self::validate_account_info_with_program_id(account_info, &crate::ID)"));
+    }
+
+    #[test]
+    fn test_program_handle_deployment_name_clashes() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "address": "11111111111111111111111111111111",
+                "metadata": {
+                    "name": "p", "version": "0.1.0", "spec": "0.1.0",
+                    "deployments": {
+                        "new": "11111111111111111111111111111111",
+                        "decode-instruction": "11111111111111111111111111111111",
+                        "parse_event": "11111111111111111111111111111111",
+                        "swap-ix": "11111111111111111111111111111111",
+                        "mainnet": "11111111111111111111111111111111"
+                    }
+                },
+                "instructions": [{
+                    "name": "swap",
+                    "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                    "accounts": [],
+                    "args": []
+                }],
+                "events": [{ "name": "Swapped", "discriminator": [2, 2, 2, 2, 2, 2, 2, 2] }],
+                "types": [{ "name": "Swapped", "type": { "kind": "struct", "fields": [] } }]
+            }"#,
+        )
+        .unwrap();
+
+        let ix = generate_program_handle(&idl, true).to_string();
+        for constructor in [
+            "new_deployment",
+            "decode_instruction_deployment",
+            "parse_event_deployment",
+            "swap_ix_deployment",
+            "mainnet",
+        ] {
+            assert!(
+                ix.contains(&format!("pub const fn {} () -> Self", constructor)),
+                "{}",
+                constructor
+            );
+        }
+    }
+
+    #[test]
+    fn test_program_handle_methods_match_generated() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "address": "11111111111111111111111111111111",
+                "metadata": { "name": "p", "version": "0.1.0", "spec": "0.1.0" },
+                "instructions": [
+                    {
+                        "name": "getPrice",
+                        "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                        "accounts": [{ "name": "pool" }],
+                        "args": [],
+                        "returns": "u64"
+                    },
+                    { "name": "ping", "discriminator": [3, 3, 3, 3, 3, 3, 3, 3], "accounts": [], "args": [] }
+                ],
+                "accounts": [{ "name": "Pool", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] }],
+                "events": [{ "name": "Swapped", "discriminator": [2, 2, 2, 2, 2, 2, 2, 2] }],
+                "types": [
+                    { "name": "Pool", "type": { "kind": "struct", "fields": [] } },
+                    { "name": "Swapped", "type": { "kind": "struct", "fields": [] } }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let tokens = generate_program_handle(&idl, true);
+        let mut generated: Vec<String> = Vec::new();
+        let mut previous = None;
+        for token in tokens.into_iter().flat_map(|tt| match tt {
+            proc_macro2::TokenTree::Group(g) => g.stream().into_iter().collect::<Vec<_>>(),
+            tt => vec![tt],
+        }) {
+            if let proc_macro2::TokenTree::Ident(ident) = &token {
+                if previous.as_deref() == Some("fn") {
+                    generated.push(ident.to_string());
+                }
+            }
+            previous = Some(token.to_string());
+        }
+        let mut expected = program_handle_methods(&idl, true);
+        expected.sort();
+        generated.sort();
+        assert_eq!(generated, expected);
+    }
+
+    #[test]
+    fn test_program_handle_without_address() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "metadata": { "name": "p", "version": "0.1.0", "spec": "0.1.0" },
+                "instructions": [{
+                    "name": "swap",
+                    "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                    "accounts": [{ "name": "pool" }],
+                    "args": []
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let ix = generate_program_handle(&idl, false).to_string();
+        assert!(ix.contains("pub struct Program"));
+        assert!(ix.contains("pub fn swap_ix"));
+        assert!(!ix.contains("impl Default for Program"));
+    }
+
+    #[test]
+    fn test_generate_program_traits() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "address": "11111111111111111111111111111111",
+                "metadata": { "name": "t", "version": "0.1.0", "spec": "0.1.0" },
+                "instructions": [{
+                    "name": "initPool",
+                    "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                    "accounts": [{ "name": "pool", "writable": true }],
+                    "args": []
+                }],
+                "accounts": [{ "name": "Instruction", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] }],
+                "events": [{ "name": "Opened", "discriminator": [7, 7, 7, 7, 7, 7, 7, 7] }],
+                "types": [
+                    {
+                        "name": "Instruction",
+                        "type": { "kind": "struct", "fields": [{ "name": "mint", "type": "pubkey" }] }
+                    },
+                    {
+                        "name": "Opened",
+                        "type": { "kind": "struct", "fields": [{ "name": "mint", "type": "pubkey" }] }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let code = generate(&idl, "test_program").unwrap();
+        assert!(code.lib.contains(
+            "pub use solana_idl_codegen_runtime::{ProgramAccount, ProgramEvent, ProgramInstruction};"
+        ));
+        assert!(code
+            .instructions
+            .contains("impl crate::ProgramInstruction for InitPoolIxData"));
+        assert!(code
+            .instructions
+            .contains("const DISCRIMINATOR: &'static [u8] = &INIT_POOL_IX_DISCM;"));
+        assert!(code
+            .instructions
+            .contains("const NAME: &'static str = \"initPool\";"));
+        // A renamed account keeps its IDL name
+        assert!(code
+            .accounts
+            .contains("impl crate::ProgramAccount for InstructionType"));
+        assert!(code
+            .accounts
+            .contains("const NAME: &'static str = \"Instruction\";"));
+        assert!(code
+            .events
+            .contains("impl crate::ProgramEvent for OpenedEvent"));
+        assert!(code
+            .events
+            .contains("const PROGRAM_ID: Option<Pubkey> = Some(crate::ID);"));
+    }
+
+    #[test]
+    fn test_program_traits_without_address() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "metadata": { "name": "t", "version": "0.1.0", "spec": "0.1.0" },
+                "instructions": [{
+                    "name": "init",
+                    "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                    "accounts": [{ "name": "pool" }],
+                    "args": []
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let code = generate(&idl, "test_program").unwrap();
+        assert!(code
+            .instructions
+            .contains("impl crate::ProgramInstruction for InitIxData"));
+        assert!(code
+            .instructions
+            .contains("const PROGRAM_ID: Option<Pubkey> = None;"));
+    }
+
+    #[test]
+    fn test_generate_anchor_impls() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "address": "11111111111111111111111111111111",
+                "metadata": { "name": "t", "version": "0.1.0", "spec": "0.1.0" },
+                "instructions": [{
+                    "name": "initPool",
+                    "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                    "accounts": [
+                        { "name": "pool", "writable": true },
+                        { "name": "payer", "writable": true, "signer": true }
+                    ],
+                    "args": [{ "name": "amount", "type": "u64" }]
+                }],
+                "accounts": [
+                    { "name": "Pool", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] },
+                    { "name": "Ticks", "discriminator": [8, 8, 8, 8, 8, 8, 8, 8] }
+                ],
+                "types": [
+                    {
+                        "name": "Pool",
+                        "type": { "kind": "struct", "fields": [{ "name": "mint", "type": "pubkey" }] }
+                    },
+                    {
+                        "name": "Ticks",
+                        "serialization": "bytemuck",
+                        "repr": { "kind": "c" },
+                        "type": { "kind": "struct", "fields": [{ "name": "start", "type": "u64" }] }
+                    },
+                    {
+                        "name": "InitPoolAccounts",
+                        "type": { "kind": "struct", "fields": [{ "name": "n", "type": "u8" }] }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        // Off by default, where `InitPoolAccounts` keeps its name
+        let code = generate(&idl, "test_program").unwrap();
+        assert!(!code.accounts.contains("anchor_lang"));
+        assert!(!code.instructions.contains("anchor_lang"));
+        assert!(code.renamed_types.is_empty());
+
+        let options = GenerateOptions { anchor: true };
+        let code = generate_with_options(&idl, "test_program", &options).unwrap();
+        assert_eq!(
+            code.renamed_types,
+            vec![(
+                "InitPoolAccounts".to_string(),
+                "InitPoolAccountsType".to_string()
+            )]
+        );
+        assert!(code
+            .accounts
+            .contains("impl anchor_lang::AccountDeserialize for Pool"));
+        assert!(code
+            .accounts
+            .contains("solana_idl_codegen_runtime::anchor::try_deserialize_unchecked("));
+        assert!(code
+            .accounts
+            .contains("solana_idl_codegen_runtime::anchor::pod_try_deserialize_unchecked("));
+        assert!(code.accounts.contains("impl anchor_lang::Owner for Ticks"));
+        assert!(code
+            .instructions
+            .contains("impl anchor_lang::InstructionData for InitPoolIxData {}"));
+        assert!(code
+            .instructions
+            .contains("const DISCRIMINATOR: &'static [u8] = &INIT_POOL_IX_DISCM;"));
+        assert!(code
+            .instructions
+            .contains("impl anchor_lang::ToAccountMetas for InitPoolKeys"));
+        assert!(code
+            .instructions
+            .contains("pub struct InitPoolAccounts<'info> {"));
+        assert!(code.instructions.contains(
+            "impl<'info> anchor_lang::ToAccountInfos<'info> for InitPoolAccounts<'info>"
+        ));
+    }
+
+    #[test]
+    fn test_anchor_owner_needs_address() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "metadata": { "name": "t", "version": "0.1.0", "spec": "0.1.0" },
+                "instructions": [{
+                    "name": "init",
+                    "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                    "accounts": [{ "name": "pool" }],
+                    "args": []
+                }],
+                "accounts": [{ "name": "Pool", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] }],
+                "types": [{
+                    "name": "Pool",
+                    "type": { "kind": "struct", "fields": [{ "name": "mint", "type": "pubkey" }] }
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let options = GenerateOptions { anchor: true };
+        let code = generate_with_options(&idl, "test_program", &options).unwrap();
+        assert!(code
+            .accounts
+            .contains("impl anchor_lang::AccountSerialize for Pool"));
+        assert!(!code.accounts.contains("anchor_lang::Owner"));
+    }
+
+    #[test]
+    fn test_pda_derivation_skips_unsupported_seeds() {
+        let ix: Instruction = serde_json::from_str(
+            r#"{
+                "name": "open",
+                "accounts": [{ "name": "owner" }],
+                "args": [{ "name": "params", "type": { "defined": { "name": "Params" } } }]
+            }"#,
+        )
+        .unwrap();
+        let seeds = |seed: Seed| Pda {
+            seeds: vec![seed],
+            program: None,
+        };
+
+        let nested_arg = seeds(Seed::Arg {
+            path: "params".to_string(),
+        });
+        assert!(pda_derivation(&ix, &nested_arg, &[]).is_none());
+        let unknown_account = seeds(Seed::Account {
+            path: "missing".to_string(),
+        });
+        assert!(pda_derivation(&ix, &unknown_account, &[]).is_none());
+        let known_account = seeds(Seed::Account {
+            path: "owner".to_string(),
+        });
+        assert!(pda_derivation(&ix, &known_account, &[]).is_some());
+    }
+
+    #[test]
+    fn test_generate_boxes_recursive_types() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "instructions": [
+                    { "name": "noop", "discriminator": [0], "accounts": [{ "name": "payer", "signer": true }], "args": [] }
+                ],
+                "types": [
+                    { "name": "Node", "type": { "kind": "struct", "fields": [
+                        { "name": "next", "type": { "option": { "defined": { "name": "Node" } } } },
+                        { "name": "children", "type": { "vec": { "defined": { "name": "Node" } } } }
+                    ] } },
+                    { "name": "Expr", "type": { "kind": "enum", "variants": [
+                        { "name": "Lit", "fields": ["u64"] },
+                        { "name": "Neg", "fields": [{ "name": "inner", "type": { "defined": { "name": "Expr" } } }] }
+                    ] } },
+                    { "name": "A", "type": { "kind": "struct", "fields": [
+                        { "name": "b", "type": { "array": [{ "defined": { "name": "B" } }, 2] } },
+                        { "name": "leaf", "type": { "defined": { "name": "Leaf" } } }
+                    ] } },
+                    { "name": "B", "type": { "kind": "struct", "fields": [
+                        { "name": "a", "type": { "coption": { "defined": { "name": "A" } } } }
+                    ] } },
+                    { "name": "Leaf", "type": { "kind": "struct", "fields": [] } }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let code = generate(&idl, "test_program").unwrap();
+        assert!(
+            code.types.contains("pub next: Option<Box<Node>>"),
+            "{}",
+            code.types
+        );
+        // Vec already allocates
+        assert!(code.types.contains("pub children: Vec<Node>"));
+        assert!(code.types.contains("Neg { inner: Box<Expr> }"));
+        assert!(code.types.contains("pub b: [Box<B>; 2usize]"));
+        assert!(code.types.contains("pub a: crate::COption<Box<A>>"));
+        // Leaf isn't part of the cycle
+        assert!(code.types.contains("pub leaf: Leaf"));
     }
 
     #[test]
@@ -2522,6 +7664,8 @@ mod tests {
                         pda: None,
                         address: None,
                         optional: None,
+                        accounts: None,
+                        relations: None,
                     },
                     AccountArg {
                         name: "destination".to_string(),
@@ -2531,12 +7675,17 @@ mod tests {
                         pda: None,
                         address: None,
                         optional: None,
+                        accounts: None,
+                        relations: None,
                     },
                 ],
                 args: vec![Arg {
                     name: "amount".to_string(),
                     ty: IdlType::Simple("u64".to_string()),
+                    docs: None,
                 }],
+                discriminant: None,
+                returns: None,
             }],
             accounts: None,
             types: Some(vec![TypeDef {
@@ -2563,11 +7712,13 @@ mod tests {
                 },
                 serialization: None,
                 repr: None,
+                generics: None,
             }]),
             errors: Some(vec![Error {
                 code: 6000,
                 name: "InsufficientFunds".to_string(),
                 msg: Some("Insufficient funds for transfer".to_string()),
+                docs: None,
             }]),
             events: Some(vec![Event {
                 name: "TransferEvent".to_string(),
@@ -2589,6 +7740,7 @@ mod tests {
                         index: false,
                     },
                 ]),
+                docs: None,
             }]),
             constants: None,
         };
@@ -2629,7 +7781,7 @@ mod tests {
         };
 
         let lib_code = generate_lib_module(&idl);
-        assert!(lib_code.contains("solana_program::declare_id!"));
+        assert!(lib_code.contains("solana_program::pubkey::declare_id!"));
         assert!(lib_code.contains("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P"));
     }
 
@@ -2653,6 +7805,28 @@ mod tests {
         assert!(lib_code.contains("YourProgramIdHere"));
     }
 
+    #[test]
+    fn test_generate_lib_with_description() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "address": "11111111111111111111111111111111",
+                "metadata": {
+                    "name": "vault",
+                    "version": "0.1.0",
+                    "spec": "0.1.0",
+                    "description": "Token vault.\n\nHolds deposits."
+                },
+                "instructions": []
+            }"#,
+        )
+        .unwrap();
+
+        let lib_code = generate_lib_module(&idl);
+        assert!(lib_code.starts_with(
+            "//! Generated Solana program bindings\n//!\n//! Token vault.\n//!\n//! Holds deposits.\n\n"
+        ));
+    }
+
     #[test]
     fn test_generated_code_includes_program_id() {
         let idl = Idl {
@@ -2666,6 +7840,8 @@ mod tests {
                 discriminator: Some(vec![0, 0, 0, 0, 0, 0, 0, 0]),
                 accounts: vec![],
                 args: vec![],
+                discriminant: None,
+                returns: None,
             }],
             accounts: None,
             types: None,
@@ -2694,9 +7870,11 @@ mod tests {
             discriminator: Some(vec![175, 175, 109, 31, 13, 152, 155, 237]),
             accounts: vec![],
             args: vec![],
+            discriminant: None,
+            returns: None,
         }];
 
-        let result = generate_instructions(&instructions, true).unwrap();
+        let result = generate_instructions(&instructions, true, false).unwrap();
         let result_str = result.to_string();
 
         // Check for discriminator constant
@@ -2721,10 +7899,13 @@ mod tests {
             args: vec![Arg {
                 name: "amount".to_string(),
                 ty: IdlType::Simple("u64".to_string()),
+                docs: None,
             }],
+            discriminant: None,
+            returns: None,
         }];
 
-        let result = generate_instructions(&instructions, true).unwrap();
+        let result = generate_instructions(&instructions, true, false).unwrap();
         let result_str = result.to_string();
 
         // Check for discriminator constant
@@ -2752,10 +7933,13 @@ mod tests {
             args: vec![Arg {
                 name: "amount".to_string(),
                 ty: IdlType::Simple("u64".to_string()),
+                docs: None,
             }],
+            discriminant: None,
+            returns: None,
         }];
 
-        let result = generate_instructions(&instructions, true).unwrap();
+        let result = generate_instructions(&instructions, true, false).unwrap();
         let result_str = result.to_string();
 
         // Check that serialize method uses the discriminator constant
@@ -2782,6 +7966,8 @@ mod tests {
                     pda: None,
                     address: None,
                     optional: None,
+                    accounts: None,
+                    relations: None,
                 },
                 AccountArg {
                     name: "to".to_string(),
@@ -2791,12 +7977,16 @@ mod tests {
                     pda: None,
                     address: None,
                     optional: None,
+                    accounts: None,
+                    relations: None,
                 },
             ],
             args: vec![],
+            discriminant: None,
+            returns: None,
         }];
 
-        let result = generate_instructions(&instructions, true).unwrap();
+        let result = generate_instructions(&instructions, true, false).unwrap();
         let result_str = result.to_string();
 
         // Check for Keys struct
@@ -2809,6 +7999,46 @@ mod tests {
         assert!(result_str.contains(": usize = 2"));
     }
 
+    #[test]
+    fn test_generate_remaining_accounts_support() {
+        let account = |name: &str| AccountArg {
+            name: name.to_string(),
+            docs: None,
+            signer: false,
+            writable: true,
+            pda: None,
+            address: None,
+            optional: None,
+            relations: None,
+            accounts: None,
+        };
+        let instructions = vec![Instruction {
+            name: "swap".to_string(),
+            docs: None,
+            discriminator: Some(vec![1, 2, 3, 4, 5, 6, 7, 8]),
+            accounts: vec![account("pool"), account("vault")],
+            args: vec![],
+            discriminant: None,
+            returns: None,
+        }];
+
+        let result = generate_instructions(&instructions, true, false).unwrap();
+        let code = prettyplease::unparse(&syn::parse2(result).unwrap());
+
+        assert!(code.contains(
+            "pub fn from_account_keys(accounts: &[Pubkey]) -> std::io::Result<(Self, &[Pubkey])>"
+        ));
+        assert!(code.contains("pool: accounts[0usize]"));
+        assert!(code.contains("vault: accounts[1usize]"));
+        assert!(code.contains("Ok((keys, &accounts[SWAP_IX_ACCOUNTS_LEN..]))"));
+        assert!(code.contains("pub fn swap_ix_with_program_id_and_remaining_accounts("));
+        assert!(code.contains("accounts.extend_from_slice(remaining_accounts);"));
+        assert!(
+            code.contains("swap_ix_with_program_id_and_remaining_accounts(program_id, keys, &[])")
+        );
+        assert!(code.contains("pub fn swap_ix_with_remaining_accounts("));
+    }
+
     #[test]
     fn test_generate_account_meta_conversion() {
         let instructions = vec![Instruction {
@@ -2824,6 +8054,8 @@ mod tests {
                     pda: None,
                     address: None,
                     optional: None,
+                    accounts: None,
+                    relations: None,
                 },
                 AccountArg {
                     name: "config".to_string(),
@@ -2833,6 +8065,8 @@ mod tests {
                     pda: None,
                     address: None,
                     optional: None,
+                    accounts: None,
+                    relations: None,
                 },
                 AccountArg {
                     name: "system_program".to_string(),
@@ -2842,12 +8076,16 @@ mod tests {
                     pda: None,
                     address: None,
                     optional: None,
+                    accounts: None,
+                    relations: None,
                 },
             ],
             args: vec![],
+            discriminant: None,
+            returns: None,
         }];
 
-        let result = generate_instructions(&instructions, true).unwrap();
+        let result = generate_instructions(&instructions, true, false).unwrap();
         let result_str = result.to_string();
 
         // Check for From implementation
@@ -2875,6 +8113,8 @@ mod tests {
                     pda: None,
                     address: None,
                     optional: None,
+                    accounts: None,
+                    relations: None,
                 },
                 AccountArg {
                     name: "signer_readonly".to_string(),
@@ -2884,6 +8124,8 @@ mod tests {
                     pda: None,
                     address: None,
                     optional: None,
+                    accounts: None,
+                    relations: None,
                 },
                 AccountArg {
                     name: "nonsigner_writable".to_string(),
@@ -2893,6 +8135,8 @@ mod tests {
                     pda: None,
                     address: None,
                     optional: None,
+                    accounts: None,
+                    relations: None,
                 },
                 AccountArg {
                     name: "nonsigner_readonly".to_string(),
@@ -2902,12 +8146,16 @@ mod tests {
                     pda: None,
                     address: None,
                     optional: None,
+                    accounts: None,
+                    relations: None,
                 },
             ],
             args: vec![],
+            discriminant: None,
+            returns: None,
         }];
 
-        let result = generate_instructions(&instructions, true).unwrap();
+        let result = generate_instructions(&instructions, true, false).unwrap();
         let result_str = result.to_string();
 
         // Verify all four combinations are represented
@@ -2935,11 +8183,15 @@ mod tests {
                 pda: None,
                 address: None,
                 optional: None,
+                accounts: None,
+                relations: None,
             }],
             args: vec![],
+            discriminant: None,
+            returns: None,
         }];
 
-        let result = generate_instructions(&instructions, true).unwrap();
+        let result = generate_instructions(&instructions, true, false).unwrap();
         let result_str = result.to_string();
 
         // Check for builder functions
@@ -2963,14 +8215,19 @@ mod tests {
                 pda: None,
                 address: None,
                 optional: None,
+                accounts: None,
+                relations: None,
             }],
             args: vec![Arg {
                 name: "amount".to_string(),
                 ty: IdlType::Simple("u64".to_string()),
+                docs: None,
             }],
+            discriminant: None,
+            returns: None,
         }];
 
-        let result = generate_instructions(&instructions, true).unwrap();
+        let result = generate_instructions(&instructions, true, false).unwrap();
         let result_str = result.to_string();
 
         // Check for builder functions with args
@@ -2994,19 +8251,24 @@ mod tests {
                 pda: None,
                 address: None,
                 optional: None,
+                accounts: None,
+                relations: None,
             }],
             args: vec![Arg {
                 name: "amount".to_string(),
                 ty: IdlType::Simple("u64".to_string()),
+                docs: None,
             }],
+            discriminant: None,
+            returns: None,
         }];
 
-        let result = generate_instructions(&instructions, true).unwrap();
+        let result = generate_instructions(&instructions, true, false).unwrap();
         let result_str = result.to_string();
 
         // Check that builder returns Instruction
         assert!(result_str.contains("-> std :: io :: Result"));
-        assert!(result_str.contains("solana_program :: instruction :: Instruction"));
+        assert!(result_str.contains("crate :: solana_program :: instruction :: Instruction"));
         assert!(result_str.contains("program_id"));
         assert!(result_str.contains("accounts"));
         assert!(result_str.contains("data"));
@@ -3026,9 +8288,10 @@ mod tests {
             },
             serialization: None,
             repr: None,
+            generics: None,
         };
 
-        let result = generate_type_def(&type_def);
+        let result = generate_type_def(&type_def, &HashSet::new());
         assert!(result.is_ok());
     }
 
@@ -3080,9 +8343,10 @@ mod tests {
             },
             serialization: None,
             repr: None,
+            generics: None,
         };
 
-        let result = generate_type_def(&type_def).unwrap();
+        let result = generate_type_def(&type_def, &HashSet::new()).unwrap();
         let result_str = result.to_string();
 
         assert!(result_str.contains("camel_case"));
@@ -3100,10 +8364,13 @@ mod tests {
             args: vec![Arg {
                 name: "value".to_string(),
                 ty: IdlType::Simple("u64".to_string()),
+                docs: None,
             }],
+            discriminant: None,
+            returns: None,
         }];
 
-        let result = generate_instructions(&instructions, true).unwrap();
+        let result = generate_instructions(&instructions, true, false).unwrap();
         let result_str = result.to_string();
 
         // Check that deserialization uses &mut buf
@@ -3132,6 +8399,7 @@ mod tests {
                     ty: IdlType::Simple("pubkey".to_string()),
                     index: false,
                 }]),
+                docs: None,
             },
             Event {
                 name: "TradeEvent".to_string(),
@@ -3141,6 +8409,7 @@ mod tests {
                     ty: IdlType::Simple("u64".to_string()),
                     index: false,
                 }]),
+                docs: None,
             },
         ];
 
@@ -3152,8 +8421,9 @@ mod tests {
         assert!(result_str.contains("CreateEvent") && result_str.contains("CreateEventEvent"));
         assert!(result_str.contains("TradeEvent") && result_str.contains("TradeEventEvent"));
 
-        // Check for EventParseError
-        assert!(result_str.contains("enum EventParseError"));
+        // EventParseError comes from the runtime crate
+        assert!(!result_str.contains("enum EventParseError"));
+        assert!(result_str.contains("pub use solana_idl_codegen_runtime :: EventParseError"));
         assert!(result_str.contains("DataTooShort"));
         assert!(result_str.contains("UnknownDiscriminator"));
         assert!(result_str.contains("DeserializationError"));
@@ -3177,6 +8447,7 @@ mod tests {
                 ty: IdlType::Simple("u64".to_string()),
                 index: false,
             }]),
+            docs: None,
         }];
 
         let result = generate_event_parsing_helpers(&events).unwrap();
@@ -3248,14 +8519,14 @@ mod tests {
         let result = generate_account_validation_helpers(&idl).unwrap();
         let result_str = result.to_string();
 
-        // Check for ValidationError enum
-        assert!(result_str.contains("enum ValidationError"));
-        assert!(result_str.contains("InvalidOwner"));
-        assert!(result_str.contains("DataTooShort"));
-        assert!(result_str.contains("InvalidDiscriminator"));
-        assert!(result_str.contains("DeserializationError"));
+        // ValidationError comes from the runtime crate
+        assert!(!result_str.contains("enum ValidationError"));
+        assert!(result_str.contains("pub use solana_idl_codegen_runtime :: ValidationError"));
 
         // Check for validation methods
+        assert!(
+            result_str.contains("solana_idl_codegen_runtime :: validate_account_info :: < Self >")
+        );
         assert!(result_str.contains("impl TestAccount"));
         assert!(result_str.contains("fn validate_account_info"));
         assert!(result_str.contains("fn try_from_account_info"));
@@ -3288,6 +8559,7 @@ mod tests {
                 },
                 serialization: None,
                 repr: None,
+                generics: None,
             }]),
             errors: None,
             events: None,
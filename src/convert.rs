@@ -0,0 +1,443 @@
+//! Legacy IDL → Anchor 0.30+ spec upgrade
+//!
+//! Pre-0.30 Anchor IDLs keep `name`/`version` at the top level, flag accounts with
+//! `isMut`/`isSigner`, inline account and event layouts, and leave discriminators to
+//! be derived by the consumer. [`upgrade`] rewrites such an IDL into the canonical
+//! new-spec shape: `metadata`, explicit discriminators, `writable`/`signer`, and
+//! every user type reachable from `types` through `{"defined": {"name": ..}}`.
+//!
+//! Upgrading an IDL that is already in the new spec leaves it unchanged.
+
+use std::collections::HashSet;
+
+use sha2::{Digest, Sha256};
+
+use crate::codegen::{self, for_each_idl_type_mut};
+use crate::idl::{
+    AccountArg, ArrayType, DefinedType, DefinedTypeOrString, Field, GenericArg, Idl, IdlType,
+    Metadata, StructFields, TypeDef, TypeDefType,
+};
+
+/// IDL spec version written into `metadata.spec` when the input doesn't have one
+pub const SPEC_VERSION: &str = "0.1.0";
+
+/// Type names that are primitives rather than references to user types
+const PRIMITIVE_TYPES: &[&str] = &[
+    "bool",
+    "u8",
+    "i8",
+    "u16",
+    "i16",
+    "u32",
+    "i32",
+    "u64",
+    "i64",
+    "u128",
+    "i128",
+    "u256",
+    "i256",
+    "f32",
+    "f64",
+    "string",
+    "bytes",
+    "pubkey",
+    "publicKey",
+];
+
+/// Anchor's 8-byte discriminator: the first bytes of `sha256("<namespace>:<name>")`
+pub fn anchor_discriminator(namespace: &str, name: &str) -> Vec<u8> {
+    let hash = Sha256::digest(format!("{}:{}", namespace, name).as_bytes());
+    hash[..8].to_vec()
+}
+
+/// Rewrite `idl` into the canonical Anchor 0.30+ layout
+///
+/// Instructions without discriminator bytes get the ones the generator would use
+/// (see [`codegen::instruction_discriminator`]), so generating from the upgraded IDL
+/// gives the same constants as generating from the original. Accounts and events get
+/// Anchor's `account:`/`event:` hashes only when the IDL comes from Anchor; other
+/// sources (Shank, Codama, ...) keep theirs unset. Explicit discriminators (e.g. from
+/// an override file) are kept as they are.
+pub fn upgrade(idl: &Idl, codama: bool) -> Result<Idl, String> {
+    let mut idl = idl.clone();
+
+    let legacy = idl.is_legacy_anchor();
+    let name = idl.get_name().to_string();
+    let version = idl.get_version().to_string();
+    let old_metadata = idl.metadata.take();
+    let metadata = Metadata {
+        name: Some(name),
+        version: Some(version),
+        spec: old_metadata
+            .as_ref()
+            .and_then(|m| m.spec.clone())
+            .or_else(|| Some(SPEC_VERSION.to_string())),
+        description: old_metadata.as_ref().and_then(|m| m.description.clone()),
+        address: None,
+        origin: old_metadata.as_ref().and_then(|m| m.origin.clone()),
+        deployments: old_metadata.as_ref().and_then(|m| m.deployments.clone()),
+    };
+    idl.address = idl
+        .address
+        .take()
+        .or_else(|| old_metadata.and_then(|m| m.address));
+    idl.name = None;
+    idl.version = None;
+    idl.metadata = Some(metadata);
+
+    let shank = idl.is_shank();
+    let anchor = !codama && idl.is_anchor();
+    for (idx, ix) in idl.instructions.iter_mut().enumerate() {
+        ix.discriminator =
+            Some(codegen::instruction_discriminator(ix, idx, shank).map_err(|e| e.to_string())?);
+        ix.discriminant = None;
+        drop_required_flags(&mut ix.accounts);
+        if legacy {
+            flip_relations(&mut ix.accounts);
+        }
+    }
+
+    let mut types = idl.types.take().unwrap_or_default();
+    for account in idl.accounts.iter_mut().flatten() {
+        if account.discriminator.is_none() && anchor {
+            account.discriminator = Some(anchor_discriminator("account", &account.name));
+        }
+        if let Some(ty) = account.ty.take() {
+            if !types.iter().any(|t| t.name == account.name) {
+                types.push(type_def(&account.name, account.docs.take(), ty));
+            }
+        }
+    }
+    for event in idl.events.iter_mut().flatten() {
+        if event.discriminator.is_none() && anchor {
+            event.discriminator = Some(anchor_discriminator("event", &event.name));
+        }
+        if let Some(fields) = event.fields.take() {
+            if !types.iter().any(|t| t.name == event.name) {
+                let fields = fields
+                    .into_iter()
+                    .map(|field| Field {
+                        name: field.name,
+                        ty: field.ty,
+                        docs: None,
+                    })
+                    .collect();
+                let ty = TypeDefType::Struct {
+                    fields: StructFields::Named(fields),
+                };
+                types.push(type_def(&event.name, None, ty));
+            }
+        }
+    }
+    idl.types = (!types.is_empty()).then_some(types);
+
+    let known: HashSet<String> = idl.types.iter().flatten().map(|t| t.name.clone()).collect();
+    for_each_idl_type_mut(&mut idl, &mut |ty| normalize_type(ty, &known));
+
+    Ok(idl)
+}
+
+/// Remove `isOptional: false`, the default, including inside composite groups
+fn drop_required_flags(accounts: &mut [AccountArg]) {
+    for account in accounts {
+        if account.optional == Some(false) {
+            account.optional = None;
+        }
+        if let Some(members) = &mut account.accounts {
+            drop_required_flags(members);
+        }
+    }
+}
+
+/// Move legacy relations, listed on the account holding the field, onto the
+/// account the field points to, as the new spec lists them
+fn flip_relations(accounts: &mut [AccountArg]) {
+    let mut moved = Vec::new();
+    for account in accounts.iter_mut() {
+        if let Some(members) = &mut account.accounts {
+            flip_relations(members);
+        }
+        for target in account.relations.take().into_iter().flatten() {
+            moved.push((target, account.name.clone()));
+        }
+    }
+    for (target, holder) in moved {
+        if let Some(account) = accounts.iter_mut().find(|a| a.name == target) {
+            account.relations.get_or_insert_with(Vec::new).push(holder);
+        }
+    }
+}
+
+fn type_def(name: &str, docs: Option<Vec<String>>, ty: TypeDefType) -> TypeDef {
+    TypeDef {
+        name: name.to_string(),
+        docs,
+        ty,
+        serialization: None,
+        repr: None,
+        generics: None,
+    }
+}
+
+/// Rewrite legacy spellings inside a type: `publicKey` → `pubkey`, bare
+/// type names and `{"defined": "Name"}` → `{"defined": {"name": "Name"}}`
+fn normalize_type(ty: &mut IdlType, known: &HashSet<String>) {
+    match ty {
+        IdlType::Simple(name) if name == "publicKey" => *name = "pubkey".to_string(),
+        IdlType::Simple(name)
+            if !PRIMITIVE_TYPES.contains(&name.as_str()) && known.contains(name.as_str()) =>
+        {
+            *ty = IdlType::Defined {
+                defined: DefinedTypeOrString::Nested(DefinedType {
+                    name: std::mem::take(name),
+                    generics: None,
+                }),
+            };
+        }
+        IdlType::Simple(_) | IdlType::Generic { .. } => {}
+        IdlType::Defined { defined } => match defined {
+            DefinedTypeOrString::String(name) => {
+                *defined = DefinedTypeOrString::Nested(DefinedType {
+                    name: std::mem::take(name),
+                    generics: None,
+                });
+            }
+            DefinedTypeOrString::Nested(nested) => {
+                for arg in nested.generics.iter_mut().flatten() {
+                    if let GenericArg::Type { ty } = arg {
+                        normalize_type(ty, known);
+                    }
+                }
+            }
+        },
+        IdlType::Vec { vec: inner }
+        | IdlType::Option { option: inner }
+        | IdlType::COption { coption: inner }
+        | IdlType::HashSet { hash_set: inner }
+        | IdlType::BTreeSet { btree_set: inner } => normalize_type(inner, known),
+        IdlType::Array {
+            array: ArrayType::Tuple((inner, _)) | ArrayType::Generic((inner, _)),
+        } => normalize_type(inner, known),
+        IdlType::HashMap {
+            hash_map: (key, value),
+        }
+        | IdlType::BTreeMap {
+            btree_map: (key, value),
+        } => {
+            normalize_type(key, known);
+            normalize_type(value, known);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEGACY_IDL: &str = r#"{
+        "version": "0.1.0",
+        "name": "legacy_program",
+        "metadata": { "address": "11111111111111111111111111111111" },
+        "instructions": [
+            {
+                "name": "initializeVault",
+                "accounts": [
+                    { "name": "vault", "isMut": true, "isSigner": false, "relations": ["authority"] },
+                    { "name": "authority", "isMut": false, "isSigner": true, "isOptional": false }
+                ],
+                "args": [
+                    { "name": "config", "type": { "defined": "VaultConfig" } },
+                    { "name": "owners", "type": { "vec": "publicKey" } }
+                ]
+            }
+        ],
+        "accounts": [
+            {
+                "name": "Vault",
+                "docs": ["A vault"],
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "authority", "type": "publicKey" },
+                        { "name": "config", "type": "VaultConfig" }
+                    ]
+                }
+            }
+        ],
+        "types": [
+            {
+                "name": "VaultConfig",
+                "type": { "kind": "struct", "fields": [{ "name": "limit", "type": "u64" }] }
+            }
+        ],
+        "events": [
+            {
+                "name": "VaultCreated",
+                "fields": [{ "name": "vault", "type": "publicKey", "index": false }]
+            }
+        ],
+        "errors": [{ "code": 6000, "name": "Unauthorized", "msg": "Unauthorized" }]
+    }"#;
+
+    #[test]
+    fn test_anchor_discriminator() {
+        // sha256("global:initialize")[..8], as used by every Anchor program
+        assert_eq!(
+            anchor_discriminator("global", "initialize"),
+            vec![175, 175, 109, 31, 13, 152, 155, 237]
+        );
+    }
+
+    #[test]
+    fn test_upgrade_legacy_idl() {
+        let idl: Idl = serde_json::from_str(LEGACY_IDL).unwrap();
+        let upgraded = upgrade(&idl, false).unwrap();
+        let json = serde_json::to_value(&upgraded).unwrap();
+
+        assert_eq!(json["address"], "11111111111111111111111111111111");
+        assert!(json.get("name").is_none());
+        assert!(json.get("version").is_none());
+        assert_eq!(json["metadata"]["name"], "legacy_program");
+        assert_eq!(json["metadata"]["version"], "0.1.0");
+        assert_eq!(json["metadata"]["spec"], SPEC_VERSION);
+        assert!(json["metadata"].get("address").is_none());
+
+        // Old Anchor instructions are tagged by index, as the generator does
+        let ix = &json["instructions"][0];
+        assert_eq!(
+            ix["discriminator"],
+            serde_json::json!([0, 0, 0, 0, 0, 0, 0, 0])
+        );
+        assert_eq!(
+            ix["accounts"][0],
+            serde_json::json!({ "name": "vault", "writable": true })
+        );
+        assert_eq!(
+            ix["accounts"][1],
+            serde_json::json!({ "name": "authority", "signer": true, "relations": ["vault"] })
+        );
+        assert_eq!(ix["args"][0]["type"]["defined"]["name"], "VaultConfig");
+        assert_eq!(ix["args"][1]["type"]["vec"], "pubkey");
+
+        let account = &json["accounts"][0];
+        assert_eq!(
+            account["discriminator"],
+            serde_json::json!(anchor_discriminator("account", "Vault"))
+        );
+        assert!(account.get("type").is_none());
+        assert!(account.get("docs").is_none());
+
+        let types = json["types"].as_array().unwrap();
+        let names: Vec<_> = types.iter().map(|t| t["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["VaultConfig", "Vault", "VaultCreated"]);
+        assert_eq!(types[1]["docs"][0], "A vault");
+        assert_eq!(types[1]["type"]["fields"][0]["type"], "pubkey");
+        assert_eq!(
+            types[1]["type"]["fields"][1]["type"]["defined"]["name"],
+            "VaultConfig"
+        );
+        assert!(types[2]["type"]["fields"][0].get("index").is_none());
+
+        let event = &json["events"][0];
+        assert_eq!(
+            event["discriminator"],
+            serde_json::json!(anchor_discriminator("event", "VaultCreated"))
+        );
+        assert!(event.get("fields").is_none());
+    }
+
+    #[test]
+    fn test_upgrade_is_idempotent() {
+        let idl: Idl = serde_json::from_str(LEGACY_IDL).unwrap();
+        let once = serde_json::to_value(upgrade(&idl, false).unwrap()).unwrap();
+        let reparsed: Idl = serde_json::from_value(once.clone()).unwrap();
+        let twice = serde_json::to_value(upgrade(&reparsed, false).unwrap()).unwrap();
+        assert_eq!(once, twice);
+    }
+
+    #[test]
+    fn test_upgrade_shank_idl_uses_instruction_tags() {
+        let idl: Idl = serde_json::from_str(
+            r#"{
+                "name": "token_vault",
+                "version": "0.1.0",
+                "metadata": { "origin": "shank" },
+                "instructions": [
+                    { "name": "Init", "accounts": [], "args": [] },
+                    {
+                        "name": "Close",
+                        "accounts": [],
+                        "args": [],
+                        "discriminant": { "type": "u8", "value": 7 }
+                    }
+                ],
+                "accounts": [
+                    { "name": "Vault", "type": { "kind": "struct", "fields": [] } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let upgraded = upgrade(&idl, false).unwrap();
+
+        assert_eq!(upgraded.instructions[0].discriminator, Some(vec![0]));
+        assert_eq!(upgraded.instructions[1].discriminator, Some(vec![7]));
+        assert!(upgraded.instructions[1].discriminant.is_none());
+        // Shank accounts carry no discriminator
+        assert!(upgraded.accounts.as_ref().unwrap()[0]
+            .discriminator
+            .is_none());
+        assert!(upgraded.is_shank());
+    }
+
+    #[test]
+    fn test_upgrade_keeps_generated_discriminators() {
+        let idl: Idl = serde_json::from_str(LEGACY_IDL).unwrap();
+        let upgraded = upgrade(&idl, false).unwrap();
+        let discm = |idl: &Idl| {
+            let code = codegen::generate(idl, "legacy_program").unwrap();
+            code.instructions
+                .lines()
+                .find(|line| line.contains("INITIALIZE_VAULT_IX_DISCM:"))
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(discm(&idl), discm(&upgraded));
+        assert!(discm(&upgraded).contains("[0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8, 0u8]"));
+    }
+
+    #[test]
+    fn test_upgrade_non_anchor_sources_keep_discriminators_unset() {
+        let json = r#"{
+            "name": "counter",
+            "version": "0.1.0",
+            "instructions": [{ "name": "increment", "accounts": [], "args": [] }],
+            "accounts": [{ "name": "Counter", "type": { "kind": "struct", "fields": [] } }],
+            "events": [{ "name": "Incremented", "fields": [] }]
+        }"#;
+        let idl: Idl = serde_json::from_str(json).unwrap();
+
+        let from_codama = upgrade(&idl, true).unwrap();
+        assert!(from_codama.accounts.as_ref().unwrap()[0]
+            .discriminator
+            .is_none());
+        assert!(from_codama.events.as_ref().unwrap()[0]
+            .discriminator
+            .is_none());
+
+        let mut other = idl.clone();
+        other.metadata = Some(Metadata {
+            origin: Some("steel".to_string()),
+            ..Default::default()
+        });
+        let upgraded = upgrade(&other, false).unwrap();
+        assert!(upgraded.accounts.as_ref().unwrap()[0]
+            .discriminator
+            .is_none());
+
+        let from_anchor = upgrade(&idl, false).unwrap();
+        assert_eq!(
+            from_anchor.accounts.as_ref().unwrap()[0].discriminator,
+            Some(anchor_discriminator("account", "Counter"))
+        );
+    }
+}
@@ -1,27 +1,35 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+mod parse;
+mod validate;
+
+pub use parse::{from_str, ParseError};
+pub(crate) use validate::recursive_type_groups;
+pub use validate::{validate, Diagnostic, Severity};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Idl {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub address: Option<String>,
     // Old format IDLs have version and name at top level, new format has metadata
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub metadata: Option<Metadata>,
     #[serde(default)]
     pub instructions: Vec<Instruction>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub accounts: Option<Vec<Account>>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub types: Option<Vec<TypeDef>>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub errors: Option<Vec<Error>>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub events: Option<Vec<Event>>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub constants: Option<Vec<Constant>>,
 }
 
@@ -52,6 +60,32 @@ impl Idl {
         }
     }
 
+    /// Whether the IDL predates the Anchor 0.30 spec, which always sets `metadata.spec`
+    pub fn is_legacy_anchor(&self) -> bool {
+        !self.is_shank()
+            && self
+                .metadata
+                .as_ref()
+                .and_then(|m| m.spec.as_ref())
+                .is_none()
+    }
+
+    /// Whether the IDL was produced by Anchor (`metadata.origin` unset or `"anchor"`)
+    pub fn is_anchor(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.origin.as_deref())
+            .is_none_or(|origin| origin.eq_ignore_ascii_case("anchor"))
+    }
+
+    /// Whether the IDL was produced by Shank (`metadata.origin: "shank"`)
+    pub fn is_shank(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .and_then(|m| m.origin.as_deref())
+            .is_some_and(|origin| origin.eq_ignore_ascii_case("shank"))
+    }
+
     pub fn get_address(&self) -> Option<&str> {
         // Check top-level address first
         if let Some(ref address) = self.address {
@@ -67,53 +101,209 @@ impl Idl {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Metadata {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub version: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub spec: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub address: Option<String>,
+    /// IDL generator, e.g. `"shank"`; Anchor IDLs leave it unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// Per-cluster program addresses, e.g. `{"mainnet": "...", "devnet": "..."}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployments: Option<BTreeMap<String, String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Instruction {
     pub name: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub docs: Option<Vec<String>>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub discriminator: Option<Vec<u8>>,
+    /// Shank-style instruction tag: `{"type": "u8", "value": 3}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discriminant: Option<Discriminant>,
     pub accounts: Vec<AccountArg>,
     pub args: Vec<Arg>,
+    /// Type of the data set with `set_return_data`, for view-style instructions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub returns: Option<IdlType>,
+}
+
+impl Instruction {
+    /// Accounts in on-chain order, with composite groups expanded in place
+    ///
+    /// Members of group `pool` are prefixed with it (`vault` → `pool_vault`), as are
+    /// seed and program paths that refer to a sibling inside the group.
+    pub fn flat_accounts(&self) -> Vec<AccountArg> {
+        let mut flat = Vec::new();
+        flatten_accounts(&self.accounts, None, &mut flat);
+        flat
+    }
+
+    /// `has_one` relations between the accounts, named as in [`Self::flat_accounts`]
+    ///
+    /// Legacy IDLs list relations on the account holding the field (`pool` lists
+    /// `mint`); Anchor 0.30 lists them on the account the field points to (`mint`
+    /// lists `pool`). Either way the field is named after the pointed-to account.
+    pub fn relations(&self, legacy: bool) -> Vec<Relation> {
+        let mut relations = Vec::new();
+        collect_relations(&self.accounts, None, legacy, &mut relations);
+        relations
+    }
+}
+
+/// An Anchor `has_one` constraint between two accounts of an instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relation {
+    /// Account whose data holds the pubkey field
+    pub holder: String,
+    /// Name of the pubkey field in the holder's data
+    pub field: String,
+    /// Account whose key the field must equal
+    pub target: String,
+}
+
+fn collect_relations(
+    accounts: &[AccountArg],
+    prefix: Option<&str>,
+    legacy: bool,
+    relations: &mut Vec<Relation>,
+) {
+    let prefixed = |name: &str| match prefix {
+        Some(prefix) => format!("{}_{}", prefix, name),
+        None => name.to_string(),
+    };
+    // Relation entries name accounts in the same group
+    let sibling = |name: &str| {
+        if accounts.iter().any(|a| a.name == name) {
+            prefixed(name)
+        } else {
+            name.to_string()
+        }
+    };
+
+    for account in accounts {
+        if let Some(members) = &account.accounts {
+            collect_relations(members, Some(&prefixed(&account.name)), legacy, relations);
+            continue;
+        }
+        for other in account.relations.iter().flatten() {
+            relations.push(if legacy {
+                Relation {
+                    holder: prefixed(&account.name),
+                    field: other.clone(),
+                    target: sibling(other),
+                }
+            } else {
+                Relation {
+                    holder: sibling(other),
+                    field: account.name.clone(),
+                    target: prefixed(&account.name),
+                }
+            });
+        }
+    }
+}
+
+fn flatten_accounts(accounts: &[AccountArg], prefix: Option<&str>, flat: &mut Vec<AccountArg>) {
+    let prefixed = |name: &str| match prefix {
+        Some(prefix) => format!("{}_{}", prefix, name),
+        None => name.to_string(),
+    };
+    let prefix_path = |path: &mut String| {
+        let first = path.split('.').next().unwrap_or_default();
+        if accounts.iter().any(|a| a.name == first) {
+            *path = prefixed(path);
+        }
+    };
+
+    for account in accounts {
+        let name = prefixed(&account.name);
+        if let Some(members) = &account.accounts {
+            flatten_accounts(members, Some(&name), flat);
+            continue;
+        }
+        let mut account = account.clone();
+        account.name = name;
+        if let (Some(_), Some(pda)) = (prefix, &mut account.pda) {
+            for seed in &mut pda.seeds {
+                if let Seed::Account { path } = seed {
+                    prefix_path(path);
+                }
+            }
+            if let Some(Program::Account { path }) = &mut pda.program {
+                prefix_path(path);
+            }
+        }
+        flat.push(account);
+    }
+}
+
+/// An integer instruction tag, as emitted by Shank
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Discriminant {
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub value: u64,
+}
+
+impl Discriminant {
+    /// The tag as little-endian bytes, sized by its integer type
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let len = match self.ty.as_str() {
+            "u8" => 1,
+            "u16" => 2,
+            "u32" => 4,
+            "u64" => 8,
+            other => return Err(format!("unsupported discriminant type `{}`", other)),
+        };
+        if len < 8 && self.value >> (len * 8) != 0 {
+            return Err(format!(
+                "discriminant value {} does not fit in {}",
+                self.value, self.ty
+            ));
+        }
+        Ok(self.value.to_le_bytes()[..len].to_vec())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccountArg {
     pub name: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub docs: Option<Vec<String>>,
     // Support both old and new format
-    #[serde(default, alias = "isSigner")]
+    #[serde(default, alias = "isSigner", skip_serializing_if = "is_false")]
     pub signer: bool,
-    #[serde(default, alias = "isMut")]
+    #[serde(default, alias = "isMut", skip_serializing_if = "is_false")]
     pub writable: bool,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pda: Option<Pda>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub address: Option<String>,
-    #[serde(default)]
+    #[serde(default, alias = "isOptional", skip_serializing_if = "Option::is_none")]
     pub optional: Option<bool>,
+    /// `has_one` relations; see [`Instruction::relations`] for their direction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relations: Option<Vec<String>>,
+    /// Members of a composite `Accounts` struct; the entry itself is then not an account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accounts: Option<Vec<AccountArg>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Pda {
     pub seeds: Vec<Seed>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub program: Option<Program>,
 }
 
@@ -142,36 +332,75 @@ pub struct Arg {
     pub name: String,
     #[serde(rename = "type")]
     pub ty: IdlType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Account {
     pub name: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub discriminator: Option<Vec<u8>>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub docs: Option<Vec<String>>,
-    #[serde(rename = "type", default)]
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
     pub ty: Option<TypeDefType>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TypeDef {
     pub name: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub docs: Option<Vec<String>>,
     #[serde(rename = "type")]
     pub ty: TypeDefType,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub serialization: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub repr: Option<Repr>,
+    /// Generic parameters (Anchor 0.30+), in declaration order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generics: Option<Vec<GenericParam>>,
+}
+
+/// A generic parameter declared on a type definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum GenericParam {
+    Type {
+        name: String,
+    },
+    Const {
+        name: String,
+        #[serde(rename = "type")]
+        ty: String,
+    },
+}
+
+impl GenericParam {
+    pub fn name(&self) -> &str {
+        match self {
+            GenericParam::Type { name } | GenericParam::Const { name, .. } => name,
+        }
+    }
+}
+
+/// A generic argument supplied when referencing a defined type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum GenericArg {
+    Type {
+        #[serde(rename = "type")]
+        ty: IdlType,
+    },
+    /// A literal such as `"32"`, or the name of a const parameter in scope
+    Const { value: String },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Repr {
     pub kind: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub packed: Option<bool>,
 }
 
@@ -182,6 +411,8 @@ pub enum TypeDefType {
     Struct { fields: StructFields },
     #[serde(rename = "enum")]
     Enum { variants: Vec<EnumVariant> },
+    #[serde(rename = "type")]
+    Alias { alias: IdlType },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -196,15 +427,20 @@ pub struct Field {
     pub name: String,
     #[serde(rename = "type")]
     pub ty: IdlType,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub docs: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnumVariant {
     pub name: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub fields: Option<EnumFields>,
+    /// Explicit discriminant of a unit variant (Shank)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -218,10 +454,46 @@ pub enum EnumFields {
 #[serde(untagged)]
 pub enum IdlType {
     Simple(String),
-    Vec { vec: Box<IdlType> },
-    Option { option: Box<IdlType> },
-    Array { array: ArrayType },
-    Defined { defined: DefinedTypeOrString },
+    Vec {
+        vec: Box<IdlType>,
+    },
+    Option {
+        option: Box<IdlType>,
+    },
+    Array {
+        array: ArrayType,
+    },
+    Defined {
+        defined: DefinedTypeOrString,
+    },
+    /// Reference to a generic parameter of the enclosing type definition
+    Generic {
+        generic: String,
+    },
+    /// `COption<T>`: like `Option<T>` but with a 4-byte tag (Shank / SPL)
+    COption {
+        coption: Box<IdlType>,
+    },
+    /// `{"hashMap": [K, V]}`
+    HashMap {
+        #[serde(rename = "hashMap")]
+        hash_map: (Box<IdlType>, Box<IdlType>),
+    },
+    /// `{"bTreeMap": [K, V]}`
+    BTreeMap {
+        #[serde(rename = "bTreeMap")]
+        btree_map: (Box<IdlType>, Box<IdlType>),
+    },
+    /// `{"hashSet": T}`
+    HashSet {
+        #[serde(rename = "hashSet")]
+        hash_set: Box<IdlType>,
+    },
+    /// `{"bTreeSet": T}`
+    BTreeSet {
+        #[serde(rename = "bTreeSet")]
+        btree_set: Box<IdlType>,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -244,6 +516,16 @@ impl DefinedTypeOrString {
 #[serde(untagged)]
 pub enum ArrayType {
     Tuple(#[serde(with = "array_tuple")] (Box<IdlType>, usize)),
+    /// Array whose length is a const generic parameter: `["u8", {"generic": "N"}]`
+    Generic(#[serde(with = "array_generic")] (Box<IdlType>, String)),
+}
+
+impl ArrayType {
+    pub fn element(&self) -> &IdlType {
+        match self {
+            ArrayType::Tuple((inner, _)) | ArrayType::Generic((inner, _)) => inner,
+        }
+    }
 }
 
 mod array_tuple {
@@ -281,25 +563,71 @@ mod array_tuple {
     }
 }
 
+mod array_generic {
+    use super::*;
+    #[allow(unused_imports)]
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct GenericLen {
+        generic: String,
+    }
+
+    pub fn serialize<S>(t: &(Box<IdlType>, String), serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::SerializeSeq;
+        let mut seq = serializer.serialize_seq(Some(2))?;
+        seq.serialize_element(&*t.0)?;
+        seq.serialize_element(&GenericLen {
+            generic: t.1.clone(),
+        })?;
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<(Box<IdlType>, String), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let arr: Vec<serde_json::Value> = Vec::deserialize(deserializer)?;
+        if arr.len() != 2 {
+            return Err(serde::de::Error::custom(
+                "Array type must have exactly 2 elements",
+            ));
+        }
+        let ty = IdlType::deserialize(&arr[0]).map_err(serde::de::Error::custom)?;
+        let len = GenericLen::deserialize(&arr[1]).map_err(serde::de::Error::custom)?;
+        Ok((Box::new(ty), len.generic))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DefinedType {
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generics: Option<Vec<GenericArg>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Error {
     pub code: u32,
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub msg: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
     pub name: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub discriminator: Option<Vec<u8>>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub fields: Option<Vec<EventField>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -307,7 +635,7 @@ pub struct EventField {
     pub name: String,
     #[serde(rename = "type")]
     pub ty: IdlType,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub index: bool,
 }
 
@@ -317,6 +645,12 @@ pub struct Constant {
     #[serde(rename = "type")]
     pub ty: IdlType,
     pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs: Option<Vec<String>>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 #[cfg(test)]
@@ -336,6 +670,8 @@ mod tests {
                 spec: None,
                 description: None,
                 address: None,
+                origin: None,
+                deployments: None,
             }),
             instructions: vec![],
             accounts: None,
@@ -396,6 +732,8 @@ mod tests {
                 spec: None,
                 description: None,
                 address: None,
+                origin: None,
+                deployments: None,
             }),
             instructions: vec![],
             accounts: None,
@@ -454,6 +792,7 @@ mod tests {
     fn test_defined_type_or_string_name_nested() {
         let defined = DefinedTypeOrString::Nested(DefinedType {
             name: "NestedType".to_string(),
+            generics: None,
         });
         assert_eq!(defined.name(), "NestedType");
     }
@@ -507,6 +846,7 @@ mod tests {
                     }
                     assert_eq!(size, 32);
                 }
+                ArrayType::Generic(_) => panic!("Expected fixed-length array"),
             },
             _ => panic!("Expected Array variant"),
         }
@@ -667,6 +1007,119 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_deserialize_generic_type_def() {
+        let json = r#"{
+            "name": "GenericType",
+            "generics": [
+                {"kind": "type", "name": "T"},
+                {"kind": "const", "name": "N", "type": "usize"}
+            ],
+            "type": {"kind": "struct", "fields": [
+                {"name": "value", "type": {"generic": "T"}},
+                {"name": "data", "type": {"array": ["u8", {"generic": "N"}]}}
+            ]}
+        }"#;
+        let result: TypeDef = serde_json::from_str(json).unwrap();
+        let generics = result.generics.unwrap();
+        assert!(matches!(&generics[0], GenericParam::Type { name } if name == "T"));
+        assert!(
+            matches!(&generics[1], GenericParam::Const { name, ty } if name == "N" && ty == "usize")
+        );
+        match result.ty {
+            TypeDefType::Struct {
+                fields: StructFields::Named(fields),
+            } => {
+                assert!(matches!(&fields[0].ty, IdlType::Generic { generic } if generic == "T"));
+                match &fields[1].ty {
+                    IdlType::Array {
+                        array: ArrayType::Generic((inner, len)),
+                    } => {
+                        assert!(matches!(inner.as_ref(), IdlType::Simple(s) if s == "u8"));
+                        assert_eq!(len, "N");
+                    }
+                    other => panic!("Expected generic-length array, got {:?}", other),
+                }
+            }
+            _ => panic!("Expected named struct"),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_type_alias() {
+        let json = r#"{"name": "Amount", "type": {"kind": "type", "alias": "u64"}}"#;
+        let result: TypeDef = serde_json::from_str(json).unwrap();
+        assert_eq!(result.name, "Amount");
+        match result.ty {
+            TypeDefType::Alias {
+                alias: IdlType::Simple(s),
+            } => assert_eq!(s, "u64"),
+            other => panic!("Expected alias, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_defined_with_generic_args() {
+        let json = r#"{"defined": {"name": "Wrapper", "generics": [
+            {"kind": "type", "type": "u64"},
+            {"kind": "const", "value": "32"}
+        ]}}"#;
+        let result: IdlType = serde_json::from_str(json).unwrap();
+        match result {
+            IdlType::Defined {
+                defined: DefinedTypeOrString::Nested(defined),
+            } => {
+                assert_eq!(defined.name, "Wrapper");
+                let args = defined.generics.unwrap();
+                assert!(
+                    matches!(&args[0], GenericArg::Type { ty: IdlType::Simple(s) } if s == "u64")
+                );
+                assert!(matches!(&args[1], GenericArg::Const { value } if value == "32"));
+            }
+            other => panic!("Expected defined type, got {:?}", other),
+        }
+
+        // Round-trips without emitting an empty generics list
+        let plain: IdlType = serde_json::from_str(r#"{"defined": {"name": "Plain"}}"#).unwrap();
+        assert_eq!(
+            serde_json::to_string(&plain).unwrap(),
+            r#"{"defined":{"name":"Plain"}}"#
+        );
+    }
+
+    #[test]
+    fn test_deserialize_shank_containers() {
+        let coption: IdlType = serde_json::from_str(r#"{"coption": "publicKey"}"#).unwrap();
+        assert!(
+            matches!(coption, IdlType::COption { coption } if matches!(*coption, IdlType::Simple(ref s) if s == "publicKey"))
+        );
+
+        let map: IdlType =
+            serde_json::from_str(r#"{"hashMap": ["string", {"vec": "u8"}]}"#).unwrap();
+        match map {
+            IdlType::HashMap {
+                hash_map: (key, value),
+            } => {
+                assert!(matches!(*key, IdlType::Simple(ref s) if s == "string"));
+                assert!(matches!(*value, IdlType::Vec { .. }));
+            }
+            other => panic!("Expected hashMap, got {:?}", other),
+        }
+
+        let btree: IdlType = serde_json::from_str(r#"{"bTreeMap": ["u64", "bool"]}"#).unwrap();
+        assert!(matches!(btree, IdlType::BTreeMap { .. }));
+        let set: IdlType = serde_json::from_str(r#"{"hashSet": "u32"}"#).unwrap();
+        assert!(matches!(set, IdlType::HashSet { .. }));
+        let btree_set: IdlType = serde_json::from_str(r#"{"bTreeSet": "u32"}"#).unwrap();
+        assert!(matches!(btree_set, IdlType::BTreeSet { .. }));
+
+        // Serializes back to the same camelCase keys
+        assert_eq!(
+            serde_json::to_string(&btree).unwrap(),
+            r#"{"bTreeMap":["u64","bool"]}"#
+        );
+    }
+
     #[test]
     fn test_deserialize_full_instruction() {
         let json = r#"{
@@ -688,6 +1141,57 @@ mod tests {
         assert_eq!(result.discriminator, Some(vec![1, 2, 3, 4, 5, 6, 7, 8]));
     }
 
+    #[test]
+    fn test_deserialize_shank_idl() {
+        let json = r#"{
+            "version": "1.0.0",
+            "name": "token_metadata",
+            "instructions": [{
+                "name": "CreateMetadata",
+                "accounts": [
+                    {"name": "metadata", "isMut": true, "isSigner": false, "desc": "Metadata key"},
+                    {"name": "rent", "isMut": false, "isSigner": false, "isOptional": true}
+                ],
+                "args": [],
+                "discriminant": {"type": "u8", "value": 33}
+            }],
+            "types": [{
+                "name": "Key",
+                "type": {"kind": "enum", "variants": [
+                    {"name": "Uninitialized", "value": 0},
+                    {"name": "MetadataV1", "value": 4}
+                ]}
+            }],
+            "metadata": {"origin": "shank", "address": "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s"}
+        }"#;
+        let idl: Idl = serde_json::from_str(json).unwrap();
+        assert!(idl.is_shank());
+
+        let ix = &idl.instructions[0];
+        assert_eq!(
+            ix.discriminant.as_ref().unwrap().to_bytes().unwrap(),
+            vec![33]
+        );
+        assert_eq!(ix.accounts[1].optional, Some(true));
+
+        match &idl.types.unwrap()[0].ty {
+            TypeDefType::Enum { variants } => assert_eq!(variants[1].value, Some(4)),
+            other => panic!("Expected enum, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_discriminant_to_bytes() {
+        let discriminant = |ty: &str, value| Discriminant {
+            ty: ty.to_string(),
+            value,
+        };
+        assert_eq!(discriminant("u16", 0x0102).to_bytes().unwrap(), vec![2, 1]);
+        assert_eq!(discriminant("u64", 1).to_bytes().unwrap().len(), 8);
+        assert!(discriminant("u8", 256).to_bytes().is_err());
+        assert!(discriminant("i8", 1).to_bytes().is_err());
+    }
+
     #[test]
     fn test_deserialize_minimal_idl() {
         let json = r#"{
@@ -716,6 +1220,167 @@ mod tests {
         assert_eq!(result.get_version(), "1.0.0");
     }
 
+    #[test]
+    fn test_flat_accounts_expands_groups_in_order() {
+        let json = r#"{
+            "name": "swap",
+            "accounts": [
+                { "name": "user", "isSigner": true },
+                {
+                    "name": "pool",
+                    "accounts": [
+                        { "name": "state", "isMut": true },
+                        {
+                            "name": "vault",
+                            "isMut": true,
+                            "pda": { "seeds": [{ "kind": "account", "path": "state" }] }
+                        },
+                        { "name": "oracle", "accounts": [{ "name": "feed" }] }
+                    ]
+                },
+                { "name": "tokenProgram" }
+            ],
+            "args": []
+        }"#;
+        let ix: Instruction = serde_json::from_str(json).unwrap();
+        assert_eq!(ix.accounts.len(), 3);
+        assert_eq!(ix.accounts[1].accounts.as_ref().unwrap().len(), 3);
+
+        let flat = ix.flat_accounts();
+        let names: Vec<_> = flat.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "user",
+                "pool_state",
+                "pool_vault",
+                "pool_oracle_feed",
+                "tokenProgram"
+            ]
+        );
+        assert!(flat[2].writable);
+        match &flat[2].pda.as_ref().unwrap().seeds[0] {
+            Seed::Account { path } => assert_eq!(path, "pool_state"),
+            seed => panic!("unexpected seed {:?}", seed),
+        }
+    }
+
+    #[test]
+    fn test_relations_in_both_directions() {
+        let legacy: Instruction = serde_json::from_str(
+            r#"{
+                "name": "swap",
+                "accounts": [
+                    { "name": "pool", "isMut": true, "isSigner": false, "relations": ["mint"] },
+                    { "name": "mint", "isMut": false, "isSigner": false }
+                ],
+                "args": []
+            }"#,
+        )
+        .unwrap();
+        let current: Instruction = serde_json::from_str(
+            r#"{
+                "name": "swap",
+                "accounts": [
+                    { "name": "pool", "writable": true },
+                    { "name": "mint", "relations": ["pool"] }
+                ],
+                "args": []
+            }"#,
+        )
+        .unwrap();
+
+        let expected = vec![Relation {
+            holder: "pool".to_string(),
+            field: "mint".to_string(),
+            target: "mint".to_string(),
+        }];
+        assert_eq!(legacy.relations(true), expected);
+        assert_eq!(current.relations(false), expected);
+    }
+
+    #[test]
+    fn test_relations_inside_group_use_flat_names() {
+        let ix: Instruction = serde_json::from_str(
+            r#"{
+                "name": "swap",
+                "accounts": [{
+                    "name": "amm",
+                    "accounts": [
+                        { "name": "pool" },
+                        { "name": "mint", "relations": ["pool"] }
+                    ]
+                }],
+                "args": []
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            ix.relations(false),
+            vec![Relation {
+                holder: "amm_pool".to_string(),
+                field: "mint".to_string(),
+                target: "amm_mint".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_instruction_returns() {
+        let json = r#"{
+            "name": "getPrice",
+            "accounts": [],
+            "args": [],
+            "returns": { "defined": { "name": "Price" } }
+        }"#;
+        let ix: Instruction = serde_json::from_str(json).unwrap();
+        assert!(matches!(ix.returns, Some(IdlType::Defined { .. })));
+
+        let ix: Instruction =
+            serde_json::from_str(r#"{ "name": "init", "accounts": [], "args": [] }"#).unwrap();
+        assert!(ix.returns.is_none());
+    }
+
+    #[test]
+    fn test_deserialize_docs() {
+        let json = r#"{
+            "instructions": [{
+                "name": "deposit",
+                "accounts": [],
+                "args": [{ "name": "amount", "type": "u64", "docs": ["Amount"] }]
+            }],
+            "types": [{
+                "name": "Side",
+                "type": { "kind": "enum", "variants": [{ "name": "Bid", "docs": ["Buy"] }] }
+            }],
+            "events": [{ "name": "Deposited", "docs": ["Deposit made"] }],
+            "errors": [{ "code": 6000, "name": "Paused", "docs": ["Vault paused"] }],
+            "constants": [{ "name": "SEED", "type": "bytes", "value": "[1]", "docs": ["Seed"] }]
+        }"#;
+        let idl: Idl = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            idl.instructions[0].args[0].docs,
+            Some(vec!["Amount".to_string()])
+        );
+        let TypeDefType::Enum { variants } = &idl.types.as_ref().unwrap()[0].ty else {
+            panic!("expected enum");
+        };
+        assert_eq!(variants[0].docs, Some(vec!["Buy".to_string()]));
+        assert_eq!(
+            idl.events.unwrap()[0].docs,
+            Some(vec!["Deposit made".to_string()])
+        );
+        assert_eq!(
+            idl.errors.unwrap()[0].docs,
+            Some(vec!["Vault paused".to_string()])
+        );
+        assert_eq!(
+            idl.constants.unwrap()[0].docs,
+            Some(vec!["Seed".to_string()])
+        );
+    }
+
     #[test]
     fn test_serialize_and_deserialize_roundtrip() {
         let original = Idl {
@@ -729,6 +1394,8 @@ mod tests {
                 discriminator: Some(vec![1, 2, 3, 4, 5, 6, 7, 8]),
                 accounts: vec![],
                 args: vec![],
+                discriminant: None,
+                returns: None,
             }],
             accounts: None,
             types: None,
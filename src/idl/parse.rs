@@ -0,0 +1,777 @@
+//! IDL parsing with located error messages
+//!
+//! `IdlType`, `StructFields` and `EnumFields` are untagged, so when one of them fails
+//! serde can only say "data did not match any variant". [`from_str`] tracks the path
+//! serde got to, then re-checks the offending JSON node by hand to find the innermost
+//! part that doesn't fit, what was expected there, and the closest supported form.
+
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use super::Idl;
+
+/// A parse failure located in the source document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Path of the offending node, e.g. `types[12].type.fields[3].type`
+    pub path: String,
+    /// 1-based line of the offending node
+    pub line: usize,
+    /// 1-based column of the offending node
+    pub column: usize,
+    pub message: String,
+    /// What the parser accepts at this position
+    pub expected: Option<String>,
+    /// The supported form closest to what was written
+    pub hint: Option<String>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "(root)"
+        } else {
+            &self.path
+        };
+        write!(
+            f,
+            "{} (line {}, column {}): {}",
+            path, self.line, self.column, self.message
+        )?;
+        if let Some(expected) = &self.expected {
+            write!(f, "\n  expected: {}", expected)?;
+        }
+        if let Some(hint) = &self.hint {
+            write!(f, "\n  closest supported form: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse an Anchor/Shank IDL document
+pub fn from_str(json: &str) -> Result<Idl, ParseError> {
+    let value: Value = serde_json::from_str(json).map_err(|e| ParseError {
+        path: String::new(),
+        line: e.line(),
+        column: e.column(),
+        message: e.to_string(),
+        expected: None,
+        hint: None,
+    })?;
+
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let error = match serde_path_to_error::deserialize::<_, Idl>(&mut deserializer) {
+        Ok(idl) => return Ok(idl),
+        Err(error) => error,
+    };
+
+    let path: Vec<Segment> = error
+        .path()
+        .iter()
+        .map_while(|segment| match segment {
+            serde_path_to_error::Segment::Seq { index } => Some(Segment::Index(*index)),
+            serde_path_to_error::Segment::Map { key } => Some(Segment::Key(key.clone())),
+            serde_path_to_error::Segment::Enum { variant } => Some(Segment::Key(variant.clone())),
+            serde_path_to_error::Segment::Unknown => None,
+        })
+        .collect();
+    let inner = error.into_inner();
+    let message = strip_position(&inner.to_string());
+
+    let problem = lookup(&value, &path).and_then(|node| diagnose(node, &path, &message));
+    let (path, message, expected, hint) = match problem {
+        Some(problem) => (
+            problem.path,
+            problem.message,
+            Some(problem.expected),
+            problem.hint,
+        ),
+        None => {
+            let hint = unknown_variant_hint(&message);
+            (path, message, None, hint)
+        }
+    };
+    let (line, column) = locate(json, &path).unwrap_or((inner.line(), inner.column()));
+
+    Err(ParseError {
+        path: format_path(&path),
+        line,
+        column,
+        message,
+        expected,
+        hint,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+fn child(path: &[Segment], segment: Segment) -> Vec<Segment> {
+    let mut path = path.to_vec();
+    path.push(segment);
+    path
+}
+
+fn key(path: &[Segment], key: &str) -> Vec<Segment> {
+    child(path, Segment::Key(key.to_string()))
+}
+
+fn index(path: &[Segment], index: usize) -> Vec<Segment> {
+    child(path, Segment::Index(index))
+}
+
+fn format_path(path: &[Segment]) -> String {
+    let mut out = String::new();
+    for segment in path {
+        match segment {
+            Segment::Key(key) if out.is_empty() => out.push_str(key),
+            Segment::Key(key) => {
+                out.push('.');
+                out.push_str(key);
+            }
+            Segment::Index(index) => out.push_str(&format!("[{}]", index)),
+        }
+    }
+    out
+}
+
+fn lookup<'v>(value: &'v Value, path: &[Segment]) -> Option<&'v Value> {
+    path.iter().try_fold(value, |value, segment| match segment {
+        Segment::Key(key) => value.get(key.as_str()),
+        Segment::Index(index) => value.get(*index),
+    })
+}
+
+/// serde_json appends " at line X column Y"; the position is reported separately
+fn strip_position(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(at) => message[..at].to_string(),
+        None => message.to_string(),
+    }
+}
+
+/// For "unknown variant `x`, expected one of `a`, `b`", suggest the closest variant
+fn unknown_variant_hint(message: &str) -> Option<String> {
+    let rest = message.strip_prefix("unknown variant `")?;
+    let (unknown, expected) = rest.split_once("`, expected ")?;
+    let candidates: Vec<&str> = expected.split('`').skip(1).step_by(2).collect();
+    closest(unknown, &candidates).map(|c| format!("`{}`", c))
+}
+
+fn closest<'c>(unknown: &str, candidates: &[&'c str]) -> Option<&'c str> {
+    let unknown = unknown.to_lowercase();
+    candidates
+        .iter()
+        .map(|c| (strsim::jaro_winkler(&unknown, &c.to_lowercase()), *c))
+        .filter(|(similarity, _)| *similarity > 0.7)
+        .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(_, c)| c)
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => format!("boolean `{}`", b),
+        Value::Number(n) => format!("number `{}`", n),
+        Value::String(s) => format!("string \"{}\"", s),
+        Value::Array(_) => "an array".to_string(),
+        Value::Object(map) => {
+            let keys: Vec<String> = map.keys().map(|k| format!("\"{}\"", k)).collect();
+            format!("an object with keys {}", keys.join(", "))
+        }
+    }
+}
+
+struct Problem {
+    path: Vec<Segment>,
+    message: String,
+    expected: String,
+    hint: Option<String>,
+}
+
+impl Problem {
+    fn new(path: Vec<Segment>, message: String, expected: &str) -> Self {
+        Self {
+            path,
+            message,
+            expected: expected.to_string(),
+            hint: None,
+        }
+    }
+
+    fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+const TYPE_EXPECTED: &str = "a type name such as \"u64\", or an object with one of the keys \
+    `vec`, `option`, `array`, `defined`, `generic`, `coption`, `hashMap`, `bTreeMap`, `hashSet`, `bTreeSet`";
+const TYPE_KEYS: &[&str] = &[
+    "vec", "option", "array", "defined", "generic", "coption", "hashMap", "bTreeMap", "hashSet",
+    "bTreeSet",
+];
+const TYPE_DEF_EXPECTED: &str = "an object with `kind` \"struct\" (with `fields`), \"enum\" (with \
+    `variants`) or \"type\" (with `alias`)";
+const FIELDS_EXPECTED: &str =
+    "an array of named fields ({\"name\": ..., \"type\": ...}) or an array of types";
+
+/// Find the innermost node under `node` that doesn't fit the schema serde was decoding
+fn diagnose(node: &Value, path: &[Segment], message: &str) -> Option<Problem> {
+    if !message.contains("did not match any variant") {
+        return None;
+    }
+    if message.contains("enum IdlType") {
+        diagnose_type(node, path)
+    } else if node.get("kind").is_some() {
+        diagnose_type_def(node, path)
+    } else if message.contains("StructFields") || message.contains("EnumFields") {
+        diagnose_fields(node, path)
+    } else if message.contains("DefinedTypeOrString") {
+        diagnose_defined(node, path)
+    } else if message.contains("ArrayType") {
+        diagnose_array(node, path)
+    } else {
+        diagnose_type(node, path)
+    }
+}
+
+fn diagnose_type_def(node: &Value, path: &[Segment]) -> Option<Problem> {
+    let Some(map) = node.as_object() else {
+        return Some(Problem::new(
+            path.to_vec(),
+            format!("type definition is {}", describe(node)),
+            TYPE_DEF_EXPECTED,
+        ));
+    };
+    match map.get("kind").and_then(Value::as_str) {
+        Some("struct") => match map.get("fields") {
+            Some(fields) => diagnose_fields(fields, &key(path, "fields")),
+            None => Some(
+                Problem::new(
+                    path.to_vec(),
+                    "struct definition has no `fields`".to_string(),
+                    TYPE_DEF_EXPECTED,
+                )
+                .hint("{\"kind\": \"struct\", \"fields\": []}"),
+            ),
+        },
+        Some("enum") => {
+            let Some(variants) = map.get("variants").and_then(Value::as_array) else {
+                return Some(
+                    Problem::new(
+                        path.to_vec(),
+                        "enum definition has no `variants` array".to_string(),
+                        TYPE_DEF_EXPECTED,
+                    )
+                    .hint("{\"kind\": \"enum\", \"variants\": [{\"name\": \"A\"}]}"),
+                );
+            };
+            let variants_path = key(path, "variants");
+            variants.iter().enumerate().find_map(|(k, variant)| {
+                let variant_path = index(&variants_path, k);
+                match variant.get("fields") {
+                    Some(fields) => diagnose_fields(fields, &key(&variant_path, "fields")),
+                    None => None,
+                }
+            })
+        }
+        Some("type") => match map.get("alias") {
+            Some(alias) => diagnose_type(alias, &key(path, "alias")),
+            None => None,
+        },
+        Some(other) => Some(with_closest(
+            Problem::new(
+                key(path, "kind"),
+                format!("unknown type definition kind \"{}\"", other),
+                TYPE_DEF_EXPECTED,
+            ),
+            other,
+            &["struct", "enum", "type"],
+            |c| format!("\"kind\": \"{}\"", c),
+        )),
+        None => Some(Problem::new(
+            path.to_vec(),
+            "type definition has no `kind`".to_string(),
+            TYPE_DEF_EXPECTED,
+        )),
+    }
+}
+
+fn diagnose_fields(node: &Value, path: &[Segment]) -> Option<Problem> {
+    let Some(items) = node.as_array() else {
+        return Some(Problem::new(
+            path.to_vec(),
+            format!("fields are {}", describe(node)),
+            FIELDS_EXPECTED,
+        ));
+    };
+    let named = items
+        .first()
+        .is_some_and(|first| first.get("name").is_some());
+    for (j, item) in items.iter().enumerate() {
+        let item_path = index(path, j);
+        if !named {
+            if let Some(problem) = diagnose_type(item, &item_path) {
+                return Some(problem);
+            }
+            continue;
+        }
+        let Some(map) = item.as_object() else {
+            return Some(Problem::new(
+                item_path,
+                format!("field is {}", describe(item)),
+                "a named field {\"name\": ..., \"type\": ...} (fields can't mix named and tuple forms)",
+            ));
+        };
+        if !map.get("name").is_some_and(Value::is_string) {
+            return Some(Problem::new(
+                item_path,
+                "field has no string `name`".to_string(),
+                "a named field {\"name\": ..., \"type\": ...} (fields can't mix named and tuple forms)",
+            ));
+        }
+        match map.get("type") {
+            Some(ty) => {
+                if let Some(problem) = diagnose_type(ty, &key(&item_path, "type")) {
+                    return Some(problem);
+                }
+            }
+            None => {
+                let problem = Problem::new(
+                    item_path,
+                    "field has no `type`".to_string(),
+                    "a named field {\"name\": ..., \"type\": ...}",
+                );
+                return Some(match misspelled_key(map, &["type"]) {
+                    Some(found) => problem.hint(format!("rename `{}` to `type`", found)),
+                    None => problem,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// A key of `map` that looks like a typo of one of `wanted`
+fn misspelled_key<'m>(map: &'m Map<String, Value>, wanted: &[&str]) -> Option<&'m str> {
+    map.keys()
+        .map(String::as_str)
+        .filter(|k| !wanted.contains(k) && *k != "name" && *k != "docs")
+        .find(|k| closest(k, wanted).is_some())
+}
+
+fn with_closest(
+    problem: Problem,
+    unknown: &str,
+    candidates: &[&str],
+    form: impl Fn(&str) -> String,
+) -> Problem {
+    match closest(unknown, candidates) {
+        Some(c) => problem.hint(form(c)),
+        None => problem,
+    }
+}
+
+fn type_form(key: &str) -> String {
+    match key {
+        "array" => "{\"array\": [<type>, <length>]}".to_string(),
+        "defined" => "{\"defined\": {\"name\": \"<TypeName>\"}}".to_string(),
+        "generic" => "{\"generic\": \"<T>\"}".to_string(),
+        "hashMap" | "bTreeMap" => format!("{{\"{}\": [<key type>, <value type>]}}", key),
+        _ => format!("{{\"{}\": <type>}}", key),
+    }
+}
+
+fn diagnose_type(node: &Value, path: &[Segment]) -> Option<Problem> {
+    let map = match node {
+        Value::String(_) => return None,
+        Value::Object(map) => map,
+        other => {
+            return Some(Problem::new(
+                path.to_vec(),
+                format!("type is {}", describe(other)),
+                TYPE_EXPECTED,
+            ))
+        }
+    };
+
+    if map.contains_key("kind") && !map.keys().any(|k| TYPE_KEYS.contains(&k.as_str())) {
+        return Some(
+            Problem::new(
+                path.to_vec(),
+                "found an inline type definition where a type reference is expected".to_string(),
+                TYPE_EXPECTED,
+            )
+            .hint("move it into `types` and reference it with {\"defined\": {\"name\": \"<TypeName>\"}}"),
+        );
+    }
+
+    let mut entries = map.iter();
+    let (Some((name, inner)), None) = (entries.next(), entries.next()) else {
+        return Some(Problem::new(
+            path.to_vec(),
+            format!("type object has {} keys instead of one", map.len()),
+            TYPE_EXPECTED,
+        ));
+    };
+    let inner_path = key(path, name);
+    match name.as_str() {
+        "vec" | "option" | "coption" | "hashSet" | "bTreeSet" => diagnose_type(inner, &inner_path),
+        "array" => diagnose_array(inner, &inner_path),
+        "defined" => diagnose_defined(inner, &inner_path),
+        "generic" if inner.is_string() => None,
+        "generic" => Some(
+            Problem::new(
+                inner_path,
+                format!("generic parameter is {}", describe(inner)),
+                "the name of a generic parameter",
+            )
+            .hint(type_form("generic")),
+        ),
+        "hashMap" | "bTreeMap" => match inner.as_array().map(Vec::as_slice) {
+            Some([k, v]) => diagnose_type(k, &index(&inner_path, 0))
+                .or_else(|| diagnose_type(v, &index(&inner_path, 1))),
+            _ => Some(
+                Problem::new(
+                    inner_path,
+                    format!("map type is {}", describe(inner)),
+                    "a two-element array of key and value types",
+                )
+                .hint(type_form(name)),
+            ),
+        },
+        other => {
+            let problem = Problem::new(
+                path.to_vec(),
+                format!("unknown type key `{}`", other),
+                TYPE_EXPECTED,
+            );
+            Some(with_closest(problem, other, TYPE_KEYS, type_form))
+        }
+    }
+}
+
+fn diagnose_array(node: &Value, path: &[Segment]) -> Option<Problem> {
+    let Some([element, len]) = node.as_array().map(Vec::as_slice) else {
+        return Some(
+            Problem::new(
+                path.to_vec(),
+                format!("array type is {}", describe(node)),
+                "a two-element array of element type and length",
+            )
+            .hint(type_form("array")),
+        );
+    };
+    if let Some(problem) = diagnose_type(element, &index(path, 0)) {
+        return Some(problem);
+    }
+    let len_path = index(path, 1);
+    match len {
+        Value::Number(n) if n.is_u64() => None,
+        Value::Object(map) if map.get("generic").is_some_and(Value::is_string) => None,
+        Value::String(s) if s.parse::<u64>().is_ok() => Some(
+            Problem::new(
+                len_path,
+                format!("array length is the string \"{}\"", s),
+                "a non-negative number or {\"generic\": \"N\"}",
+            )
+            .hint(s.clone()),
+        ),
+        other => Some(
+            Problem::new(
+                len_path,
+                format!("array length is {}", describe(other)),
+                "a non-negative number or {\"generic\": \"N\"}",
+            )
+            .hint("{\"generic\": \"N\"}"),
+        ),
+    }
+}
+
+fn diagnose_defined(node: &Value, path: &[Segment]) -> Option<Problem> {
+    let map = match node {
+        Value::String(_) => return None,
+        Value::Object(map) => map,
+        other => {
+            return Some(
+                Problem::new(
+                    path.to_vec(),
+                    format!("`defined` is {}", describe(other)),
+                    "a type name or {\"name\": ..., \"generics\": [...]}",
+                )
+                .hint("{\"defined\": {\"name\": \"<TypeName>\"}}"),
+            )
+        }
+    };
+    if !map.get("name").is_some_and(Value::is_string) {
+        let problem = Problem::new(
+            path.to_vec(),
+            "`defined` has no string `name`".to_string(),
+            "a type name or {\"name\": ..., \"generics\": [...]}",
+        );
+        return Some(match misspelled_key(map, &["name"]) {
+            Some(found) => problem.hint(format!("rename `{}` to `name`", found)),
+            None => problem.hint("{\"defined\": {\"name\": \"<TypeName>\"}}"),
+        });
+    }
+    let generics_path = key(path, "generics");
+    let generics = match map.get("generics") {
+        None | Some(Value::Null) => return None,
+        Some(Value::Array(generics)) => generics,
+        Some(other) => {
+            return Some(Problem::new(
+                generics_path,
+                format!("generic arguments are {}", describe(other)),
+                "an array of {\"kind\": \"type\", \"type\": ...} or {\"kind\": \"const\", \"value\": ...}",
+            ))
+        }
+    };
+    generics.iter().enumerate().find_map(|(k, arg)| {
+        let arg_path = index(&generics_path, k);
+        match arg.get("kind").and_then(Value::as_str) {
+            Some("type") => match arg.get("type") {
+                Some(ty) => diagnose_type(ty, &key(&arg_path, "type")),
+                None => Some(Problem::new(
+                    arg_path,
+                    "type argument has no `type`".to_string(),
+                    "{\"kind\": \"type\", \"type\": ...}",
+                )),
+            },
+            Some("const") => match arg.get("value") {
+                Some(Value::String(_)) => None,
+                Some(Value::Number(n)) => Some(
+                    Problem::new(
+                        key(&arg_path, "value"),
+                        format!("const argument is the number {}", n),
+                        "a string",
+                    )
+                    .hint(format!("\"value\": \"{}\"", n)),
+                ),
+                _ => Some(Problem::new(
+                    arg_path,
+                    "const argument has no string `value`".to_string(),
+                    "{\"kind\": \"const\", \"value\": \"<value>\"}",
+                )),
+            },
+            _ => Some(Problem::new(
+                arg_path,
+                format!("generic argument is {}", describe(arg)),
+                "{\"kind\": \"type\", \"type\": ...} or {\"kind\": \"const\", \"value\": ...}",
+            )),
+        }
+    })
+}
+
+/// 1-based line and column where the value at `path` starts in `source`
+fn locate(source: &str, path: &[Segment]) -> Option<(usize, usize)> {
+    let bytes = source.as_bytes();
+    let mut pos = skip_ws(bytes, 0);
+    for segment in path {
+        pos = match (segment, bytes.get(pos)?) {
+            (Segment::Key(wanted), b'{') => {
+                let mut pos = skip_ws(bytes, pos + 1);
+                loop {
+                    let key_end = skip_string(bytes, pos)?;
+                    let key = &source[pos + 1..key_end - 1];
+                    pos = skip_ws(bytes, key_end);
+                    if bytes.get(pos) != Some(&b':') {
+                        return None;
+                    }
+                    pos = skip_ws(bytes, pos + 1);
+                    if key == wanted {
+                        break pos;
+                    }
+                    pos = skip_ws(bytes, skip_value(bytes, pos)?);
+                    if bytes.get(pos) != Some(&b',') {
+                        return None;
+                    }
+                    pos = skip_ws(bytes, pos + 1);
+                }
+            }
+            (Segment::Index(wanted), b'[') => {
+                let mut pos = skip_ws(bytes, pos + 1);
+                for _ in 0..*wanted {
+                    pos = skip_ws(bytes, skip_value(bytes, pos)?);
+                    if bytes.get(pos) != Some(&b',') {
+                        return None;
+                    }
+                    pos = skip_ws(bytes, pos + 1);
+                }
+                pos
+            }
+            _ => return None,
+        };
+    }
+
+    let before = &source[..pos];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = source[line_start..pos].chars().count() + 1;
+    Some((line, column))
+}
+
+fn skip_ws(bytes: &[u8], mut pos: usize) -> usize {
+    while bytes.get(pos).is_some_and(u8::is_ascii_whitespace) {
+        pos += 1;
+    }
+    pos
+}
+
+/// Position just past the string starting at `pos`
+fn skip_string(bytes: &[u8], pos: usize) -> Option<usize> {
+    if bytes.get(pos) != Some(&b'"') {
+        return None;
+    }
+    let mut pos = pos + 1;
+    loop {
+        match bytes.get(pos)? {
+            b'\\' => pos += 2,
+            b'"' => return Some(pos + 1),
+            _ => pos += 1,
+        }
+    }
+}
+
+/// Position just past the value starting at `pos`
+fn skip_value(bytes: &[u8], pos: usize) -> Option<usize> {
+    match bytes.get(pos)? {
+        b'"' => skip_string(bytes, pos),
+        b'{' | b'[' => {
+            let mut depth = 0usize;
+            let mut pos = pos;
+            loop {
+                match bytes.get(pos)? {
+                    b'"' => {
+                        pos = skip_string(bytes, pos)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(pos + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                pos += 1;
+            }
+        }
+        _ => {
+            let mut pos = pos;
+            while bytes
+                .get(pos)
+                .is_some_and(|b| !matches!(b, b',' | b'}' | b']') && !b.is_ascii_whitespace())
+            {
+                pos += 1;
+            }
+            Some(pos)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_err(json: &str) -> ParseError {
+        from_str(json).unwrap_err()
+    }
+
+    const TYPES_PREFIX: &str = r#"{
+  "instructions": [],
+  "types": [
+    {
+      "name": "Config",
+      "type": {
+        "kind": "struct",
+        "fields": [
+          { "name": "limit", "type": "u64" },
+"#;
+
+    #[test]
+    fn test_parse_valid_idl() {
+        let idl = from_str(r#"{"instructions": [], "types": []}"#).unwrap();
+        assert!(idl.instructions.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_type_key_is_located() {
+        let json = format!(
+            "{}{}",
+            TYPES_PREFIX,
+            r#"          { "name": "items", "type": { "Vec": "u8" } }
+        ]
+      }
+    }
+  ]
+}"#
+        );
+        let err = parse_err(&json);
+        assert_eq!(err.path, "types[0].type.fields[1].type");
+        assert_eq!((err.line, err.column), (10, 38));
+        assert_eq!(err.message, "unknown type key `Vec`");
+        assert_eq!(err.hint.as_deref(), Some("{\"vec\": <type>}"));
+        assert!(err.expected.unwrap().contains("`vec`"));
+    }
+
+    #[test]
+    fn test_nested_array_length_string() {
+        let json = format!(
+            "{}{}",
+            TYPES_PREFIX,
+            r#"          { "name": "data", "type": { "option": { "array": ["u8", "32"] } } }
+        ]
+      }
+    }
+  ]
+}"#
+        );
+        let err = parse_err(&json);
+        assert_eq!(err.path, "types[0].type.fields[1].type.option.array[1]");
+        assert_eq!(err.line, 10);
+        assert_eq!(err.hint.as_deref(), Some("32"));
+    }
+
+    #[test]
+    fn test_inline_definition_in_instruction_arg() {
+        let err = parse_err(
+            r#"{"instructions": [{"name": "init", "accounts": [], "args": [
+                {"name": "config", "type": {"kind": "struct", "fields": []}}
+            ]}]}"#,
+        );
+        assert_eq!(err.path, "instructions[0].args[0].type");
+        assert_eq!(err.line, 2);
+        assert!(err.message.contains("inline type definition"));
+        assert!(err.hint.unwrap().contains("defined"));
+    }
+
+    #[test]
+    fn test_unknown_definition_kind() {
+        let err = parse_err(
+            r#"{"instructions": [], "types": [{"name": "A", "type": {"kind": "structure", "fields": []}}]}"#,
+        );
+        assert_eq!(err.path, "types[0].type.kind");
+        assert!(err.message.contains("unknown variant `structure`"));
+        assert_eq!(err.hint.as_deref(), Some("`struct`"));
+    }
+
+    #[test]
+    fn test_missing_field_and_syntax_errors() {
+        let err = parse_err(r#"{"instructions": [{"name": "init", "args": []}]}"#);
+        assert_eq!(err.path, "instructions[0]");
+        assert_eq!(err.message, "missing field `accounts`");
+
+        let err = parse_err("{\n  \"instructions\": [\n}");
+        assert_eq!(err.path, "");
+        assert_eq!(err.line, 3);
+        assert!(err.to_string().starts_with("(root) (line 3, column 1)"));
+    }
+}
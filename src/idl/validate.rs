@@ -0,0 +1,1408 @@
+//! Semantic checks on a parsed IDL
+//!
+//! Deserializing only proves that the JSON has the right shape. [`validate`] looks for
+//! the problems that would otherwise show up as a `cargo check` failure in the
+//! generated crate (dangling references, duplicate names, infinitely sized types) or
+//! not at all (colliding discriminators, padding in a bytemuck layout).
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use heck::ToSnakeCase;
+use serde::Serialize;
+
+use super::{
+    AccountArg, ArrayType, DefinedTypeOrString, EnumFields, GenericArg, GenericParam, Idl, IdlType,
+    StructFields, TypeDef, TypeDefType,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A single finding, located by its JSON path in the IDL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable identifier of the check, e.g. `unknown-type`
+    pub code: &'static str,
+    /// Path of the offending node, e.g. `types[3].type.fields[0].type`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}] {}: {}",
+            self.severity, self.code, self.path, self.message
+        )
+    }
+}
+
+/// Run every semantic check over `idl`, in IDL order
+pub fn validate(idl: &Idl) -> Vec<Diagnostic> {
+    let mut validator = Validator::new(idl);
+    validator.check_duplicates();
+    validator.check_types();
+    validator.check_accounts_have_types();
+    validator.check_cycles();
+    validator.check_discriminators();
+    validator.check_bytemuck_layouts();
+    validator.check_relations();
+    validator.check_deployments();
+    validator.diagnostics
+}
+
+/// Names of the definitions that contain each other by value, grouped by cycle
+///
+/// The generator boxes references between members of the same group.
+pub(crate) fn recursive_type_groups(idl: &Idl) -> Vec<Vec<&str>> {
+    Validator::new(idl).recursive_groups()
+}
+
+/// Type names that map to a built-in Rust type rather than a definition
+const BUILTIN_TYPES: &[&str] = &[
+    "bool",
+    "u8",
+    "i8",
+    "u16",
+    "i16",
+    "u32",
+    "i32",
+    "u64",
+    "i64",
+    "u128",
+    "i128",
+    "u256",
+    "i256",
+    "f32",
+    "f64",
+    "string",
+    "bytes",
+    "publicKey",
+    "pubkey",
+    "Pubkey",
+];
+
+/// A named layout: a `types` entry or a legacy account with an inline `type`
+struct Definition<'a> {
+    path: String,
+    ty: &'a TypeDefType,
+    generics: &'a [GenericParam],
+    serialization: Option<&'a str>,
+    packed: bool,
+}
+
+struct Validator<'a> {
+    idl: &'a Idl,
+    definitions: HashMap<&'a str, Definition<'a>>,
+    /// Definition names in IDL order, for deterministic output
+    order: Vec<&'a str>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Validator<'a> {
+    fn new(idl: &'a Idl) -> Self {
+        let mut definitions = HashMap::new();
+        let mut order = Vec::new();
+        for (i, ty) in idl.types.iter().flatten().enumerate() {
+            if definitions.contains_key(ty.name.as_str()) {
+                continue;
+            }
+            order.push(ty.name.as_str());
+            definitions.insert(
+                ty.name.as_str(),
+                Definition {
+                    path: format!("types[{}]", i),
+                    ty: &ty.ty,
+                    generics: ty.generics.as_deref().unwrap_or_default(),
+                    serialization: ty.serialization.as_deref(),
+                    packed: is_packed(ty),
+                },
+            );
+        }
+        for (i, account) in idl.accounts.iter().flatten().enumerate() {
+            let Some(ty) = &account.ty else { continue };
+            if definitions.contains_key(account.name.as_str()) {
+                continue;
+            }
+            order.push(account.name.as_str());
+            definitions.insert(
+                account.name.as_str(),
+                Definition {
+                    path: format!("accounts[{}]", i),
+                    ty,
+                    generics: &[],
+                    serialization: None,
+                    packed: false,
+                },
+            );
+        }
+        Self {
+            idl,
+            definitions,
+            order,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, severity: Severity, code: &'static str, path: String, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            code,
+            path,
+            message,
+        });
+    }
+
+    fn check_duplicates(&mut self) {
+        let idl = self.idl;
+        self.duplicate_names(
+            "instruction",
+            "instructions",
+            idl.instructions.iter().map(|ix| ix.name.as_str()),
+        );
+        self.duplicate_names(
+            "account",
+            "accounts",
+            idl.accounts.iter().flatten().map(|a| a.name.as_str()),
+        );
+        self.duplicate_names(
+            "type",
+            "types",
+            idl.types.iter().flatten().map(|t| t.name.as_str()),
+        );
+        self.duplicate_names(
+            "event",
+            "events",
+            idl.events.iter().flatten().map(|e| e.name.as_str()),
+        );
+        self.duplicate_names(
+            "error",
+            "errors",
+            idl.errors.iter().flatten().map(|e| e.name.as_str()),
+        );
+        self.duplicate_names(
+            "constant",
+            "constants",
+            idl.constants.iter().flatten().map(|c| c.name.as_str()),
+        );
+
+        let mut codes: HashMap<u32, usize> = HashMap::new();
+        for (i, error) in idl.errors.iter().flatten().enumerate() {
+            if let Some(first) = codes.insert(error.code, i) {
+                codes.insert(error.code, first);
+                self.push(
+                    Severity::Error,
+                    "duplicate-error-code",
+                    format!("errors[{}].code", i),
+                    format!(
+                        "error code {} is also used by errors[{}]",
+                        error.code, first
+                    ),
+                );
+            }
+        }
+
+        for (i, ix) in idl.instructions.iter().enumerate() {
+            self.duplicate_account_names(&ix.accounts, &format!("instructions[{}].accounts", i));
+            self.duplicate_names(
+                "argument",
+                &format!("instructions[{}].args", i),
+                ix.args.iter().map(|a| a.name.as_str()),
+            );
+        }
+        for (i, event) in idl.events.iter().flatten().enumerate() {
+            self.duplicate_names(
+                "field",
+                &format!("events[{}].fields", i),
+                event.fields.iter().flatten().map(|f| f.name.as_str()),
+            );
+        }
+    }
+
+    /// `has_one` relations must name accounts of the same instruction, and some
+    /// program account must hold the pubkey field `<ix>_verify_relations` compares
+    fn check_relations(&mut self) {
+        let idl = self.idl;
+        let with_discriminators = crate::codegen::with_legacy_account_discriminators(idl);
+        let pubkey_fields: HashSet<String> =
+            crate::codegen::account_pubkey_fields(&with_discriminators)
+                .into_iter()
+                .flat_map(|(_, fields)| fields)
+                .collect();
+        for (i, ix) in idl.instructions.iter().enumerate() {
+            let accounts = ix.flat_accounts();
+            for relation in ix.relations(idl.is_legacy_anchor()) {
+                let mut known = true;
+                for name in [&relation.holder, &relation.target] {
+                    if !accounts.iter().any(|a| &a.name == name) {
+                        known = false;
+                        self.push(
+                            Severity::Warning,
+                            "unknown-relation",
+                            format!("instructions[{}].accounts", i),
+                            format!(
+                                "relation between `{}` and `{}` names no account `{}`",
+                                relation.holder, relation.target, name
+                            ),
+                        );
+                    }
+                }
+                if known && !pubkey_fields.contains(&relation.field.to_snake_case()) {
+                    self.push(
+                        Severity::Warning,
+                        "unchecked-relation",
+                        format!("instructions[{}].accounts", i),
+                        format!(
+                            "no program account with a discriminator has a pubkey field `{}`, \
+                             so the relation between `{}` and `{}` is not verified",
+                            relation.field, relation.holder, relation.target
+                        ),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Deployment addresses that aren't base58 pubkeys get no named constructor, and
+    /// cluster names that clash with a `Program` function get a suffixed one
+    fn check_deployments(&mut self) {
+        let idl = self.idl;
+        let Some(deployments) = idl.metadata.as_ref().and_then(|m| m.deployments.as_ref()) else {
+            return;
+        };
+        let with_discriminators = crate::codegen::with_legacy_account_discriminators(idl);
+        let methods = crate::codegen::program_handle_methods(
+            &with_discriminators,
+            idl.get_address().is_some(),
+        );
+        for (cluster, address) in deployments {
+            let valid = bs58::decode(address)
+                .into_vec()
+                .is_ok_and(|bytes| bytes.len() == 32);
+            if !valid {
+                self.push(
+                    Severity::Warning,
+                    "invalid-deployment",
+                    format!("metadata.deployments.{}", cluster),
+                    format!("`{}` is not a base58 program address", address),
+                );
+                continue;
+            }
+            let constructor = crate::codegen::deployment_constructor(cluster, &methods);
+            if constructor != cluster.to_snake_case() {
+                self.push(
+                    Severity::Warning,
+                    "deployment-name-clash",
+                    format!("metadata.deployments.{}", cluster),
+                    format!(
+                        "`{}` clashes with `Program::{}`, so its constructor is `Program::{}()`",
+                        cluster,
+                        cluster.to_snake_case(),
+                        constructor
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Duplicate instruction account names, level by level through composite groups
+    fn duplicate_account_names(&mut self, accounts: &'a [AccountArg], path: &str) {
+        self.duplicate_names("account", path, accounts.iter().map(|a| a.name.as_str()));
+        for (i, account) in accounts.iter().enumerate() {
+            if let Some(members) = &account.accounts {
+                self.duplicate_account_names(members, &format!("{}[{}].accounts", path, i));
+            }
+        }
+    }
+
+    /// Report every name that already appeared earlier in `names`
+    fn duplicate_names<'n>(
+        &mut self,
+        what: &str,
+        path: &str,
+        names: impl Iterator<Item = &'n str>,
+    ) {
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        for (i, name) in names.enumerate() {
+            if let Some(&first) = seen.get(name) {
+                self.push(
+                    Severity::Error,
+                    "duplicate-name",
+                    format!("{}[{}].name", path, i),
+                    format!(
+                        "{} `{}` is already defined at {}[{}]",
+                        what, name, path, first
+                    ),
+                );
+            } else {
+                seen.insert(name, i);
+            }
+        }
+    }
+
+    fn check_types(&mut self) {
+        let idl = self.idl;
+        for (i, ty) in idl.types.iter().flatten().enumerate() {
+            let scope = ty.generics.as_deref().unwrap_or_default();
+            self.check_type_def(&ty.ty, &format!("types[{}].type", i), scope);
+        }
+        for (i, account) in idl.accounts.iter().flatten().enumerate() {
+            if let Some(ty) = &account.ty {
+                self.check_type_def(ty, &format!("accounts[{}].type", i), &[]);
+            }
+        }
+        for (i, ix) in idl.instructions.iter().enumerate() {
+            for (j, arg) in ix.args.iter().enumerate() {
+                self.check_type(
+                    &arg.ty,
+                    &format!("instructions[{}].args[{}].type", i, j),
+                    &[],
+                );
+            }
+            if let Some(returns) = &ix.returns {
+                self.check_type(returns, &format!("instructions[{}].returns", i), &[]);
+            }
+        }
+        for (i, event) in idl.events.iter().flatten().enumerate() {
+            for (j, field) in event.fields.iter().flatten().enumerate() {
+                self.check_type(&field.ty, &format!("events[{}].fields[{}].type", i, j), &[]);
+            }
+        }
+        for (i, constant) in idl.constants.iter().flatten().enumerate() {
+            self.check_type(&constant.ty, &format!("constants[{}].type", i), &[]);
+        }
+    }
+
+    fn check_type_def(&mut self, ty: &TypeDefType, path: &str, scope: &[GenericParam]) {
+        match ty {
+            TypeDefType::Struct { fields } => {
+                self.check_fields(fields_of(fields), &format!("{}.fields", path), scope)
+            }
+            TypeDefType::Enum { variants } => {
+                self.duplicate_names(
+                    "variant",
+                    &format!("{}.variants", path),
+                    variants.iter().map(|v| v.name.as_str()),
+                );
+                for (k, variant) in variants.iter().enumerate() {
+                    let fields = match &variant.fields {
+                        Some(EnumFields::Named(fields)) => Fields::Named(fields),
+                        Some(EnumFields::Tuple(types)) => Fields::Tuple(types),
+                        None => continue,
+                    };
+                    self.check_fields(fields, &format!("{}.variants[{}].fields", path, k), scope);
+                }
+            }
+            TypeDefType::Alias { alias } => {
+                self.check_type(alias, &format!("{}.alias", path), scope)
+            }
+        }
+    }
+
+    fn check_fields(&mut self, fields: Fields<'_>, path: &str, scope: &[GenericParam]) {
+        match fields {
+            Fields::Named(fields) => {
+                self.duplicate_names("field", path, fields.iter().map(|f| f.name.as_str()));
+                for (j, field) in fields.iter().enumerate() {
+                    self.check_type(&field.ty, &format!("{}[{}].type", path, j), scope);
+                }
+            }
+            Fields::Tuple(types) => {
+                for (j, ty) in types.iter().enumerate() {
+                    self.check_type(ty, &format!("{}[{}]", path, j), scope);
+                }
+            }
+        }
+    }
+
+    /// Check that every name referenced by `ty` resolves
+    fn check_type(&mut self, ty: &IdlType, path: &str, scope: &[GenericParam]) {
+        match ty {
+            IdlType::Simple(name) => {
+                if !BUILTIN_TYPES.contains(&name.as_str())
+                    && !self.definitions.contains_key(name.as_str())
+                {
+                    let message = format!(
+                        "unknown type `{}`{}",
+                        name,
+                        self.suggest(name, BUILTIN_TYPES)
+                    );
+                    self.push(Severity::Error, "unknown-type", path.to_string(), message);
+                }
+            }
+            IdlType::Defined { defined } => {
+                let name = defined.name();
+                let args = match defined {
+                    DefinedTypeOrString::Nested(nested) => nested.generics.as_deref(),
+                    DefinedTypeOrString::String(_) => None,
+                };
+                let Some(expected) = self.definitions.get(name).map(|d| d.generics.len()) else {
+                    let message = format!(
+                        "`defined` references unknown type `{}`{}",
+                        name,
+                        self.suggest(name, &[])
+                    );
+                    self.push(Severity::Error, "unknown-type", path.to_string(), message);
+                    return;
+                };
+                let supplied = args.map_or(0, <[GenericArg]>::len);
+                if supplied != expected {
+                    self.push(
+                        Severity::Error,
+                        "generic-arity",
+                        path.to_string(),
+                        format!(
+                            "`{}` takes {} generic argument(s) but {} were supplied",
+                            name, expected, supplied
+                        ),
+                    );
+                }
+                for (k, arg) in args.into_iter().flatten().enumerate() {
+                    match arg {
+                        GenericArg::Type { ty } => self.check_type(
+                            ty,
+                            &format!("{}.defined.generics[{}].type", path, k),
+                            scope,
+                        ),
+                        GenericArg::Const { value } => {
+                            self.check_const_generic(
+                                value,
+                                &format!("{}.defined.generics[{}].value", path, k),
+                                scope,
+                            );
+                        }
+                    }
+                }
+            }
+            IdlType::Generic { generic } => {
+                let declared = scope
+                    .iter()
+                    .any(|p| matches!(p, GenericParam::Type { name } if name == generic));
+                if !declared {
+                    self.push(
+                        Severity::Error,
+                        "unknown-generic",
+                        path.to_string(),
+                        format!("generic type parameter `{}` is not declared", generic),
+                    );
+                }
+            }
+            IdlType::Vec { vec: inner } => self.check_type(inner, &format!("{}.vec", path), scope),
+            IdlType::Option { option: inner } => {
+                self.check_type(inner, &format!("{}.option", path), scope)
+            }
+            IdlType::COption { coption: inner } => {
+                self.check_type(inner, &format!("{}.coption", path), scope)
+            }
+            IdlType::HashSet { hash_set: inner } => {
+                self.check_type(inner, &format!("{}.hashSet", path), scope)
+            }
+            IdlType::BTreeSet { btree_set: inner } => {
+                self.check_type(inner, &format!("{}.bTreeSet", path), scope)
+            }
+            IdlType::HashMap {
+                hash_map: (key, value),
+            } => {
+                self.check_type(key, &format!("{}.hashMap[0]", path), scope);
+                self.check_type(value, &format!("{}.hashMap[1]", path), scope);
+            }
+            IdlType::BTreeMap {
+                btree_map: (key, value),
+            } => {
+                self.check_type(key, &format!("{}.bTreeMap[0]", path), scope);
+                self.check_type(value, &format!("{}.bTreeMap[1]", path), scope);
+            }
+            IdlType::Array { array } => {
+                self.check_type(array.element(), &format!("{}.array[0]", path), scope);
+                if let ArrayType::Generic((_, len)) = array {
+                    self.check_const_generic(len, &format!("{}.array[1]", path), scope);
+                }
+            }
+        }
+    }
+
+    fn check_const_generic(&mut self, value: &str, path: &str, scope: &[GenericParam]) {
+        let declared = scope
+            .iter()
+            .any(|p| matches!(p, GenericParam::Const { name, .. } if name == value));
+        if !declared && value.parse::<u64>().is_err() {
+            self.push(
+                Severity::Error,
+                "unknown-generic",
+                path.to_string(),
+                format!(
+                    "`{}` is neither a number nor a declared const parameter",
+                    value
+                ),
+            );
+        }
+    }
+
+    /// `"\nDid you mean 'X'?"` for the closest defined (or extra) name
+    fn suggest(&self, unknown: &str, extra: &[&str]) -> String {
+        self.order
+            .iter()
+            .chain(extra)
+            .map(|candidate| (strsim::jaro_winkler(unknown, candidate), candidate))
+            .filter(|(similarity, _)| *similarity > 0.8)
+            .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(_, closest)| format!(" (did you mean `{}`?)", closest))
+            .unwrap_or_default()
+    }
+
+    fn check_accounts_have_types(&mut self) {
+        let idl = self.idl;
+        for (i, account) in idl.accounts.iter().flatten().enumerate() {
+            if account.ty.is_none() && !self.definitions.contains_key(account.name.as_str()) {
+                let message = format!(
+                    "account `{}` has no inline `type` and no matching entry in `types`{}",
+                    account.name,
+                    self.suggest(&account.name, &[])
+                );
+                self.push(
+                    Severity::Error,
+                    "account-without-type",
+                    format!("accounts[{}]", i),
+                    message,
+                );
+            }
+        }
+    }
+
+    /// Groups of definitions that contain each other by value (not behind a `Vec`,
+    /// map or set), in IDL order
+    fn recursive_groups(&self) -> Vec<Vec<&'a str>> {
+        let index: HashMap<&str, usize> = self
+            .order
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (*name, i))
+            .collect();
+        let edges: Vec<Vec<usize>> = self
+            .order
+            .iter()
+            .map(|name| {
+                let mut targets = Vec::new();
+                visit_type_def_types(self.definitions[name].ty, &mut |ty| {
+                    embedded_names(ty, &mut targets)
+                });
+                let mut targets: Vec<usize> = targets
+                    .iter()
+                    .filter_map(|t| index.get(t).copied())
+                    .collect();
+                targets.sort_unstable();
+                targets.dedup();
+                targets
+            })
+            .collect();
+
+        strongly_connected_components(&edges)
+            .into_iter()
+            .filter(|component| component.len() > 1 || edges[component[0]].contains(&component[0]))
+            .map(|component| component.iter().map(|&i| self.order[i]).collect())
+            .collect()
+    }
+
+    /// Recursive types are generated with `Box` around the recursive references,
+    /// which isn't possible for bytemuck types
+    fn check_cycles(&mut self) {
+        for names in self.recursive_groups() {
+            if !names
+                .iter()
+                .any(|name| is_bytemuck(self.definitions[name].serialization))
+            {
+                continue;
+            }
+            let path = self.definitions[names[0]].path.clone();
+            let message = if names.len() == 1 {
+                format!(
+                    "bytemuck type `{}` contains itself and has infinite size",
+                    names[0]
+                )
+            } else {
+                format!(
+                    "types {} contain each other and include a bytemuck type, which can't be boxed",
+                    names
+                        .iter()
+                        .map(|name| format!("`{}`", name))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            };
+            self.push(Severity::Error, "recursive-type", path, message);
+        }
+    }
+
+    fn check_discriminators(&mut self) {
+        let idl = self.idl;
+        let shank = idl.is_shank();
+        // Anchor derives 8-byte discriminators; other generators choose their own
+        let anchor = !shank
+            && idl
+                .metadata
+                .as_ref()
+                .and_then(|m| m.origin.as_deref())
+                .is_none_or(|origin| origin.eq_ignore_ascii_case("anchor"));
+
+        let mut instructions: Vec<(usize, Vec<u8>)> = Vec::new();
+        for (i, ix) in idl.instructions.iter().enumerate() {
+            let path = format!("instructions[{}].discriminator", i);
+            match crate::codegen::instruction_discriminator(ix, i, shank) {
+                Ok(disc) => {
+                    self.check_discriminator_length("instruction", &ix.name, &disc, &path, anchor);
+                    for (j, other) in &instructions {
+                        if !disc.is_empty() && !other.is_empty() && is_prefix_pair(&disc, other) {
+                            self.push(
+                                Severity::Error,
+                                "discriminator-collision",
+                                path.clone(),
+                                format!(
+                                    "instruction `{}` discriminator {:?} is indistinguishable from instruction `{}` ({:?}) at instructions[{}]",
+                                    ix.name, disc, idl.instructions[*j].name, other, j
+                                ),
+                            );
+                        }
+                    }
+                    instructions.push((i, disc));
+                }
+                Err(e) => self.push(
+                    Severity::Error,
+                    "invalid-discriminator",
+                    path,
+                    e.to_string(),
+                ),
+            }
+        }
+
+        // Accounts and events are both decoded from raw bytes by their prefix
+        let mut seen: Vec<(&str, String, &str, &[u8])> = Vec::new();
+        for (i, account) in idl.accounts.iter().flatten().enumerate() {
+            let Some(disc) = &account.discriminator else {
+                continue;
+            };
+            let path = format!("accounts[{}].discriminator", i);
+            self.check_discriminator_length("account", &account.name, disc, &path, anchor);
+            self.check_collision("account", &account.name, disc, &path, &seen);
+            seen.push(("account", path, &account.name, disc));
+        }
+        for (i, event) in idl.events.iter().flatten().enumerate() {
+            let Some(disc) = &event.discriminator else {
+                continue;
+            };
+            let path = format!("events[{}].discriminator", i);
+            if disc.len() != 8 {
+                self.push(
+                    Severity::Error,
+                    "invalid-discriminator",
+                    path.clone(),
+                    format!(
+                        "event `{}` discriminator is {} byte(s); events are matched on exactly 8",
+                        event.name,
+                        disc.len()
+                    ),
+                );
+            }
+            self.check_collision("event", &event.name, disc, &path, &seen);
+            seen.push(("event", path, &event.name, disc));
+        }
+    }
+
+    fn check_discriminator_length(
+        &mut self,
+        what: &str,
+        name: &str,
+        disc: &[u8],
+        path: &str,
+        anchor: bool,
+    ) {
+        if disc.is_empty() {
+            self.push(
+                Severity::Error,
+                "invalid-discriminator",
+                path.to_string(),
+                format!("{} `{}` has an empty discriminator", what, name),
+            );
+        } else if anchor && disc.len() != 8 {
+            self.push(
+                Severity::Warning,
+                "discriminator-length",
+                path.to_string(),
+                format!(
+                    "{} `{}` discriminator is {} byte(s); Anchor discriminators are 8",
+                    what,
+                    name,
+                    disc.len()
+                ),
+            );
+        }
+    }
+
+    fn check_collision(
+        &mut self,
+        what: &str,
+        name: &str,
+        disc: &[u8],
+        path: &str,
+        seen: &[(&str, String, &str, &[u8])],
+    ) {
+        for (other_what, other_path, other_name, other) in seen {
+            if disc.is_empty() || other.is_empty() || !is_prefix_pair(disc, other) {
+                continue;
+            }
+            // Accounts and events live in separate decoders, so a clash between the two
+            // only matters when both are tried on the same data
+            let severity = if *other_what == what {
+                Severity::Error
+            } else {
+                Severity::Warning
+            };
+            self.push(
+                severity,
+                "discriminator-collision",
+                path.to_string(),
+                format!(
+                    "{} `{}` discriminator {:?} collides with {} `{}` at {}",
+                    what, name, disc, other_what, other_name, other_path
+                ),
+            );
+        }
+    }
+
+    /// `bytemuck` types are reinterpreted in place, so every field needs a fixed size
+    /// and (unless packed) the struct must not contain padding
+    fn check_bytemuck_layouts(&mut self) {
+        let order = self.order.clone();
+        for name in order {
+            let definition = &self.definitions[name];
+            let (path, ty, serialization, packed) = (
+                definition.path.clone(),
+                definition.ty,
+                definition.serialization,
+                definition.packed,
+            );
+            if !is_bytemuck(serialization) {
+                continue;
+            }
+            let TypeDefType::Struct { fields } = ty else {
+                continue;
+            };
+            let checked = serialization == Some("bytemuck") && !packed;
+
+            let mut offset = 0usize;
+            let mut max_align = 1usize;
+            let mut complete = true;
+            let fields: Vec<(String, &IdlType)> = match fields {
+                StructFields::Named(fields) => fields
+                    .iter()
+                    .map(|f| (format!("`{}`", f.name), &f.ty))
+                    .collect(),
+                StructFields::Tuple(types) => types
+                    .iter()
+                    .enumerate()
+                    .map(|(i, ty)| (format!("{}", i), ty))
+                    .collect(),
+            };
+            for (j, (field, ty)) in fields.into_iter().enumerate() {
+                match self.pod_layout(ty, &mut HashSet::new()) {
+                    Err(reason) => {
+                        self.push(
+                            Severity::Error,
+                            "bytemuck-layout",
+                            format!("{}.type.fields[{}].type", path, j),
+                            format!("field {} of bytemuck type `{}` {}", field, name, reason),
+                        );
+                        complete = false;
+                    }
+                    Ok(None) => complete = false,
+                    Ok(Some((size, align))) if complete => {
+                        let aligned = offset.next_multiple_of(align);
+                        if checked && aligned != offset {
+                            self.push(
+                                Severity::Warning,
+                                "bytemuck-padding",
+                                format!("{}.type.fields[{}]", path, j),
+                                format!(
+                                    "`{}` has {} byte(s) of padding before field {}; bytemuck::Pod requires a layout without padding",
+                                    name,
+                                    aligned - offset,
+                                    field
+                                ),
+                            );
+                        }
+                        offset = aligned + size;
+                        max_align = max_align.max(align);
+                    }
+                    Ok(Some(_)) => {}
+                }
+            }
+            let total = offset.next_multiple_of(max_align);
+            if checked && complete && total != offset {
+                self.push(
+                    Severity::Warning,
+                    "bytemuck-padding",
+                    path,
+                    format!(
+                        "`{}` has {} byte(s) of trailing padding; bytemuck::Pod requires a layout without padding",
+                        name,
+                        total - offset
+                    ),
+                );
+            }
+        }
+    }
+
+    /// `(size, align)` of `ty` under `#[repr(C)]`, `None` when it can't be computed
+    /// statically (e.g. a const generic length), or why it can't be `Pod`
+    fn pod_layout(
+        &self,
+        ty: &IdlType,
+        visiting: &mut HashSet<&'a str>,
+    ) -> Result<Option<(usize, usize)>, String> {
+        match ty {
+            IdlType::Simple(name) => match name.as_str() {
+                "bool" | "u8" | "i8" => Ok(Some((1, 1))),
+                "u16" | "i16" => Ok(Some((2, 2))),
+                "u32" | "i32" | "f32" => Ok(Some((4, 4))),
+                "u64" | "i64" | "f64" => Ok(Some((8, 8))),
+                "u128" | "i128" => Ok(Some((16, 16))),
+                "u256" | "i256" | "publicKey" | "pubkey" | "Pubkey" => Ok(Some((32, 1))),
+                "string" | "bytes" => Err(format!("has dynamically sized type `{}`", name)),
+                other => self.defined_pod_layout(other, visiting),
+            },
+            IdlType::Defined { defined } => self.defined_pod_layout(defined.name(), visiting),
+            IdlType::Array {
+                array: ArrayType::Tuple((inner, len)),
+            } => Ok(self
+                .pod_layout(inner, visiting)?
+                .map(|(size, align)| (size * len, align))),
+            IdlType::Array { array } => self.pod_layout(array.element(), visiting).map(|_| None),
+            IdlType::Generic { .. } => Ok(None),
+            IdlType::Vec { .. }
+            | IdlType::HashMap { .. }
+            | IdlType::BTreeMap { .. }
+            | IdlType::HashSet { .. }
+            | IdlType::BTreeSet { .. } => Err("has a dynamically sized type".to_string()),
+            IdlType::Option { .. } | IdlType::COption { .. } => {
+                Err("has an optional type, which is not Pod".to_string())
+            }
+        }
+    }
+
+    fn defined_pod_layout(
+        &self,
+        name: &str,
+        visiting: &mut HashSet<&'a str>,
+    ) -> Result<Option<(usize, usize)>, String> {
+        // Unknown names are reported by `check_types`
+        let Some((&key, definition)) = self.definitions.get_key_value(name) else {
+            return Ok(None);
+        };
+        if !is_bytemuck(definition.serialization) {
+            return Err(format!("has type `{}`, which is not a bytemuck type", name));
+        }
+        let TypeDefType::Struct { fields } = definition.ty else {
+            return Ok(None);
+        };
+        if !visiting.insert(key) {
+            return Ok(None);
+        }
+        let mut offset = 0usize;
+        let mut max_align = 1usize;
+        for ty in fields_of(fields).types() {
+            let Some((size, align)) = self.pod_layout(ty, visiting).unwrap_or(None) else {
+                visiting.remove(key);
+                return Ok(None);
+            };
+            let align = if definition.packed { 1 } else { align };
+            offset = offset.next_multiple_of(align) + size;
+            max_align = max_align.max(align);
+        }
+        visiting.remove(key);
+        Ok(Some((offset.next_multiple_of(max_align), max_align)))
+    }
+}
+
+enum Fields<'f> {
+    Named(&'f [super::Field]),
+    Tuple(&'f [IdlType]),
+}
+
+impl<'f> Fields<'f> {
+    fn types(&self) -> Vec<&'f IdlType> {
+        match self {
+            Fields::Named(fields) => fields.iter().map(|f| &f.ty).collect(),
+            Fields::Tuple(types) => types.iter().collect(),
+        }
+    }
+}
+
+fn fields_of(fields: &StructFields) -> Fields<'_> {
+    match fields {
+        StructFields::Named(fields) => Fields::Named(fields),
+        StructFields::Tuple(types) => Fields::Tuple(types),
+    }
+}
+
+fn is_bytemuck(serialization: Option<&str>) -> bool {
+    matches!(serialization, Some("bytemuck" | "bytemuckunsafe"))
+}
+
+fn is_packed(ty: &TypeDef) -> bool {
+    ty.repr.as_ref().and_then(|r| r.packed).unwrap_or(false)
+}
+
+/// Whether one discriminator is a prefix of the other, so the earlier one
+/// shadows the later one when matching
+fn is_prefix_pair(a: &[u8], b: &[u8]) -> bool {
+    a.starts_with(b) || b.starts_with(a)
+}
+
+fn visit_type_def_types<'t>(ty: &'t TypeDefType, f: &mut dyn FnMut(&'t IdlType)) {
+    match ty {
+        TypeDefType::Struct { fields } => fields_of(fields).types().into_iter().for_each(f),
+        TypeDefType::Enum { variants } => {
+            for variant in variants {
+                match &variant.fields {
+                    Some(EnumFields::Named(fields)) => fields.iter().for_each(|field| f(&field.ty)),
+                    Some(EnumFields::Tuple(types)) => types.iter().for_each(&mut *f),
+                    None => {}
+                }
+            }
+        }
+        TypeDefType::Alias { alias } => f(alias),
+    }
+}
+
+/// Names of the types stored inline in `ty`; `Vec`, maps and sets allocate
+/// their contents, so recursion through them is fine
+fn embedded_names<'t>(ty: &'t IdlType, out: &mut Vec<&'t str>) {
+    match ty {
+        IdlType::Simple(name) => out.push(name),
+        IdlType::Defined { defined } => {
+            out.push(defined.name());
+            if let DefinedTypeOrString::Nested(nested) = defined {
+                for arg in nested.generics.iter().flatten() {
+                    if let GenericArg::Type { ty } = arg {
+                        embedded_names(ty, out);
+                    }
+                }
+            }
+        }
+        IdlType::Option { option: inner } | IdlType::COption { coption: inner } => {
+            embedded_names(inner, out)
+        }
+        IdlType::Array { array } => embedded_names(array.element(), out),
+        IdlType::Generic { .. }
+        | IdlType::Vec { .. }
+        | IdlType::HashMap { .. }
+        | IdlType::BTreeMap { .. }
+        | IdlType::HashSet { .. }
+        | IdlType::BTreeSet { .. } => {}
+    }
+}
+
+/// Tarjan's algorithm; each component lists its nodes in ascending order
+fn strongly_connected_components(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    struct State<'e> {
+        edges: &'e [Vec<usize>],
+        index: Vec<Option<usize>>,
+        low: Vec<usize>,
+        on_stack: Vec<bool>,
+        stack: Vec<usize>,
+        next: usize,
+        components: Vec<Vec<usize>>,
+    }
+
+    fn connect(state: &mut State<'_>, v: usize) {
+        state.index[v] = Some(state.next);
+        state.low[v] = state.next;
+        state.next += 1;
+        state.stack.push(v);
+        state.on_stack[v] = true;
+
+        for &w in &state.edges[v] {
+            match state.index[w] {
+                None => {
+                    connect(state, w);
+                    state.low[v] = state.low[v].min(state.low[w]);
+                }
+                Some(index) if state.on_stack[w] => state.low[v] = state.low[v].min(index),
+                Some(_) => {}
+            }
+        }
+
+        if Some(state.low[v]) == state.index[v] {
+            let mut component = Vec::new();
+            while let Some(w) = state.stack.pop() {
+                state.on_stack[w] = false;
+                component.push(w);
+                if w == v {
+                    break;
+                }
+            }
+            component.sort_unstable();
+            state.components.push(component);
+        }
+    }
+
+    let n = edges.len();
+    let mut state = State {
+        edges,
+        index: vec![None; n],
+        low: vec![0; n],
+        on_stack: vec![false; n],
+        stack: Vec::new(),
+        next: 0,
+        components: Vec::new(),
+    };
+    for v in 0..n {
+        if state.index[v].is_none() {
+            connect(&mut state, v);
+        }
+    }
+    state.components.sort_by_key(|component| component[0]);
+    state.components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostics(json: &str) -> Vec<Diagnostic> {
+        validate(&serde_json::from_str(json).unwrap())
+    }
+
+    fn codes(diagnostics: &[Diagnostic]) -> Vec<&'static str> {
+        diagnostics.iter().map(|d| d.code).collect()
+    }
+
+    #[test]
+    fn test_valid_idl_has_no_diagnostics() {
+        let result = diagnostics(
+            r#"{
+                "address": "11111111111111111111111111111111",
+                "metadata": { "name": "ok", "version": "0.1.0", "spec": "0.1.0" },
+                "instructions": [
+                    {
+                        "name": "initialize",
+                        "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                        "accounts": [{ "name": "state", "writable": true }],
+                        "args": [{ "name": "config", "type": { "defined": { "name": "Config" } } }]
+                    }
+                ],
+                "accounts": [{ "name": "State", "discriminator": [8, 7, 6, 5, 4, 3, 2, 1] }],
+                "types": [
+                    { "name": "Config", "type": { "kind": "struct", "fields": [{ "name": "limit", "type": "u64" }] } },
+                    {
+                        "name": "State",
+                        "type": {
+                            "kind": "struct",
+                            "fields": [
+                                { "name": "config", "type": { "defined": { "name": "Config" } } },
+                                { "name": "children", "type": { "vec": { "defined": { "name": "State" } } } }
+                            ]
+                        }
+                    }
+                ]
+            }"#,
+        );
+        assert!(result.is_empty(), "{:?}", result);
+    }
+
+    #[test]
+    fn test_dangling_reference_has_path_and_suggestion() {
+        let result = diagnostics(
+            r#"{
+                "instructions": [],
+                "types": [
+                    { "name": "Config", "type": { "kind": "struct", "fields": [] } },
+                    {
+                        "name": "Wrapper",
+                        "type": {
+                            "kind": "struct",
+                            "fields": [{ "name": "inner", "type": { "option": { "defined": { "name": "Confg" } } } }]
+                        }
+                    }
+                ]
+            }"#,
+        );
+        assert_eq!(codes(&result), ["unknown-type"]);
+        assert_eq!(result[0].severity, Severity::Error);
+        assert_eq!(result[0].path, "types[1].type.fields[0].type.option");
+        assert!(result[0].message.contains("did you mean `Config`"));
+    }
+
+    #[test]
+    fn test_unknown_relation() {
+        let result = diagnostics(
+            r#"{
+                "metadata": { "name": "p", "version": "0.1.0", "spec": "0.1.0" },
+                "instructions": [{
+                    "name": "swap",
+                    "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                    "accounts": [
+                        { "name": "pool" },
+                        { "name": "mint", "relations": ["pool", "poool"] }
+                    ],
+                    "args": []
+                }]
+            }"#,
+        );
+        // `pool` has no data to check `mint` against either
+        assert_eq!(codes(&result), ["unchecked-relation", "unknown-relation"]);
+        assert_eq!(result[1].severity, Severity::Warning);
+        assert!(result[1].message.contains("no account `poool`"));
+    }
+
+    #[test]
+    fn test_unchecked_relation() {
+        let legacy = |field: &str| {
+            diagnostics(&format!(
+                r#"{{
+                    "version": "0.1.0",
+                    "name": "p",
+                    "instructions": [{{
+                        "name": "initializeVault",
+                        "accounts": [
+                            {{ "name": "vault", "isMut": true, "isSigner": false, "relations": ["authority"] }},
+                            {{ "name": "authority", "isMut": false, "isSigner": true }}
+                        ],
+                        "args": []
+                    }}],
+                    "accounts": [{{
+                        "name": "Vault",
+                        "type": {{ "kind": "struct", "fields": [{{ "name": "{}", "type": "publicKey" }}] }}
+                    }}]
+                }}"#,
+                field
+            ))
+        };
+        // Legacy Anchor accounts have an implied discriminator
+        assert!(legacy("authority").is_empty());
+
+        let result = legacy("owner");
+        assert_eq!(codes(&result), ["unchecked-relation"]);
+        assert_eq!(result[0].severity, Severity::Warning);
+        assert!(result[0].message.contains("pubkey field `authority`"));
+    }
+
+    #[test]
+    fn test_invalid_deployment() {
+        let result = diagnostics(
+            r#"{
+                "metadata": {
+                    "name": "p", "version": "0.1.0", "spec": "0.1.0",
+                    "deployments": {
+                        "mainnet": "11111111111111111111111111111111",
+                        "devnet": "not-an-address"
+                    }
+                },
+                "instructions": []
+            }"#,
+        );
+        assert_eq!(codes(&result), ["invalid-deployment"]);
+        assert_eq!(result[0].path, "metadata.deployments.devnet");
+    }
+
+    #[test]
+    fn test_deployment_name_clash() {
+        let result = diagnostics(
+            r#"{
+                "address": "11111111111111111111111111111111",
+                "metadata": {
+                    "name": "p", "version": "0.1.0", "spec": "0.1.0",
+                    "deployments": {
+                        "new": "11111111111111111111111111111111",
+                        "decode-instruction": "11111111111111111111111111111111",
+                        "mainnet": "11111111111111111111111111111111"
+                    }
+                },
+                "instructions": [{
+                    "name": "swap",
+                    "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+                    "accounts": [],
+                    "args": []
+                }]
+            }"#,
+        );
+        assert_eq!(
+            codes(&result),
+            ["deployment-name-clash", "deployment-name-clash"]
+        );
+        let paths: Vec<_> = result.iter().map(|d| d.path.as_str()).collect();
+        assert!(paths.contains(&"metadata.deployments.new"));
+        assert!(paths.contains(&"metadata.deployments.decode-instruction"));
+        assert!(result
+            .iter()
+            .any(|d| d.message.contains("`Program::new_deployment()`")));
+    }
+
+    #[test]
+    fn test_duplicates_and_unmatched_accounts() {
+        let result = diagnostics(
+            r#"{
+                "instructions": [
+                    { "name": "init", "discriminator": [1], "accounts": [], "args": [] },
+                    { "name": "init", "discriminator": [2], "accounts": [], "args": [] }
+                ],
+                "accounts": [{ "name": "Missing", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] }],
+                "errors": [
+                    { "code": 6000, "name": "A" },
+                    { "code": 6000, "name": "B" }
+                ],
+                "metadata": { "origin": "custom" }
+            }"#,
+        );
+        assert_eq!(
+            codes(&result),
+            [
+                "duplicate-name",
+                "duplicate-error-code",
+                "account-without-type"
+            ]
+        );
+        assert_eq!(result[0].path, "instructions[1].name");
+        assert_eq!(result[1].path, "errors[1].code");
+        assert_eq!(result[2].path, "accounts[0]");
+    }
+
+    #[test]
+    fn test_recursive_types() {
+        let json = r#"{
+            "instructions": [],
+            "types": [
+                { "name": "A", "type": { "kind": "struct", "fields": [{ "name": "b", "type": { "defined": { "name": "B" } } }] } },
+                { "name": "B", "type": { "kind": "struct", "fields": [{ "name": "a", "type": { "option": { "defined": { "name": "A" } } } }] } },
+                { "name": "Node", "serialization": "bytemuck", "type": { "kind": "struct", "fields": [{ "name": "next", "type": { "array": [{ "defined": { "name": "Node" } }, 2] } }] } },
+                { "name": "List", "type": { "kind": "struct", "fields": [{ "name": "items", "type": { "vec": { "defined": { "name": "List" } } } }] } }
+            ]
+        }"#;
+        let idl: Idl = serde_json::from_str(json).unwrap();
+        assert_eq!(recursive_type_groups(&idl), [vec!["A", "B"], vec!["Node"]]);
+
+        // Only the bytemuck cycle can't be fixed by boxing
+        let result = validate(&idl);
+        assert_eq!(codes(&result), ["recursive-type"]);
+        assert_eq!(result[0].path, "types[2]");
+    }
+
+    #[test]
+    fn test_discriminator_checks() {
+        let result = diagnostics(
+            r#"{
+                "instructions": [
+                    { "name": "a", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8], "accounts": [], "args": [] },
+                    { "name": "b", "discriminator": [1, 2, 3, 4], "accounts": [], "args": [] }
+                ],
+                "accounts": [{ "name": "State", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] }],
+                "events": [
+                    { "name": "Created", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] },
+                    { "name": "Short", "discriminator": [1, 2] }
+                ],
+                "types": [{ "name": "State", "type": { "kind": "struct", "fields": [] } }]
+            }"#,
+        );
+        assert_eq!(
+            codes(&result),
+            [
+                "discriminator-length",
+                "discriminator-collision",
+                "discriminator-collision",
+                "invalid-discriminator"
+            ]
+        );
+        assert_eq!(result[1].severity, Severity::Error);
+        assert_eq!(result[1].path, "instructions[1].discriminator");
+        // account/event clash is only a warning
+        assert_eq!(result[2].severity, Severity::Warning);
+        assert_eq!(result[2].path, "events[0].discriminator");
+        assert_eq!(result[3].path, "events[1].discriminator");
+    }
+
+    #[test]
+    fn test_bytemuck_layout() {
+        let result = diagnostics(
+            r#"{
+                "instructions": [],
+                "types": [
+                    {
+                        "name": "Padded",
+                        "serialization": "bytemuck",
+                        "repr": { "kind": "c" },
+                        "type": { "kind": "struct", "fields": [
+                            { "name": "flag", "type": "u8" },
+                            { "name": "amount", "type": "u64" },
+                            { "name": "inner", "type": { "defined": { "name": "Inner" } } }
+                        ] }
+                    },
+                    {
+                        "name": "Packed",
+                        "serialization": "bytemuck",
+                        "repr": { "kind": "c", "packed": true },
+                        "type": { "kind": "struct", "fields": [
+                            { "name": "flag", "type": "u8" },
+                            { "name": "amount", "type": "u64" }
+                        ] }
+                    },
+                    {
+                        "name": "Dynamic",
+                        "serialization": "bytemuckunsafe",
+                        "type": { "kind": "struct", "fields": [{ "name": "items", "type": { "vec": "u8" } }] }
+                    },
+                    { "name": "Inner", "type": { "kind": "struct", "fields": [] } }
+                ]
+            }"#,
+        );
+        assert_eq!(
+            codes(&result),
+            ["bytemuck-padding", "bytemuck-layout", "bytemuck-layout"]
+        );
+        assert_eq!(result[0].path, "types[0].type.fields[1]");
+        assert!(result[0].message.contains("7 byte(s) of padding"));
+        assert_eq!(result[1].path, "types[0].type.fields[2].type");
+        assert!(result[1].message.contains("not a bytemuck type"));
+        assert_eq!(result[2].path, "types[2].type.fields[0].type");
+    }
+
+    #[test]
+    fn test_undeclared_generics() {
+        let result = diagnostics(
+            r#"{
+                "instructions": [],
+                "types": [
+                    {
+                        "name": "Slot",
+                        "generics": [{ "kind": "type", "name": "T" }],
+                        "type": { "kind": "struct", "fields": [
+                            { "name": "value", "type": { "generic": "T" } },
+                            { "name": "other", "type": { "generic": "U" } }
+                        ] }
+                    },
+                    {
+                        "name": "User",
+                        "type": { "kind": "struct", "fields": [{ "name": "slot", "type": { "defined": { "name": "Slot" } } }] }
+                    }
+                ]
+            }"#,
+        );
+        assert_eq!(codes(&result), ["unknown-generic", "generic-arity"]);
+        assert_eq!(result[0].path, "types[0].type.fields[1].type");
+        assert_eq!(result[1].path, "types[1].type.fields[0].type");
+    }
+}
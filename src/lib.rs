@@ -1,4 +1,6 @@
 // Public modules for benchmarking and testing
+pub mod codama;
 pub mod codegen;
+pub mod convert;
 pub mod idl;
 pub mod r#override;
@@ -1,18 +1,22 @@
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use heck::{ToPascalCase, ToSnakeCase};
 use std::fs;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
-use solana_idl_codegen::{codegen, idl, r#override};
+use solana_idl_codegen::{codama, codegen, convert, idl, r#override};
 
 #[derive(Parser)]
 #[command(name = "solana-idl-codegen")]
 #[command(about = "Generate Rust code bindings from Solana IDL files", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to the IDL JSON file
-    #[arg(short, long, value_name = "FILE")]
-    input: PathBuf,
+    #[arg(short, long, value_name = "FILE", required = true)]
+    input: Option<PathBuf>,
 
     /// Output directory for generated code
     #[arg(short, long, value_name = "DIR", default_value = "generated")]
@@ -25,40 +29,200 @@ struct Cli {
     /// Path to override file (optional)
     #[arg(long, value_name = "FILE")]
     override_file: Option<PathBuf>,
+
+    /// Depend on the runtime crate at this path (relative to the generated crate)
+    /// instead of the `runtime/` directory of the checkout this tool was built from
+    #[arg(long, value_name = "DIR")]
+    runtime_path: Option<PathBuf>,
+
+    /// Implement anchor-lang traits so generated types work with `Account<'info, T>`
+    /// and `CpiContext` (targets the Solana 2 / anchor-lang 0.31+ line)
+    #[arg(long)]
+    anchor: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Upgrade a legacy IDL to the Anchor 0.30+ spec
+    Convert {
+        /// Path to the IDL JSON file
+        #[arg(short, long, value_name = "FILE")]
+        input: PathBuf,
+
+        /// Where to write the converted IDL (defaults to stdout)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Override file to apply before converting (optional)
+        #[arg(long, value_name = "FILE")]
+        override_file: Option<PathBuf>,
+    },
+    /// Check an IDL for semantic problems without generating code
+    Lint {
+        /// Path to the IDL JSON file
+        #[arg(short, long, value_name = "FILE")]
+        input: PathBuf,
+
+        /// Override file to apply before checking (optional)
+        #[arg(long, value_name = "FILE")]
+        override_file: Option<PathBuf>,
+
+        /// Print diagnostics as a JSON array
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    // Read and parse IDL file
-    let idl_content = fs::read_to_string(&cli.input)
-        .context(format!("Failed to read IDL file: {:?}", cli.input))?;
+    match cli.command {
+        Some(Command::Convert {
+            input,
+            output,
+            override_file,
+        }) => return convert_idl(&input, output.as_deref(), override_file.as_deref()),
+        Some(Command::Lint {
+            input,
+            override_file,
+            json,
+        }) => return lint_idl(&input, override_file.as_deref(), json),
+        None => {}
+    }
+
+    let input = cli.input.context("--input is required")?;
+    generate(
+        &input,
+        &cli.output,
+        &cli.module,
+        cli.override_file.as_deref(),
+        cli.runtime_path.as_deref(),
+        &codegen::GenerateOptions { anchor: cli.anchor },
+    )
+}
 
-    let mut idl: idl::Idl =
+/// Read an IDL file, lowering Codama documents into the Anchor model
+fn load_idl(path: &Path) -> Result<(idl::Idl, bool)> {
+    let idl_content =
+        fs::read_to_string(path).context(format!("Failed to read IDL file: {:?}", path))?;
+
+    let idl_json: serde_json::Value =
         serde_json::from_str(&idl_content).context("Failed to parse IDL JSON")?;
+    if codama::is_root_node(&idl_json) {
+        let idl = codama::from_str(&idl_content).context("Failed to convert Codama IDL")?;
+        Ok((idl, true))
+    } else {
+        let idl = idl::from_str(&idl_content).context("Failed to parse IDL")?;
+        Ok((idl, false))
+    }
+}
+
+/// Load, validate and apply an override file
+fn apply_override_file(
+    idl: idl::Idl,
+    override_path: &Path,
+) -> Result<(idl::Idl, Vec<r#override::AppliedOverride>)> {
+    // T024: Load override file
+    let override_file =
+        r#override::load_override_file(override_path).context("Failed to load override file")?;
+
+    // T025: Validate override file
+    r#override::validate_override_file(&override_file, &idl)
+        .context("Override file validation failed")?;
+
+    // T026: Apply overrides to IDL
+    r#override::apply_overrides(idl, &override_file).context("Failed to apply overrides to IDL")
+}
+
+/// `convert` subcommand: write the IDL back out in the canonical new spec
+fn convert_idl(input: &Path, output: Option<&Path>, override_file: Option<&Path>) -> Result<()> {
+    let (mut idl, is_codama) = load_idl(input)?;
+
+    if let Some(override_path) = override_file {
+        let (modified_idl, applied_overrides) = apply_override_file(idl, override_path)?;
+        idl = modified_idl;
+        eprintln!("Applied {} override(s)", applied_overrides.len());
+    }
+
+    let upgraded = convert::upgrade(&idl, is_codama)
+        .map_err(anyhow::Error::msg)
+        .context("Failed to convert IDL")?;
+    let json = serde_json::to_string_pretty(&upgraded).context("Failed to serialize IDL")?;
+
+    match output {
+        Some(path) => {
+            fs::write(path, json + "\n")
+                .context(format!("Failed to write converted IDL: {:?}", path))?;
+            eprintln!("✓ Wrote {} IDL to {:?}", upgraded.get_name(), path);
+        }
+        None => println!("{}", json),
+    }
+
+    Ok(())
+}
+
+/// `lint` subcommand: report diagnostics, failing when any is an error
+fn lint_idl(input: &Path, override_file: Option<&Path>, json: bool) -> Result<()> {
+    let (mut idl, _) = load_idl(input)?;
+    if let Some(override_path) = override_file {
+        idl = apply_override_file(idl, override_path)?.0;
+    }
+
+    let diagnostics = idl::validate(&idl);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+    } else {
+        for diagnostic in &diagnostics {
+            println!("{}", diagnostic);
+        }
+    }
+    report_diagnostics(&diagnostics, json)
+}
+
+/// Print the error/warning totals and fail if there were any errors
+fn report_diagnostics(diagnostics: &[idl::Diagnostic], quiet: bool) -> Result<()> {
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == idl::Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+    if !quiet {
+        eprintln!("{} error(s), {} warning(s)", errors, warnings);
+    }
+    if errors > 0 {
+        anyhow::bail!("IDL validation failed with {} error(s)", errors);
+    }
+    Ok(())
+}
+
+fn generate(
+    input: &Path,
+    output: &Path,
+    module: &str,
+    override_file: Option<&Path>,
+    runtime_path: Option<&Path>,
+    options: &codegen::GenerateOptions,
+) -> Result<()> {
+    if runtime_path.is_none() {
+        check_default_runtime()?;
+    }
+
+    // Read and parse IDL file
+    let (mut idl, is_codama) = load_idl(input)?;
+    if is_codama {
+        println!("Detected Codama IDL");
+    }
 
     // T027: Discover and apply override file if present
     // Use module name for override discovery (more reliable than IDL filename)
-    let override_discovery =
-        r#override::discover_override_file(&cli.input, &cli.module, cli.override_file.as_deref())
-            .context("Failed to discover override file")?;
+    let override_discovery = r#override::discover_override_file(input, module, override_file)
+        .context("Failed to discover override file")?;
 
     match override_discovery {
         r#override::OverrideDiscovery::Found(override_path) => {
             println!("Found override file: {}", override_path.display());
 
-            // T024: Load override file
-            let override_file = r#override::load_override_file(&override_path)
-                .context("Failed to load override file")?;
-
-            // T025: Validate override file
-            r#override::validate_override_file(&override_file, &idl)
-                .context("Override file validation failed")?;
-
-            // T026: Apply overrides to IDL
-            let (modified_idl, applied_overrides) =
-                r#override::apply_overrides(idl, &override_file)
-                    .context("Failed to apply overrides to IDL")?;
+            let (modified_idl, applied_overrides) = apply_override_file(idl, &override_path)?;
             idl = modified_idl;
 
             // T028: Log applied overrides
@@ -73,6 +237,14 @@ fn main() -> Result<()> {
                                 applied.override_value
                             );
                         }
+                        r#override::OverrideType::Deployment => {
+                            println!(
+                                "  Deployment '{}': {} → {}",
+                                applied.entity_name.as_deref().unwrap_or("unknown"),
+                                applied.original_value.as_deref().unwrap_or("(none)"),
+                                applied.override_value
+                            );
+                        }
                         r#override::OverrideType::AccountDiscriminator => {
                             println!(
                                 "  Account '{}': discriminator overridden",
@@ -114,8 +286,19 @@ fn main() -> Result<()> {
         }
     }
 
+    let diagnostics = idl::validate(&idl);
+    for diagnostic in &diagnostics {
+        eprintln!("{}", diagnostic);
+    }
+    if !diagnostics.is_empty() {
+        report_diagnostics(&diagnostics, false)?;
+    }
+
     println!("Successfully parsed IDL for program: {}", idl.get_name());
     println!("Version: {}", idl.get_version());
+    if idl.is_shank() {
+        println!("Format: Shank");
+    }
     println!("Instructions: {}", idl.instructions.len());
     println!(
         "Accounts: {}",
@@ -127,10 +310,16 @@ fn main() -> Result<()> {
     );
 
     // Generate code
-    let generated_code = codegen::generate(&idl, &cli.module)?;
+    let generated_code = codegen::generate_with_options(&idl, module, options)?;
+    for (original, renamed) in &generated_code.renamed_types {
+        println!(
+            "  ⚠ Type '{}' renamed to '{}' (clashes with a generated item)",
+            original, renamed
+        );
+    }
 
     // Create crate structure
-    let crate_dir = cli.output.join(&cli.module);
+    let crate_dir = output.join(module);
     let src_dir = crate_dir.join("src");
 
     fs::create_dir_all(&src_dir).context(format!(
@@ -199,13 +388,16 @@ fn main() -> Result<()> {
     }
 
     // Generate Cargo.toml
-    let cargo_toml = generate_cargo_toml(&cli.module, &idl);
+    let runtime_path = runtime_path
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| default_runtime_path(&crate_dir));
+    let cargo_toml = generate_cargo_toml(module, &idl, &runtime_path, options.anchor);
     let cargo_toml_file = crate_dir.join("Cargo.toml");
     fs::write(&cargo_toml_file, cargo_toml)
         .context(format!("Failed to write Cargo.toml: {:?}", cargo_toml_file))?;
 
     // Generate README.md
-    let readme = generate_readme(&cli.module, &idl);
+    let readme = generate_readme(module, &idl);
     let readme_file = crate_dir.join("README.md");
     fs::write(&readme_file, readme)
         .context(format!("Failed to write README.md: {:?}", readme_file))?;
@@ -223,7 +415,7 @@ fn main() -> Result<()> {
         examples_dir
     ))?;
 
-    generate_examples(&examples_dir, &cli.module, &idl)?;
+    generate_examples(&examples_dir, module, &idl)?;
 
     // Format generated code with rustfmt
     let mut rustfmt_files = Vec::new();
@@ -279,7 +471,94 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn generate_cargo_toml(module_name: &str, idl: &idl::Idl) -> String {
+/// `runtime/` of the checkout this tool was built from
+///
+/// `solana-idl-codegen-runtime` is not published to crates.io, so without
+/// `--runtime-path` generated crates depend on this directory. It is fixed at build
+/// time, so an installed or copied binary may point at a checkout that is gone.
+const RUNTIME_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/runtime");
+
+/// Fail unless [`RUNTIME_DIR`] still holds the runtime crate
+fn check_default_runtime() -> Result<()> {
+    if !Path::new(RUNTIME_DIR).join("Cargo.toml").is_file() {
+        anyhow::bail!(
+            "No runtime crate at {} (the checkout solana-idl-codegen was built from); \
+             pass --runtime-path with the path of solana-idl-codegen-runtime",
+            RUNTIME_DIR
+        );
+    }
+    Ok(())
+}
+
+/// [`RUNTIME_DIR`] as seen from `crate_dir`
+///
+/// The path is relative when the generated crate and the checkout share a parent
+/// directory, so the pair can move together.
+fn default_runtime_path(crate_dir: &Path) -> PathBuf {
+    let runtime = Path::new(RUNTIME_DIR);
+    let (Ok(from), Ok(to)) = (crate_dir.canonicalize(), runtime.canonicalize()) else {
+        return runtime.to_path_buf();
+    };
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    // Only the root in common: an absolute path is clearer than a chain of `..`
+    if !from
+        .components()
+        .take(common)
+        .any(|c| matches!(c, Component::Normal(_)))
+    {
+        return runtime.to_path_buf();
+    }
+    let mut relative = PathBuf::new();
+    for _ in from.components().skip(common) {
+        relative.push("..");
+    }
+    relative.extend(to.components().skip(common));
+    relative
+}
+
+/// `s` as a TOML basic string, quoted and escaped
+fn toml_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// anchor-lang releases `--anchor` output supports; all are on the Solana 2 line
+const ANCHOR_LANG_VERSION: &str = ">=0.31, <0.33";
+
+fn generate_cargo_toml(
+    module_name: &str,
+    idl: &idl::Idl,
+    runtime_path: &Path,
+    anchor: bool,
+) -> String {
+    let runtime_source = format!("path = {}", toml_string(&runtime_path.to_string_lossy()));
+    // Solana types come from the runtime's `solana_program` re-export, which is
+    // anchor-lang's in anchor mode
+    let (runtime, anchor_lang) = if anchor {
+        (
+            format!(
+                "{{ {}, default-features = false, features = [\"anchor\"] }}",
+                runtime_source
+            ),
+            format!("anchor-lang = \"{}\"\n", ANCHOR_LANG_VERSION),
+        )
+    } else {
+        (format!("{{ {} }}", runtime_source), String::new())
+    };
     format!(
         r#"[package]
 name = "{}"
@@ -291,8 +570,8 @@ license = "MIT OR Apache-2.0"
 [dependencies]
 borsh = {{ version = "^1.5", features = ["derive"] }}
 bytemuck = {{ version = "^1.14", features = ["derive"] }}
-solana-program = "3.0"
-thiserror = "^2.0"
+solana-idl-codegen-runtime = {}
+{}thiserror = "^2.0"
 num-derive = "^0.4"
 num-traits = "^0.2"
 
@@ -301,16 +580,26 @@ version = "^1.0"
 features = ["derive"]
 optional = true
 
+[dependencies.base64]
+version = "^0.22"
+
 [features]
 default = ["serde"]
 serde = ["dep:serde"]
+# Render [u8; N] fields as hex or base64 strings in serde output
+serde-bytes-hex = ["serde"]
+serde-bytes-base64 = ["serde"]
+# Render u64/i64/u128/i128 fields as decimal strings in serde output
+serde-int-string = ["serde"]
 
 [lib]
 crate-type = ["lib"]
 "#,
         module_name,
         idl.get_version(),
-        idl.get_name()
+        idl.get_name(),
+        runtime,
+        anchor_lang
     )
 }
 
@@ -380,7 +669,7 @@ fn generate_examples(examples_dir: &Path, module_name: &str, idl: &idl::Idl) ->
 
         // Generate keys struct initialization with all fields (commented out)
         let mut keys_fields = String::new();
-        for account in &first_ix.accounts {
+        for account in &first_ix.flat_accounts() {
             let field_name = account.name.to_snake_case();
             keys_fields.push_str(&format!(
                 "    //     {}: solana_program::pubkey::Pubkey::default(), // TODO: Fill in actual pubkey\n",
@@ -471,7 +760,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {{
     // Example: Parse and validate {1} account
     // In a real application, you would get account_info from a transaction or RPC call
     // use {0}::*;
-    // use solana_program::account_info::AccountInfo;
+    // use {0}::solana_program::account_info::AccountInfo;
     // let account_info: &AccountInfo = /* ... */;
     // let account = {1}::try_from_account_info(account_info)?;
     // println!("Parsed account: {{:?}}", account);
@@ -582,3 +871,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toml_string_escapes_paths() {
+        assert_eq!(toml_string("../../runtime"), r#""../../runtime""#);
+        assert_eq!(
+            toml_string(r"C:\Users\dev\runtime"),
+            r#""C:\\Users\\dev\\runtime""#
+        );
+        assert_eq!(toml_string("say \"hi\"\n"), r#""say \"hi\"\u000A""#);
+    }
+
+    #[test]
+    fn test_default_runtime_path_is_relative_inside_the_checkout() {
+        let src_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        assert_eq!(default_runtime_path(&src_dir), Path::new("../runtime"));
+    }
+}
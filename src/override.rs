@@ -63,6 +63,11 @@ pub struct OverrideFile {
     /// Instruction discriminator overrides (instruction name → discriminator)
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub instructions: HashMap<String, DiscriminatorOverride>,
+
+    /// Per-cluster program addresses (cluster name → base58-encoded Pubkey),
+    /// merged into `metadata.deployments`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub deployments: HashMap<String, String>,
 }
 
 /// Represents an 8-byte discriminator override for an account, event, or instruction
@@ -133,6 +138,7 @@ pub struct AppliedOverride {
 #[derive(Debug, Clone)]
 pub enum OverrideType {
     ProgramAddress,
+    Deployment,
     AccountDiscriminator,
     EventDiscriminator,
     InstructionDiscriminator,
@@ -330,6 +336,30 @@ fn validate_entity_names(
     }
 }
 
+/// Validate a base58 program address
+///
+/// # Returns
+/// - `Ok(())` if the address decodes to a non-default 32-byte Pubkey
+/// - `Err(ValidationError::InvalidProgramAddress)` if it isn't valid base58 or isn't 32 bytes
+/// - `Err(ValidationError::SystemDefaultPubkey)` if it is the system default pubkey
+fn validate_program_address(address: &str) -> Result<(), ValidationError> {
+    // Solana Pubkeys are 32 bytes when decoded from base58
+    match bs58::decode(address).into_vec() {
+        Ok(decoded) if decoded.len() == 32 => {
+            // Check for system default pubkey (all 1s in base58 = 32 bytes of 0x00)
+            if decoded == vec![0u8; 32] {
+                return Err(ValidationError::SystemDefaultPubkey {
+                    address: address.to_string(),
+                });
+            }
+            Ok(())
+        }
+        _ => Err(ValidationError::InvalidProgramAddress {
+            address: address.to_string(),
+        }),
+    }
+}
+
 /// Validate override file structure and values
 ///
 /// # Returns
@@ -340,6 +370,7 @@ fn validate_entity_names(
 /// - At least one field must be non-empty
 /// - Program address must be valid base58 Pubkey (if present)
 /// - Program address cannot be system default (11111...1111)
+/// - Deployment addresses follow the same rules as the program address
 /// - Discriminators must be exactly 8 bytes (enforced by type)
 /// - Discriminators cannot be all zeros
 /// - Entity names MUST exist in IDL (errors for unknown names)
@@ -352,37 +383,22 @@ pub fn validate_override_file(
         && override_file.accounts.is_empty()
         && override_file.events.is_empty()
         && override_file.instructions.is_empty()
+        && override_file.deployments.is_empty()
     {
         return Err(ValidationError::EmptyOverrideFile);
     }
 
     // Validate program address if present
     if let Some(ref address) = override_file.address {
-        // Validate base58 format by attempting to decode
-        // Solana Pubkeys are 32 bytes when decoded from base58
-        match bs58::decode(address).into_vec() {
-            Ok(decoded) => {
-                if decoded.len() != 32 {
-                    return Err(ValidationError::InvalidProgramAddress {
-                        address: address.clone(),
-                    });
-                }
-
-                // Check for system default pubkey (all 1s in base58 = 32 bytes of 0x00)
-                if decoded == vec![0u8; 32] {
-                    return Err(ValidationError::SystemDefaultPubkey {
-                        address: address.clone(),
-                    });
-                }
-            }
-            Err(_) => {
-                return Err(ValidationError::InvalidProgramAddress {
-                    address: address.clone(),
-                });
-            }
-        }
+        validate_program_address(address)?;
     }
 
+    // Deployment addresses follow the same rules as the program address
+    override_file
+        .deployments
+        .values()
+        .try_for_each(|address| validate_program_address(address))?;
+
     // Validate all discriminators in a single pass
     [
         (&override_file.accounts, "account"),
@@ -437,6 +453,7 @@ pub fn validate_override_file(
 ///
 /// # Behavior
 /// - Applies program address override (if present)
+/// - Merges deployment addresses into `metadata.deployments`
 /// - Applies account discriminator overrides (User Story 3)
 /// - Applies event discriminator overrides (User Story 4)
 /// - Applies instruction discriminator overrides (User Story 5)
@@ -447,6 +464,7 @@ pub fn apply_overrides(
 ) -> Result<(crate::idl::Idl, Vec<AppliedOverride>)> {
     // Pre-allocate vector capacity to avoid reallocations
     let capacity = override_file.address.iter().count()
+        + override_file.deployments.len()
         + override_file.accounts.len()
         + override_file.events.len()
         + override_file.instructions.len();
@@ -467,6 +485,26 @@ pub fn apply_overrides(
         });
     }
 
+    // Merge per-cluster deployment addresses, sorted so the log order is stable
+    if !override_file.deployments.is_empty() {
+        let deployments = idl
+            .metadata
+            .get_or_insert_with(Default::default)
+            .deployments
+            .get_or_insert_with(Default::default);
+        let mut clusters: Vec<_> = override_file.deployments.iter().collect();
+        clusters.sort();
+        for (cluster, address) in clusters {
+            let original_value = deployments.insert(cluster.clone(), address.clone());
+            applied.push(AppliedOverride {
+                override_type: OverrideType::Deployment,
+                entity_name: Some(cluster.clone()),
+                original_value,
+                override_value: address.clone(),
+            });
+        }
+    }
+
     // T057 [US3]: Apply account discriminator overrides
     if let Some(ref mut accounts) = idl.accounts {
         for account in accounts.iter_mut() {
@@ -660,6 +698,7 @@ mod tests {
             accounts: HashMap::new(),
             events: HashMap::new(),
             instructions: HashMap::new(),
+            deployments: HashMap::new(),
         };
 
         // Create minimal IDL for validation
@@ -688,6 +727,7 @@ mod tests {
             accounts: HashMap::new(),
             events: HashMap::new(),
             instructions: HashMap::new(),
+            deployments: HashMap::new(),
         };
 
         let idl = crate::idl::Idl {
@@ -718,6 +758,7 @@ mod tests {
             accounts: HashMap::new(),
             events: HashMap::new(),
             instructions: HashMap::new(),
+            deployments: HashMap::new(),
         };
 
         let idl = crate::idl::Idl {
@@ -752,6 +793,7 @@ mod tests {
             accounts: HashMap::new(),
             events: HashMap::new(),
             instructions: HashMap::new(),
+            deployments: HashMap::new(),
         };
 
         // IDL with different program address
@@ -799,6 +841,7 @@ mod tests {
             accounts: HashMap::new(),
             events: HashMap::new(),
             instructions: HashMap::new(),
+            deployments: HashMap::new(),
         };
 
         // IDL with same program address
@@ -835,6 +878,7 @@ mod tests {
             accounts: HashMap::new(),
             events: HashMap::new(),
             instructions: HashMap::new(),
+            deployments: HashMap::new(),
         };
 
         let original_address = "11111111111111111111111111111112".to_string();
@@ -864,6 +908,78 @@ mod tests {
         // The warning format is: "⚠ Program address: {original} → {new}"
     }
 
+    /// Deployment overrides are merged into metadata.deployments
+    #[test]
+    fn test_deployment_overrides() {
+        let override_file: OverrideFile = serde_json::from_str(
+            r#"{
+                "deployments": {
+                    "devnet": "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8",
+                    "mainnet": "11111111111111111111111111111112"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mut deployments = std::collections::BTreeMap::new();
+        deployments.insert("devnet".to_string(), "old".to_string());
+        let idl = crate::idl::Idl {
+            address: None,
+            name: Some("test".to_string()),
+            version: Some("1.0.0".to_string()),
+            instructions: vec![],
+            accounts: None,
+            types: None,
+            events: None,
+            errors: None,
+            constants: None,
+            metadata: Some(crate::idl::Metadata {
+                deployments: Some(deployments),
+                ..Default::default()
+            }),
+        };
+
+        assert!(validate_override_file(&override_file, &idl).is_ok());
+
+        let (modified_idl, applied) = apply_overrides(idl, &override_file).unwrap();
+        assert_eq!(applied.len(), 2);
+        assert!(matches!(applied[0].override_type, OverrideType::Deployment));
+        assert_eq!(applied[0].entity_name.as_deref(), Some("devnet"));
+        assert_eq!(applied[0].original_value.as_deref(), Some("old"));
+        assert_eq!(applied[1].original_value, None);
+
+        let deployments = modified_idl.metadata.unwrap().deployments.unwrap();
+        assert_eq!(
+            deployments["devnet"],
+            "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8"
+        );
+        assert_eq!(deployments["mainnet"], "11111111111111111111111111111112");
+    }
+
+    /// Deployment addresses are validated like the program address
+    #[test]
+    fn test_invalid_deployment_address() {
+        let override_file: OverrideFile =
+            serde_json::from_str(r#"{ "deployments": { "devnet": "not-a-valid-pubkey" } }"#)
+                .unwrap();
+
+        let idl = crate::idl::Idl {
+            address: None,
+            name: Some("test".to_string()),
+            version: Some("1.0.0".to_string()),
+            instructions: vec![],
+            accounts: None,
+            types: None,
+            events: None,
+            errors: None,
+            constants: None,
+            metadata: None,
+        };
+
+        let err = validate_override_file(&override_file, &idl).unwrap_err();
+        assert!(matches!(err, ValidationError::InvalidProgramAddress { .. }));
+    }
+
     // ====================
     // User Story 3 Tests: Override Incorrect Account Discriminators
     // ====================
@@ -907,6 +1023,7 @@ mod tests {
             },
             events: HashMap::new(),
             instructions: HashMap::new(),
+            deployments: HashMap::new(),
         };
 
         let idl = crate::idl::Idl {
@@ -947,6 +1064,7 @@ mod tests {
             },
             events: HashMap::new(),
             instructions: HashMap::new(),
+            deployments: HashMap::new(),
         };
 
         // For now, just verify the structure is correct
@@ -971,6 +1089,7 @@ mod tests {
             },
             events: HashMap::new(),
             instructions: HashMap::new(),
+            deployments: HashMap::new(),
         };
 
         // IDL with no accounts defined
@@ -1035,6 +1154,7 @@ mod tests {
             .into_iter()
             .collect(),
             instructions: HashMap::new(),
+            deployments: HashMap::new(),
         };
 
         let idl = crate::idl::Idl {
@@ -1049,11 +1169,13 @@ mod tests {
                     name: "TradeEvent".to_string(),
                     discriminator: Some(vec![255, 255, 255, 255, 255, 255, 255, 255]),
                     fields: None,
+                    docs: None,
                 },
                 crate::idl::Event {
                     name: "SwapEvent".to_string(),
                     discriminator: Some(vec![254, 254, 254, 254, 254, 254, 254, 254]),
                     fields: None,
+                    docs: None,
                 },
             ]),
             errors: None,
@@ -1107,6 +1229,7 @@ mod tests {
             .into_iter()
             .collect(),
             instructions: HashMap::new(),
+            deployments: HashMap::new(),
         };
 
         let idl = crate::idl::Idl {
@@ -1120,6 +1243,7 @@ mod tests {
                 name: "TradeEvent".to_string(),
                 discriminator: Some(vec![255, 255, 255, 255, 255, 255, 255, 255]),
                 fields: None,
+                docs: None,
             }]),
             errors: None,
             constants: None,
@@ -1177,6 +1301,7 @@ mod tests {
             .into_iter()
             .collect(),
             instructions: HashMap::new(),
+            deployments: HashMap::new(),
         };
 
         let idl = crate::idl::Idl {
@@ -1191,16 +1316,19 @@ mod tests {
                     name: "Event1".to_string(),
                     discriminator: Some(vec![255, 255, 255, 255, 255, 255, 255, 255]),
                     fields: None,
+                    docs: None,
                 },
                 crate::idl::Event {
                     name: "Event2".to_string(),
                     discriminator: Some(vec![254, 254, 254, 254, 254, 254, 254, 254]),
                     fields: None,
+                    docs: None,
                 },
                 crate::idl::Event {
                     name: "Event3".to_string(),
                     discriminator: Some(vec![253, 253, 253, 253, 253, 253, 253, 253]),
                     fields: None,
+                    docs: None,
                 },
             ]),
             errors: None,
@@ -1263,6 +1391,7 @@ mod tests {
             ]
             .into_iter()
             .collect(),
+            deployments: HashMap::new(),
         };
 
         let idl = crate::idl::Idl {
@@ -1276,6 +1405,8 @@ mod tests {
                     accounts: vec![],
                     args: vec![],
                     docs: None,
+                    discriminant: None,
+                    returns: None,
                 },
                 crate::idl::Instruction {
                     name: "Trade".to_string(),
@@ -1283,6 +1414,8 @@ mod tests {
                     accounts: vec![],
                     args: vec![],
                     docs: None,
+                    discriminant: None,
+                    returns: None,
                 },
             ],
             accounts: None,
@@ -1346,6 +1479,7 @@ mod tests {
             ]
             .into_iter()
             .collect(),
+            deployments: HashMap::new(),
         };
 
         let idl = crate::idl::Idl {
@@ -1358,6 +1492,8 @@ mod tests {
                 accounts: vec![],
                 args: vec![],
                 docs: None,
+                discriminant: None,
+                returns: None,
             }],
             accounts: None,
             types: None,
@@ -1460,6 +1596,7 @@ mod tests {
             accounts: HashMap::new(),
             events: HashMap::new(),
             instructions: HashMap::new(),
+            deployments: HashMap::new(),
         };
 
         let idl = crate::idl::Idl {
@@ -1567,6 +1704,7 @@ mod tests {
             .collect(),
             events: HashMap::new(),
             instructions: HashMap::new(),
+            deployments: HashMap::new(),
         };
 
         // Validation should fail with UnknownEntity error containing suggestion
@@ -0,0 +1,235 @@
+//! Compile tests for freshly generated crates
+//!
+//! Each test generates a crate from a small inline IDL into a temporary directory,
+//! which depends on this checkout's runtime crate by default, and runs `cargo check`
+//! on it. Lints
+//! that only fire for some IDL shapes are denied so the generator can't regress on them.
+//! Tests that exercise generated behavior add a test file to the crate and run it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+
+/// Lints denied in generated crates
+const DENIED_LINTS: &str = "-D unused_mut -D unused_variables";
+
+/// Shared target directory, so dependencies are only built once
+fn target_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("generated_crates")
+}
+
+/// Run `cargo <args>` on the generated crate in `crate_dir`, failing with its output
+fn cargo(crate_dir: &Path, args: &[&str]) {
+    let output = Command::new(env!("CARGO"))
+        .args(args)
+        .arg("--manifest-path")
+        .arg(crate_dir.join("Cargo.toml"))
+        .env("CARGO_TARGET_DIR", target_dir())
+        .env("RUSTFLAGS", DENIED_LINTS)
+        .output()
+        .expect("Failed to run cargo");
+    assert!(
+        output.status.success(),
+        "cargo {} failed for {}:\n{}\n{}",
+        args.join(" "),
+        crate_dir.display(),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Generate `module` from `idl` with the extra generator `flags` and `cargo check` it,
+/// returning the crate directory
+fn generate_and_check(temp_dir: &TempDir, idl: &str, module: &str, flags: &[&str]) -> PathBuf {
+    let idl_path = temp_dir.path().join(format!("{}.json", module));
+    fs::write(&idl_path, idl).expect("Failed to write test IDL");
+
+    let output_dir = temp_dir.path().join("generated");
+    let status = Command::new(env!("CARGO_BIN_EXE_solana-idl-codegen"))
+        .args([
+            "--input",
+            idl_path.to_str().unwrap(),
+            "--output",
+            output_dir.to_str().unwrap(),
+            "--module",
+            module,
+        ])
+        .args(flags)
+        .status()
+        .expect("Failed to execute codegen");
+    assert!(status.success(), "Code generation failed");
+
+    let crate_dir = output_dir.join(module);
+    cargo(&crate_dir, &["check"]);
+    crate_dir
+}
+
+/// Add `source` to the generated crate as an integration test and run it
+fn run_generated_test(crate_dir: &Path, source: &str) {
+    let tests_dir = crate_dir.join("tests");
+    fs::create_dir_all(&tests_dir).expect("Failed to create tests directory");
+    fs::write(tests_dir.join("generated_behavior.rs"), source).expect("Failed to write test");
+    cargo(crate_dir, &["test", "--test", "generated_behavior"]);
+}
+
+#[test]
+fn test_plain_accounts_resolver_compiles() {
+    // No fixed addresses, PDAs, relations or optional accounts: the keys resolver
+    // has nothing to derive
+    let idl = r#"{
+        "address": "11111111111111111111111111111111",
+        "metadata": { "name": "plain", "version": "0.1.0", "spec": "0.1.0" },
+        "instructions": [{
+            "name": "transfer",
+            "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+            "accounts": [
+                { "name": "from", "writable": true, "signer": true },
+                { "name": "to", "writable": true }
+            ],
+            "args": [{ "name": "amount", "type": "u64" }]
+        }]
+    }"#;
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let crate_dir = generate_and_check(&temp_dir, idl, "plain", &[]);
+
+    let instructions = fs::read_to_string(crate_dir.join("src/instructions.rs")).unwrap();
+    assert!(instructions.contains("pub struct TransferKeysResolver"));
+    assert!(instructions.contains("pub fn resolve_with_program_id(\n        self,"));
+
+    // The unpublished runtime crate is a path dependency on this checkout, absolute
+    // here since the temporary directory is outside it
+    let cargo_toml = fs::read_to_string(crate_dir.join("Cargo.toml")).unwrap();
+    let runtime_dep = cargo_toml
+        .lines()
+        .find(|line| line.starts_with("solana-idl-codegen-runtime = "))
+        .unwrap();
+    let path = runtime_dep
+        .strip_prefix("solana-idl-codegen-runtime = { path = \"")
+        .and_then(|rest| rest.strip_suffix("\" }"))
+        .unwrap();
+    assert!(Path::new(path).is_absolute(), "{}", runtime_dep);
+    assert_eq!(
+        Path::new(path).canonicalize().unwrap(),
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("runtime")
+            .canonicalize()
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_builder_defaults_option_args_to_none() {
+    let idl = r#"{
+        "address": "11111111111111111111111111111111",
+        "metadata": { "name": "builder", "version": "0.1.0", "spec": "0.1.0" },
+        "instructions": [{
+            "name": "swap",
+            "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+            "accounts": [{ "name": "pool", "writable": true }],
+            "args": [
+                { "name": "amount", "type": "u64" },
+                { "name": "type", "type": { "option": "u8" } }
+            ]
+        }]
+    }"#;
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let crate_dir = generate_and_check(&temp_dir, idl, "builder", &[]);
+    run_generated_test(
+        &crate_dir,
+        r#"
+use builder::instructions::*;
+use builder::solana_program::pubkey::Pubkey;
+
+#[test]
+fn option_args_are_not_required() {
+    let pool = Pubkey::new_unique();
+    let ix = SwapBuilder::new().pool(pool).amount(5).build().unwrap();
+    let expected = swap_ix(
+        SwapKeys { pool },
+        SwapIxArgs { amount: 5, r#type: None },
+    )
+    .unwrap();
+    assert_eq!(ix, expected);
+
+    let ix = SwapBuilder::new()
+        .pool(pool)
+        .amount(5)
+        .r#type(Some(2))
+        .build()
+        .unwrap();
+    assert_eq!(ix.data.last(), Some(&2));
+
+    match SwapBuilder::new().pool(pool).build() {
+        Err(BuildError::MissingArgs(missing)) => assert_eq!(missing, ["amount"]),
+        other => panic!("expected MissingArgs, got {:?}", other),
+    }
+}
+"#,
+    );
+}
+
+#[test]
+fn test_anchor_output_compiles() {
+    // anchor-lang brings its own Solana crates; the generated code must use the same
+    // `Pubkey` and `AccountInfo`
+    let idl = r#"{
+        "address": "11111111111111111111111111111111",
+        "metadata": { "name": "vault", "version": "0.1.0", "spec": "0.1.0" },
+        "instructions": [{
+            "name": "deposit",
+            "discriminator": [1, 2, 3, 4, 5, 6, 7, 8],
+            "accounts": [
+                { "name": "vault", "writable": true },
+                { "name": "owner", "signer": true }
+            ],
+            "args": [{ "name": "amount", "type": "u64" }]
+        }],
+        "accounts": [{ "name": "Vault", "discriminator": [9, 9, 9, 9, 9, 9, 9, 9] }],
+        "events": [{ "name": "Deposited", "discriminator": [8, 8, 8, 8, 8, 8, 8, 8] }],
+        "types": [
+            {
+                "name": "Vault",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "owner", "type": "pubkey" },
+                        { "name": "balance", "type": "u64" }
+                    ]
+                }
+            },
+            {
+                "name": "Deposited",
+                "type": {
+                    "kind": "struct",
+                    "fields": [
+                        { "name": "vault", "type": "pubkey" },
+                        { "name": "amount", "type": "u64" }
+                    ]
+                }
+            }
+        ]
+    }"#;
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let crate_dir = generate_and_check(&temp_dir, idl, "vault", &["--anchor"]);
+    run_generated_test(
+        &crate_dir,
+        r#"
+use anchor_lang::{AccountDeserialize, Owner};
+use vault::accounts::Vault;
+use vault::solana_program::pubkey::Pubkey;
+
+#[test]
+fn anchor_account_round_trips() {
+    let owner: Pubkey = Vault::owner();
+    assert_eq!(owner, vault::ID);
+
+    let account = Vault { owner: Pubkey::new_unique(), balance: 7 };
+    let mut data = Vec::new();
+    account.serialize_with_discriminator(&mut data).unwrap();
+    let decoded = Vault::try_deserialize(&mut &data[..]).unwrap();
+    assert_eq!(decoded.balance, 7);
+}
+"#,
+    );
+}
@@ -244,7 +244,7 @@ fn test_lib_rs_structure() {
         // Check for serde helper if feature is present
         if content.contains("#[cfg(feature = \"serde\")]") {
             assert!(
-                content.contains("serialize_pubkey_as_string"),
+                content.contains("pub mod serde_pubkey"),
                 "{} lib.rs should have Pubkey serde helper",
                 crate_name
             );
@@ -495,6 +495,11 @@ fn test_cargo_toml_structure() {
             "{} should depend on num-traits",
             crate_name
         );
+        assert!(
+            content.contains("solana-idl-codegen-runtime"),
+            "{} should depend on solana-idl-codegen-runtime",
+            crate_name
+        );
 
         // Check for serde feature
         assert!(
@@ -528,7 +533,7 @@ fn test_pubkey_serde_serialization() {
         let lib_content = fs::read_to_string(&lib_path).expect("Failed to read lib.rs");
 
         // Check for Pubkey serialization helper
-        if lib_content.contains("serialize_pubkey_as_string") {
+        if lib_content.contains("pub mod serde_pubkey") {
             // Check that it's properly gated behind serde feature
             assert!(
                 lib_content.contains("#[cfg(feature = \"serde\")]"),
@@ -543,8 +548,7 @@ fn test_pubkey_serde_serialization() {
                     fs::read_to_string(&events_path).expect("Failed to read events.rs");
                 if events_content.contains("Pubkey") {
                     assert!(
-                        events_content
-                            .contains("serialize_with = \"crate::serialize_pubkey_as_string\""),
+                        events_content.contains("with = \"crate::serde_pubkey"),
                         "{} events should use Pubkey serialization helper",
                         crate_name
                     );
@@ -626,11 +630,10 @@ fn test_events_have_parsing_helpers() {
                 crate_name
             );
 
-            // Should have EventParseError
+            // Should re-export EventParseError
             assert!(
-                content.contains("enum EventParseError")
-                    || content.contains("pub enum EventParseError"),
-                "{} should have EventParseError enum",
+                content.contains("pub use solana_idl_codegen_runtime::EventParseError"),
+                "{} should re-export the runtime's EventParseError",
                 crate_name
             );
 
@@ -685,11 +688,10 @@ fn test_accounts_have_validation_helpers() {
 
         // Check for validation helpers if accounts have discriminators
         if content.contains("DISCRIMINATOR") {
-            // Should have ValidationError enum
+            // Should re-export ValidationError
             assert!(
-                content.contains("enum ValidationError")
-                    || content.contains("pub enum ValidationError"),
-                "{} should have ValidationError enum for account validation",
+                content.contains("pub use solana_idl_codegen_runtime::ValidationError"),
+                "{} should re-export the runtime's ValidationError",
                 crate_name
             );
 
@@ -136,17 +136,12 @@ fn test_discriminator_validation_rejects_invalid() {
 
         tested += 1;
 
-        // Verify error handling code exists
+        // Length and discriminator checks live in the runtime crate
         assert!(
-            content.contains("InvalidData") || content.contains("Invalid discriminator"),
-            "{} should have discriminator validation error handling",
-            crate_name
-        );
-
-        // Verify discriminator length check exists
-        assert!(
-            content.contains("data.len() < 8") || content.contains("data too short"),
-            "{} should check for minimum discriminator length",
+            content.contains("solana_idl_codegen_runtime::try_from_slice_with_discriminator")
+                || content
+                    .contains("solana_idl_codegen_runtime::pod_from_slice_with_discriminator"),
+            "{} should check the discriminator through the runtime crate",
             crate_name
         );
 